@@ -0,0 +1,775 @@
+//! Price alerts.
+//!
+//! An `AlertRule` describes a condition on one currency pair, such as `BTC/USD crosses 70000`,
+//! `BTC/USD moves 5% in 1h` or `BTC/USD spread exceeds 1%`. An `AlertEngine` evaluates its rules
+//! against incoming quotes and publishes an `AlertEvent` on a broadcast channel every time a rule
+//! fires, so any number of components can subscribe to the same alerts.
+//!
+//! Rules are edge triggered: a rule that fired stays quiet until its condition has cleared by the
+//! rule's hysteresis margin, and no rule fires twice within its cooldown. Time is taken from the
+//! quotes rather than the clock, so replaying recorded quotes produces the same alerts.
+
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::{broadcast, mpsc};
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::currency::CurrencyPair;
+use super::money::{ensure_currency, Money};
+use super::price::Quote;
+
+const DEFAULT_CHANNEL_CAPACITY: usize = 64;
+
+/// What an `AlertRule` watches for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// The price moves from one side of the level to the other.
+    Crosses(Money),
+    /// The price is at or above the level.
+    Above(Money),
+    /// The price is at or below the level.
+    Below(Money),
+    /// The price changes by at least `percent` percent, up or down, within the window.
+    Moves { percent: f64, window: Duration },
+    /// The bid/ask spread is at least the given percentage of the mid price. Quotes without a
+    /// bid and ask are ignored.
+    SpreadExceeds(f64),
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Condition::Crosses(level) => write!(f, "crosses {}", level.to_decimal_string()),
+            Condition::Above(level) => write!(f, "above {}", level.to_decimal_string()),
+            Condition::Below(level) => write!(f, "below {}", level.to_decimal_string()),
+            Condition::Moves { percent, window } => {
+                write!(f, "moves {}% in {}", percent, format_window(window))
+            }
+            Condition::SpreadExceeds(percent) => write!(f, "spread exceeds {}%", percent),
+        }
+    }
+}
+
+/// A named condition on a currency pair.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    name: Box<str>,
+    pair: CurrencyPair,
+    condition: Condition,
+    hysteresis: f64,
+    cooldown: Duration,
+}
+
+impl AlertRule {
+    pub fn new(name: &str, pair: CurrencyPair, condition: Condition) -> AlertRuleBuilder {
+        AlertRuleBuilder::new(name, pair, condition)
+    }
+
+    /// Parses a rule written as `PAIR crosses|above|below AMOUNT`, `PAIR moves PERCENT in WINDOW`
+    /// or `PAIR spread exceeds PERCENT`, e.g. `"BTC/USD moves 5% in 1h"`. Windows are a whole
+    /// number of seconds (`s`), minutes (`m`), hours (`h`) or days (`d`).
+    ///
+    /// Fails with `ErrorCode::Invalid` if the rule is not in one of these forms.
+    pub fn parse(name: &str, rule: &str) -> Result<AlertRuleBuilder, Error> {
+        let invalid = || {
+            Error::new("Invalid alert rule", ErrorCode::Invalid)
+                .with_meta(ErrorMeta::new().add("rule", rule).build())
+        };
+
+        let tokens: Vec<&str> = rule.split_whitespace().collect();
+        let (pair, condition) = tokens.split_first().ok_or_else(invalid)?;
+        let pair = CurrencyPair::parse(pair)?;
+        let level = |amount: &str| Money::parse(amount, pair.quote().clone());
+
+        let condition = match condition {
+            ["crosses", amount] => Condition::Crosses(level(amount)?),
+            ["above", amount] => Condition::Above(level(amount)?),
+            ["below", amount] => Condition::Below(level(amount)?),
+            ["moves", percent, "in", window] => Condition::Moves {
+                percent: parse_percent(percent).ok_or_else(invalid)?,
+                window: parse_window(window).ok_or_else(invalid)?,
+            },
+            ["spread", "exceeds", percent] => {
+                Condition::SpreadExceeds(parse_percent(percent).ok_or_else(invalid)?)
+            }
+            _ => return Err(invalid()),
+        };
+
+        Ok(Self::new(name, pair, condition))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    pub fn condition(&self) -> &Condition {
+        &self.condition
+    }
+
+    pub fn hysteresis(&self) -> f64 {
+        self.hysteresis
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+}
+
+impl Display for AlertRule {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{} {}", self.pair, self.condition)
+    }
+}
+
+pub struct AlertRuleBuilder {
+    name: Box<str>,
+    pair: CurrencyPair,
+    condition: Condition,
+    hysteresis: f64,
+    cooldown: Duration,
+}
+
+impl AlertRuleBuilder {
+    pub fn new(name: &str, pair: CurrencyPair, condition: Condition) -> Self {
+        Self {
+            name: name.into(),
+            pair,
+            condition,
+            hysteresis: 0.0,
+            cooldown: Duration::zero(),
+        }
+    }
+
+    /// How far, as a fraction of the threshold, the watched value has to move back before the
+    /// rule can fire again, e.g. `0.01` for 1%. Defaults to zero.
+    pub fn hysteresis(mut self, hysteresis: f64) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// The shortest time between two alerts of the rule. Defaults to zero.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Fails with `ErrorCode::Invalid` if a price level is not a positive amount of the pair's
+    /// quote currency, a percentage or window is not positive, or the hysteresis is outside
+    /// `0..1`.
+    pub fn build(self) -> Result<AlertRule, Error> {
+        let invalid = |reason: &str| {
+            Error::new(
+                format!("Invalid alert rule: {}", reason).as_str(),
+                ErrorCode::Invalid,
+            )
+            .with_meta(ErrorMeta::new().add("rule", &self.name).build())
+        };
+
+        match &self.condition {
+            Condition::Crosses(level) | Condition::Above(level) | Condition::Below(level) => {
+                ensure_currency(self.pair.quote(), level.currency())?;
+                if level.amount() <= 0 {
+                    return Err(invalid("level must be positive"));
+                }
+            }
+            Condition::Moves { percent, window } => {
+                if !(percent.is_finite() && *percent > 0.0) {
+                    return Err(invalid("percentage must be positive"));
+                }
+                if *window <= Duration::zero() {
+                    return Err(invalid("window must be positive"));
+                }
+            }
+            Condition::SpreadExceeds(percent) => {
+                if !(percent.is_finite() && *percent > 0.0) {
+                    return Err(invalid("percentage must be positive"));
+                }
+            }
+        }
+        if !(0.0..1.0).contains(&self.hysteresis) {
+            return Err(invalid("hysteresis must be between 0 and 1"));
+        }
+        if self.cooldown < Duration::zero() {
+            return Err(invalid("cooldown must not be negative"));
+        }
+
+        Ok(AlertRule {
+            name: self.name,
+            pair: self.pair,
+            condition: self.condition,
+            hysteresis: self.hysteresis,
+            cooldown: self.cooldown,
+        })
+    }
+}
+
+/// A rule firing on a quote.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    rule: Box<str>,
+    pair: CurrencyPair,
+    condition: Condition,
+    price: Money,
+    percent: Option<f64>,
+    source: Box<str>,
+    timestamp: DateTime<Utc>,
+}
+
+impl AlertEvent {
+    /// The name of the rule that fired.
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    pub fn condition(&self) -> &Condition {
+        &self.condition
+    }
+
+    /// The price of the quote that fired the rule.
+    pub fn price(&self) -> &Money {
+        &self.price
+    }
+
+    /// The signed price change of a `Moves` rule or the spread of a `SpreadExceeds` rule, in
+    /// percent.
+    pub fn percent(&self) -> Option<f64> {
+        self.percent
+    }
+
+    /// The source of the quote that fired the rule.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+}
+
+impl Display for AlertEvent {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} {} at {} ({})",
+            self.rule, self.pair, self.condition, self.price, self.source
+        )?;
+        if let Some(percent) = self.percent {
+            write!(f, " {:+.2}%", percent)?;
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates alert rules against quotes and publishes the alerts they fire.
+pub struct AlertEngine {
+    rules: Vec<RuleState>,
+    sender: broadcast::Sender<AlertEvent>,
+}
+
+impl AlertEngine {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CHANNEL_CAPACITY)
+    }
+
+    /// An engine whose subscribers can fall behind by up to `capacity` alerts before they start
+    /// missing the oldest ones.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            rules: Vec::new(),
+            sender,
+        }
+    }
+
+    /// A receiver for every alert fired from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<AlertEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Adds a rule.
+    ///
+    /// Fails with `ErrorCode::Conflict` if a rule with the same name is already registered.
+    pub fn add_rule(&mut self, rule: AlertRule) -> Result<(), Error> {
+        if self.rule(rule.name()).is_some() {
+            return Err(Error::new("Alert rule already exists", ErrorCode::Conflict)
+                .with_meta(ErrorMeta::new().add("rule", rule.name()).build()));
+        }
+
+        self.rules.push(RuleState::new(rule));
+        Ok(())
+    }
+
+    /// Removes a rule by name, returning it if it was registered.
+    pub fn remove_rule(&mut self, name: &str) -> Option<AlertRule> {
+        let index = self.rules.iter().position(|s| s.rule.name() == name)?;
+        Some(self.rules.remove(index).rule)
+    }
+
+    pub fn rule(&self, name: &str) -> Option<&AlertRule> {
+        self.rules
+            .iter()
+            .map(|state| &state.rule)
+            .find(|rule| rule.name() == name)
+    }
+
+    /// Every rule, in the order they were added.
+    pub fn rules(&self) -> impl Iterator<Item = &AlertRule> {
+        self.rules.iter().map(|state| &state.rule)
+    }
+
+    /// Evaluates every rule for the quote's pair, publishes the alerts that fire and returns them.
+    ///
+    /// Quotes older than the last quote a rule has seen are ignored by that rule.
+    pub fn evaluate(&mut self, quote: &Quote) -> Vec<AlertEvent> {
+        let events: Vec<AlertEvent> = self
+            .rules
+            .iter_mut()
+            .filter_map(|state| state.evaluate(quote))
+            .collect();
+
+        for event in &events {
+            // Sending only fails when nobody is subscribed, which is not an error for the engine.
+            let _ = self.sender.send(event.clone());
+        }
+        events
+    }
+
+    /// Evaluates quotes from the channel until it closes.
+    pub async fn run(mut self, mut quotes: mpsc::Receiver<Quote>) {
+        while let Some(quote) = quotes.recv().await {
+            self.evaluate(&quote);
+        }
+    }
+}
+
+impl Default for AlertEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where a rule's watched value stands relative to its threshold.
+enum Level {
+    /// The condition holds, with the percentage that triggered it if any.
+    Active(Option<f64>),
+    /// The condition does not hold but has not moved back past the hysteresis margin.
+    Band,
+    /// The condition has cleared and the rule can fire again.
+    Clear,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Above,
+    Below,
+}
+
+struct RuleState {
+    rule: AlertRule,
+    /// Set once the rule fires, until its condition clears.
+    latched: bool,
+    side: Option<Side>,
+    history: VecDeque<(DateTime<Utc>, i128)>,
+    last_seen: Option<DateTime<Utc>>,
+    last_fired: Option<DateTime<Utc>>,
+}
+
+impl RuleState {
+    fn new(rule: AlertRule) -> Self {
+        Self {
+            rule,
+            latched: false,
+            side: None,
+            history: VecDeque::new(),
+            last_seen: None,
+            last_fired: None,
+        }
+    }
+
+    fn evaluate(&mut self, quote: &Quote) -> Option<AlertEvent> {
+        if quote.pair() != &self.rule.pair || quote.price().currency() != self.rule.pair.quote() {
+            return None;
+        }
+
+        let now = quote.timestamp();
+        if self.last_seen.is_some_and(|seen| now < seen) {
+            return None;
+        }
+        self.last_seen = Some(now);
+
+        let level = self.level(quote)?;
+        let percent = match level {
+            Level::Active(percent) => percent,
+            Level::Band => return None,
+            Level::Clear => {
+                self.latched = false;
+                return None;
+            }
+        };
+
+        // A rule that is still cooling down stays unlatched, so a condition that still holds
+        // once the cooldown has passed fires then.
+        let cooling = self
+            .last_fired
+            .is_some_and(|fired| now - fired < self.rule.cooldown);
+        if self.latched || cooling {
+            return None;
+        }
+
+        self.latched = true;
+        self.last_fired = Some(now);
+        Some(AlertEvent {
+            rule: self.rule.name.clone(),
+            pair: self.rule.pair.clone(),
+            condition: self.rule.condition.clone(),
+            price: quote.price().clone(),
+            percent,
+            source: quote.source().into(),
+            timestamp: now,
+        })
+    }
+
+    /// Returns `None` if the quote says nothing about the condition.
+    fn level(&mut self, quote: &Quote) -> Option<Level> {
+        let hysteresis = self.rule.hysteresis;
+        let price = quote.price().amount() as f64;
+
+        let level = match &self.rule.condition {
+            Condition::Crosses(level) => {
+                let level = level.amount() as f64;
+                let side = if price > level * (1.0 + hysteresis) {
+                    Side::Above
+                } else if price < level * (1.0 - hysteresis) {
+                    Side::Below
+                } else {
+                    return Some(Level::Band);
+                };
+
+                let previous = self.side.replace(side);
+                match previous {
+                    Some(previous) if previous != side => Level::Active(None),
+                    _ => Level::Clear,
+                }
+            }
+            Condition::Above(level) => {
+                let level = level.amount() as f64;
+                if price >= level {
+                    Level::Active(None)
+                } else if price < level * (1.0 - hysteresis) {
+                    Level::Clear
+                } else {
+                    Level::Band
+                }
+            }
+            Condition::Below(level) => {
+                let level = level.amount() as f64;
+                if price <= level {
+                    Level::Active(None)
+                } else if price > level * (1.0 + hysteresis) {
+                    Level::Clear
+                } else {
+                    Level::Band
+                }
+            }
+            Condition::Moves { percent, window } => {
+                let now = quote.timestamp();
+                self.history.push_back((now, quote.price().amount()));
+                while self
+                    .history
+                    .front()
+                    .is_some_and(|(timestamp, _)| now - *timestamp > *window)
+                {
+                    self.history.pop_front();
+                }
+
+                let (_, base) = self.history.front()?;
+                if *base == 0 {
+                    return None;
+                }
+                let change = (price - *base as f64) / *base as f64 * 100.0;
+                threshold(change, change.abs(), *percent, hysteresis)
+            }
+            Condition::SpreadExceeds(percent) => {
+                let bid = quote.bid()?.amount() as f64;
+                let ask = quote.ask()?.amount() as f64;
+                let mid = (bid + ask) / 2.0;
+                if mid <= 0.0 {
+                    return None;
+                }
+                let spread = (ask - bid) / mid * 100.0;
+                threshold(spread, spread, *percent, hysteresis)
+            }
+        };
+
+        Some(level)
+    }
+}
+
+fn threshold(reported: f64, value: f64, limit: f64, hysteresis: f64) -> Level {
+    if value >= limit {
+        Level::Active(Some(reported))
+    } else if value < limit * (1.0 - hysteresis) {
+        Level::Clear
+    } else {
+        Level::Band
+    }
+}
+
+fn parse_percent(percent: &str) -> Option<f64> {
+    let percent: f64 = percent.strip_suffix('%').unwrap_or(percent).parse().ok()?;
+    (percent.is_finite() && percent > 0.0).then_some(percent)
+}
+
+fn parse_window(window: &str) -> Option<Duration> {
+    let split = window.len().checked_sub(1)?;
+    let (count, unit) = window.split_at(split);
+    let count: i64 = count.parse().ok()?;
+    match unit {
+        "s" => Duration::try_seconds(count),
+        "m" => Duration::try_minutes(count),
+        "h" => Duration::try_hours(count),
+        "d" => Duration::try_days(count),
+        _ => None,
+    }
+}
+
+fn format_window(window: &Duration) -> String {
+    let seconds = window.num_seconds();
+    match seconds {
+        s if s != 0 && s % 86_400 == 0 => format!("{}d", s / 86_400),
+        s if s != 0 && s % 3_600 == 0 => format!("{}h", s / 3_600),
+        s if s != 0 && s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::test_support::{pair, usd};
+
+    fn quote(price: i64, seconds: i64) -> Quote {
+        let start = Utc.with_ymd_and_hms(2023, 6, 1, 12, 0, 0).unwrap();
+        Quote::new(
+            "test",
+            pair(),
+            usd(&price.to_string()),
+            start + Duration::seconds(seconds),
+        )
+    }
+
+    fn fired(engine: &mut AlertEngine, prices: &[(i64, i64)]) -> Vec<i64> {
+        prices
+            .iter()
+            .filter(|(price, seconds)| !engine.evaluate(&quote(*price, *seconds)).is_empty())
+            .map(|(_, seconds)| *seconds)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let rule = AlertRule::parse("btc", "BTC/USD crosses 70000")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(rule.condition(), &Condition::Crosses(usd("70000")));
+        assert_eq!(rule.to_string(), "BTC/USD crosses 70000.00");
+
+        let rule = AlertRule::parse("btc", "BTC/USD moves 5% in 1h")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            rule.condition(),
+            &Condition::Moves {
+                percent: 5.0,
+                window: Duration::hours(1)
+            }
+        );
+        assert_eq!(rule.to_string(), "BTC/USD moves 5% in 1h");
+
+        let rule = AlertRule::parse("btc", "BTC/USD spread exceeds 1%")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(rule.condition(), &Condition::SpreadExceeds(1.0));
+
+        for invalid in [
+            "",
+            "BTC/USD",
+            "BTC/USD crosses",
+            "BTC/USD moves 5% in 1w",
+            "BTC/USD spread exceeds -1%",
+        ] {
+            let error = AlertRule::parse("btc", invalid).err().unwrap();
+            assert_eq!(error.code(), ErrorCode::Invalid, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_build_validates() {
+        let bitcoin = Money::from_minor(1, pair().base().clone());
+        let error = AlertRule::new("btc", pair(), Condition::Above(bitcoin))
+            .build()
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+
+        let error = AlertRule::new("btc", pair(), Condition::Above(usd("1")))
+            .hysteresis(1.5)
+            .build()
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+    }
+
+    #[test]
+    fn test_crossing_with_hysteresis() {
+        let mut engine = AlertEngine::new();
+        let rule = AlertRule::new("btc", pair(), Condition::Crosses(usd("70000")))
+            .hysteresis(0.01)
+            .build()
+            .unwrap();
+        engine.add_rule(rule).unwrap();
+
+        // Wobbling around the level inside the 1% band does not count as a crossing.
+        let events = fired(
+            &mut engine,
+            &[
+                (69_000, 0),
+                (70_100, 1),
+                (69_900, 2),
+                (70_800, 3),
+                (69_500, 4),
+                (71_000, 5),
+                (69_000, 6),
+            ],
+        );
+        assert_eq!(events, vec![3, 6]);
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let mut engine = AlertEngine::new();
+        let rule = AlertRule::new("btc", pair(), Condition::Above(usd("70000")))
+            .cooldown(Duration::seconds(60))
+            .build()
+            .unwrap();
+        engine.add_rule(rule).unwrap();
+
+        let events = fired(
+            &mut engine,
+            &[
+                (70_000, 0),
+                (70_500, 10),
+                (69_000, 20),
+                (70_100, 30),
+                (70_200, 70),
+                (70_300, 80),
+            ],
+        );
+        assert_eq!(events, vec![0, 70]);
+    }
+
+    #[test]
+    fn test_moves_within_window() {
+        let mut engine = AlertEngine::new();
+        let rule = AlertRule::parse("btc", "BTC/USD moves 5% in 1h")
+            .unwrap()
+            .build()
+            .unwrap();
+        engine.add_rule(rule).unwrap();
+
+        assert!(engine.evaluate(&quote(60_000, 0)).is_empty());
+        assert!(engine.evaluate(&quote(62_000, 1_800)).is_empty());
+
+        // The first quote has left the window, so the change is measured from 62000.
+        assert!(engine.evaluate(&quote(63_000, 3_700)).is_empty());
+
+        let events = engine.evaluate(&quote(58_800, 3_800));
+        assert_eq!(events.len(), 1);
+        let percent = events[0].percent().unwrap();
+        assert!((percent - -3_200.0 / 620.0).abs() < 1e-9, "{}", percent);
+    }
+
+    #[test]
+    fn test_spread() {
+        let mut engine = AlertEngine::new();
+        let rule = AlertRule::parse("btc", "BTC/USD spread exceeds 1%")
+            .unwrap()
+            .build()
+            .unwrap();
+        engine.add_rule(rule).unwrap();
+
+        assert!(engine.evaluate(&quote(70_000, 0)).is_empty());
+
+        let wide = quote(70_000, 1).with_bid_ask(usd("69500"), usd("70500"));
+        let events = engine.evaluate(&wide);
+        assert_eq!(events.len(), 1);
+        assert!(events[0]
+            .to_string()
+            .starts_with("btc: BTC/USD spread exceeds 1%"));
+
+        let narrow = quote(70_000, 2).with_bid_ask(usd("69990"), usd("70010"));
+        assert!(engine.evaluate(&narrow).is_empty());
+
+        let wide = quote(70_000, 3).with_bid_ask(usd("69500"), usd("70500"));
+        assert_eq!(engine.evaluate(&wide).len(), 1);
+    }
+
+    #[test]
+    fn test_rules() {
+        let mut engine = AlertEngine::new();
+        let rule = AlertRule::new("btc", pair(), Condition::Below(usd("60000")))
+            .build()
+            .unwrap();
+        engine.add_rule(rule.clone()).unwrap();
+
+        let error = engine.add_rule(rule).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Conflict);
+
+        let other = CurrencyPair::parse("BTC/EUR").unwrap();
+        let euros = Money::from_minor(100, other.quote().clone());
+        assert!(engine
+            .evaluate(&Quote::new("test", other, euros, Utc::now()))
+            .is_empty());
+
+        assert!(engine.remove_rule("btc").is_some());
+        assert_eq!(engine.rules().count(), 0);
+    }
+
+    #[macros::async_test]
+    async fn test_subscribers_receive_alerts() {
+        let mut engine = AlertEngine::new();
+        let rule = AlertRule::parse("btc", "BTC/USD above 70000")
+            .unwrap()
+            .build()
+            .unwrap();
+        engine.add_rule(rule).unwrap();
+
+        let mut first = engine.subscribe();
+        let mut second = engine.subscribe();
+
+        let (quotes, receiver) = mpsc::channel(8);
+        let task = tokio::spawn(engine.run(receiver));
+        quotes.send(quote(69_000, 0)).await.unwrap();
+        quotes.send(quote(71_000, 1)).await.unwrap();
+        drop(quotes);
+        task.await.unwrap();
+
+        let event = first.recv().await.unwrap();
+        assert_eq!(event.rule(), "btc");
+        assert_eq!(event.price(), &usd("71000"));
+        assert_eq!(second.recv().await.unwrap(), event);
+    }
+}
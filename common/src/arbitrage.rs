@@ -0,0 +1,524 @@
+//! Cross-exchange arbitrage.
+//!
+//! An `ArbitrageMonitor` keeps the latest quote from every venue quoting one currency pair and,
+//! for every ordered pair of venues, works out what buying a fixed quantity at one venue's ask
+//! and selling it at the other's bid would make after each venue's taker fee and the cost of
+//! withdrawing the asset from the buying venue. Spreads whose net return reaches the configured
+//! threshold are published as `Opportunity` events on a broadcast channel.
+//!
+//! `poll` feeds the monitor by fetching quotes from a set of providers at a fixed interval, so
+//! the two together can run for the lifetime of a process.
+
+pub mod opportunity;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinSet;
+use utils::adapters::http_client::HttpClientPool;
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::currency::CurrencyPair;
+use super::money::{ensure_currency, Money};
+use super::oracle::fetch;
+use super::price::Quote;
+use super::providers::PriceProvider;
+
+pub use opportunity::Opportunity;
+
+const DEFAULT_CHANNEL_CAPACITY: usize = 64;
+const DEFAULT_THRESHOLD: f64 = 0.001;
+const DEFAULT_MAX_AGE_SECONDS: i64 = 60;
+
+/// The costs of trading on and moving funds out of a venue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VenueFees {
+    taker: f64,
+    withdrawal: Option<Money>,
+}
+
+impl VenueFees {
+    /// Fees charged as a fraction of the notional value of a market order, e.g. `0.0026`.
+    pub fn new(taker: f64) -> Self {
+        Self {
+            taker,
+            withdrawal: None,
+        }
+    }
+
+    /// A flat amount of the asset withheld when withdrawing it from the venue.
+    pub fn withdrawal(mut self, withdrawal: Money) -> Self {
+        self.withdrawal = Some(withdrawal);
+        self
+    }
+
+    pub fn taker(&self) -> f64 {
+        self.taker
+    }
+
+    pub fn withdrawal_cost(&self) -> Option<&Money> {
+        self.withdrawal.as_ref()
+    }
+}
+
+impl Default for VenueFees {
+    /// No fees at all.
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
+pub struct ArbitrageMonitor {
+    pair: CurrencyPair,
+    fees: HashMap<Box<str>, VenueFees>,
+    default_fees: VenueFees,
+    quantity: Money,
+    threshold: f64,
+    max_age: chrono::Duration,
+    quotes: HashMap<Box<str>, Quote>,
+    sender: broadcast::Sender<Opportunity>,
+}
+
+impl ArbitrageMonitor {
+    pub fn new(pair: CurrencyPair) -> ArbitrageMonitorBuilder {
+        ArbitrageMonitorBuilder::new(pair)
+    }
+
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    /// A receiver for every opportunity found from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Opportunity> {
+        self.sender.subscribe()
+    }
+
+    /// The latest usable quote of every venue.
+    pub fn quotes(&self) -> impl Iterator<Item = &Quote> {
+        self.quotes.values()
+    }
+
+    /// Records a venue's latest quote, publishes the opportunities at or above the threshold and
+    /// returns them, best first.
+    ///
+    /// Quotes for other pairs and quotes older than the venue's current one are ignored.
+    pub fn update(&mut self, quote: Quote) -> Vec<Opportunity> {
+        self.update_at(quote, Utc::now())
+    }
+
+    /// The net result of every buy and sell venue combination among the fresh quotes, whatever
+    /// the threshold, best first.
+    ///
+    /// Quotes older than the maximum age are left out.
+    pub fn spreads(&self) -> Vec<Opportunity> {
+        self.spreads_at(Utc::now())
+    }
+
+    /// Evaluates quotes from the channel until it closes.
+    pub async fn run(mut self, mut quotes: mpsc::Receiver<Quote>) {
+        while let Some(quote) = quotes.recv().await {
+            self.update(quote);
+        }
+    }
+
+    fn update_at(&mut self, quote: Quote, now: DateTime<Utc>) -> Vec<Opportunity> {
+        if quote.pair() != &self.pair || quote.price().currency() != self.pair.quote() {
+            return Vec::new();
+        }
+        if let Some(current) = self.quotes.get(quote.source()) {
+            if current.timestamp() > quote.timestamp() {
+                return Vec::new();
+            }
+        }
+        self.quotes.insert(quote.source().into(), quote);
+
+        let opportunities: Vec<Opportunity> = self
+            .spreads_at(now)
+            .into_iter()
+            .filter(|opportunity| opportunity.net_spread() >= self.threshold)
+            .collect();
+        for opportunity in &opportunities {
+            // Sending only fails when nobody is subscribed, which is not an error for the monitor.
+            let _ = self.sender.send(opportunity.clone());
+        }
+        opportunities
+    }
+
+    fn spreads_at(&self, now: DateTime<Utc>) -> Vec<Opportunity> {
+        let mut fresh: Vec<&Quote> = self
+            .quotes
+            .values()
+            .filter(|quote| now - quote.timestamp() <= self.max_age)
+            .collect();
+        fresh.sort_by(|a, b| a.source().cmp(b.source()));
+
+        let mut spreads = Vec::new();
+        for buy in &fresh {
+            for sell in &fresh {
+                if buy.source() == sell.source() {
+                    continue;
+                }
+                if let Some(opportunity) = self.spread(buy, sell) {
+                    spreads.push(opportunity);
+                }
+            }
+        }
+        spreads.sort_by(|a, b| b.net_spread().total_cmp(&a.net_spread()));
+        spreads
+    }
+
+    fn fees(&self, venue: &str) -> &VenueFees {
+        self.fees.get(venue).unwrap_or(&self.default_fees)
+    }
+
+    fn spread(&self, buy: &Quote, sell: &Quote) -> Option<Opportunity> {
+        let ask = buy.ask().unwrap_or(buy.price());
+        let bid = sell.bid().unwrap_or(sell.price());
+        let buy_fees = self.fees(buy.source());
+        let sell_fees = self.fees(sell.source());
+        let scale = 10f64.powi(self.pair.base().decimal_places() as i32);
+
+        let quantity = self.quantity.amount() as f64;
+        let withdrawal = buy_fees.withdrawal.as_ref().map_or(0, |w| w.amount()) as f64;
+        let delivered = quantity - withdrawal;
+        if delivered <= 0.0 || ask.amount() <= 0 {
+            return None;
+        }
+
+        let cost = quantity * ask.amount() as f64 / scale * (1.0 + buy_fees.taker);
+        let proceeds = delivered * bid.amount() as f64 / scale * (1.0 - sell_fees.taker);
+        let net = proceeds - cost;
+
+        Some(Opportunity::new(
+            self.pair.clone(),
+            buy.source(),
+            sell.source(),
+            ask.clone(),
+            bid.clone(),
+            self.quantity.clone(),
+            Money::from_minor(net.round() as i128, self.pair.quote().clone()),
+            net / cost,
+            buy.timestamp().max(sell.timestamp()),
+        ))
+    }
+}
+
+pub struct ArbitrageMonitorBuilder {
+    pair: CurrencyPair,
+    fees: HashMap<Box<str>, VenueFees>,
+    default_fees: VenueFees,
+    quantity: Option<Money>,
+    threshold: f64,
+    max_age: chrono::Duration,
+    capacity: usize,
+}
+
+impl ArbitrageMonitorBuilder {
+    pub fn new(pair: CurrencyPair) -> Self {
+        Self {
+            pair,
+            fees: HashMap::new(),
+            default_fees: VenueFees::default(),
+            quantity: None,
+            threshold: DEFAULT_THRESHOLD,
+            max_age: chrono::Duration::seconds(DEFAULT_MAX_AGE_SECONDS),
+            capacity: DEFAULT_CHANNEL_CAPACITY,
+        }
+    }
+
+    /// The fees of a venue, by provider name.
+    pub fn venue(mut self, name: &str, fees: VenueFees) -> Self {
+        self.fees.insert(name.into(), fees);
+        self
+    }
+
+    /// The fees of venues without their own. Defaults to none.
+    pub fn default_fees(mut self, fees: VenueFees) -> Self {
+        self.default_fees = fees;
+        self
+    }
+
+    /// The amount of the base currency each opportunity buys, which spreads the flat withdrawal
+    /// cost. Defaults to one whole unit.
+    pub fn quantity(mut self, quantity: Money) -> Self {
+        self.quantity = Some(quantity);
+        self
+    }
+
+    /// The smallest net spread, as a fraction of the purchase cost, that is published. Defaults
+    /// to `0.001` (0.1%).
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Quotes older than this are not compared. Defaults to one minute.
+    pub fn max_age(mut self, max_age: chrono::Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// How many opportunities subscribers can fall behind by before they start missing the
+    /// oldest ones.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Fails with `ErrorCode::Invalid` if the quantity or a withdrawal cost is not a positive
+    /// amount of the base currency, or a taker fee is outside `0..1`.
+    pub fn build(self) -> Result<ArbitrageMonitor, Error> {
+        let base = self.pair.base();
+        let quantity = self
+            .quantity
+            .unwrap_or_else(|| Money::from_minor(10i128.pow(base.decimal_places()), base.clone()));
+        ensure_currency(base, quantity.currency())?;
+        if quantity.amount() <= 0 {
+            return Err(invalid("quantity", &quantity.to_decimal_string()));
+        }
+
+        let venues = self
+            .fees
+            .iter()
+            .map(|(name, fees)| (name.as_ref(), fees))
+            .chain(std::iter::once(("default", &self.default_fees)));
+        for (venue, fees) in venues {
+            if !(0.0..1.0).contains(&fees.taker) {
+                return Err(
+                    Error::new("Invalid taker fee", ErrorCode::Invalid).with_meta(
+                        ErrorMeta::new()
+                            .add("venue", venue)
+                            .add("taker", fees.taker.to_string().as_str())
+                            .build(),
+                    ),
+                );
+            }
+            if let Some(withdrawal) = &fees.withdrawal {
+                ensure_currency(base, withdrawal.currency())?;
+                if withdrawal.is_negative() {
+                    return Err(invalid("withdrawal", &withdrawal.to_decimal_string()));
+                }
+            }
+        }
+
+        let (sender, _) = broadcast::channel(self.capacity.max(1));
+        Ok(ArbitrageMonitor {
+            pair: self.pair,
+            fees: self.fees,
+            default_fees: self.default_fees,
+            quantity,
+            threshold: self.threshold,
+            max_age: self.max_age,
+            quotes: HashMap::new(),
+            sender,
+        })
+    }
+}
+
+/// Fetches a quote from every provider every `interval` and sends the ones that arrive within
+/// `timeout` to the channel, until the receiving end is closed.
+pub async fn poll(
+    pool: Arc<Mutex<HttpClientPool>>,
+    providers: Vec<Arc<dyn PriceProvider>>,
+    interval: Duration,
+    timeout: Duration,
+    quotes: mpsc::Sender<Quote>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    while !quotes.is_closed() {
+        ticker.tick().await;
+
+        let mut tasks = JoinSet::new();
+        for provider in &providers {
+            let pool = pool.clone();
+            let provider = provider.clone();
+            tasks.spawn(async move {
+                tokio::time::timeout(timeout, fetch(pool, provider.as_ref())).await
+            });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok(Ok(Ok(quote))) = joined {
+                if quotes.send(quote).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn invalid(setting: &str, value: &str) -> Error {
+    Error::new("Invalid arbitrage setting", ErrorCode::Invalid).with_meta(
+        ErrorMeta::new()
+            .add("setting", setting)
+            .add("value", value)
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::test_support::{btc, pair, usd};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    fn quote(source: &str, bid: &str, ask: &str, age_seconds: i64) -> Quote {
+        quote_at(
+            source,
+            bid,
+            ask,
+            now() - chrono::Duration::seconds(age_seconds),
+        )
+    }
+
+    fn quote_at(source: &str, bid: &str, ask: &str, timestamp: DateTime<Utc>) -> Quote {
+        Quote::new(source, pair(), usd(bid), timestamp).with_bid_ask(usd(bid), usd(ask))
+    }
+
+    #[test]
+    fn test_spreads_net_of_fees() {
+        let mut monitor = ArbitrageMonitor::new(pair())
+            .venue("cheap", VenueFees::new(0.001))
+            .venue("dear", VenueFees::new(0.002))
+            .threshold(0.0)
+            .build()
+            .unwrap();
+        assert!(monitor
+            .update_at(quote("cheap", "29990", "30000", 0), now())
+            .is_empty());
+        let opportunities = monitor.update_at(quote("dear", "30300", "30310", 0), now());
+
+        assert_eq!(opportunities.len(), 1);
+        let best = &opportunities[0];
+        assert_eq!(best.buy_venue(), "cheap");
+        assert_eq!(best.sell_venue(), "dear");
+        assert_eq!(best.buy_price(), &usd("30000"));
+        assert_eq!(best.sell_price(), &usd("30300"));
+        // 30300 * 0.998 - 30000 * 1.001
+        assert_eq!(best.net_profit(), &usd("209.40"));
+        assert!((best.net_spread() - 209.4 / 30_030.0).abs() < 1e-12);
+
+        let spreads = monitor.spreads_at(now());
+        assert_eq!(spreads.len(), 2);
+        assert!(spreads[1].net_profit().is_negative());
+    }
+
+    #[test]
+    fn test_withdrawal_cost_and_threshold() {
+        let fees = VenueFees::new(0.001).withdrawal(btc("0.005"));
+        let mut monitor = ArbitrageMonitor::new(pair())
+            .venue("a", fees)
+            .default_fees(VenueFees::new(0.001))
+            .threshold(0.001)
+            .build()
+            .unwrap();
+        monitor.update_at(quote("a", "29990", "30000", 0), now());
+
+        // The 0.5% withdrawal cost eats a 0.8% gross spread.
+        assert!(monitor
+            .update_at(quote("b", "30240", "30250", 0), now())
+            .is_empty());
+        let spread = &monitor.spreads_at(now())[0];
+        assert_eq!(spread.buy_venue(), "a");
+        assert!(spread.net_spread() < 0.001);
+
+        // Bought with a large enough quantity the withdrawal cost no longer matters.
+        let mut monitor = ArbitrageMonitor::new(pair())
+            .venue("a", VenueFees::new(0.001).withdrawal(btc("0.005")))
+            .default_fees(VenueFees::new(0.001))
+            .quantity(btc("10"))
+            .build()
+            .unwrap();
+        monitor.update_at(quote("a", "29990", "30000", 0), now());
+        assert_eq!(
+            monitor
+                .update_at(quote("b", "30240", "30250", 0), now())
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_ignores_stale_and_foreign_quotes() {
+        let mut monitor = ArbitrageMonitor::new(pair())
+            .max_age(chrono::Duration::seconds(30))
+            .build()
+            .unwrap();
+        monitor.update_at(quote("old", "29000", "29010", 120), now());
+        assert!(monitor
+            .update_at(quote("new", "30000", "30010", 0), now())
+            .is_empty());
+
+        let euros = CurrencyPair::parse("BTC/EUR").unwrap();
+        let foreign = Quote::new(
+            "eur",
+            euros.clone(),
+            Money::parse("1", euros.quote().clone()).unwrap(),
+            now(),
+        );
+        assert!(monitor.update_at(foreign, now()).is_empty());
+        assert_eq!(monitor.quotes().count(), 2);
+
+        // Quotes that are equally old are stale all the same: age is measured against the clock.
+        let mut monitor = ArbitrageMonitor::new(pair())
+            .max_age(chrono::Duration::seconds(30))
+            .threshold(0.0)
+            .build()
+            .unwrap();
+        monitor.update_at(quote("a", "29990", "30000", 120), now());
+        assert!(monitor
+            .update_at(quote("b", "30300", "30310", 120), now())
+            .is_empty());
+        assert_eq!(
+            monitor
+                .spreads_at(now() - chrono::Duration::seconds(120))
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_settings() {
+        let error = ArbitrageMonitor::new(pair())
+            .venue("a", VenueFees::new(1.5))
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("venue"), Some("a"));
+
+        let result = ArbitrageMonitor::new(pair()).quantity(usd("1")).build();
+        assert!(result.is_err());
+    }
+
+    #[macros::async_test]
+    async fn test_run_publishes_opportunities() {
+        let monitor = ArbitrageMonitor::new(pair()).build().unwrap();
+        let mut opportunities = monitor.subscribe();
+        let (sender, receiver) = mpsc::channel(4);
+        let task = tokio::spawn(monitor.run(receiver));
+
+        let now = Utc::now();
+        sender
+            .send(quote_at("a", "29990", "30000", now))
+            .await
+            .unwrap();
+        sender
+            .send(quote_at("b", "30300", "30310", now))
+            .await
+            .unwrap();
+        let opportunity = opportunities.recv().await.unwrap();
+        assert_eq!(opportunity.buy_venue(), "a");
+        assert_eq!(opportunity.net_profit(), &usd("300"));
+
+        drop(sender);
+        task.await.unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt::{Display, Formatter};
+
+use chrono::{DateTime, Utc};
+
+use crate::currency::CurrencyPair;
+use crate::money::Money;
+
+/// Buying on one venue and selling on another, with the result after fees and withdrawal costs.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Opportunity {
+    pair: CurrencyPair,
+    buy_venue: Box<str>,
+    sell_venue: Box<str>,
+    buy_price: Money,
+    sell_price: Money,
+    quantity: Money,
+    net_profit: Money,
+    net_spread: f64,
+    timestamp: DateTime<Utc>,
+}
+
+impl Opportunity {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        pair: CurrencyPair,
+        buy_venue: &str,
+        sell_venue: &str,
+        buy_price: Money,
+        sell_price: Money,
+        quantity: Money,
+        net_profit: Money,
+        net_spread: f64,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            pair,
+            buy_venue: buy_venue.into(),
+            sell_venue: sell_venue.into(),
+            buy_price,
+            sell_price,
+            quantity,
+            net_profit,
+            net_spread,
+            timestamp,
+        }
+    }
+
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    pub fn buy_venue(&self) -> &str {
+        &self.buy_venue
+    }
+
+    pub fn sell_venue(&self) -> &str {
+        &self.sell_venue
+    }
+
+    /// The ask on the buying venue, or its last price if it did not quote one.
+    pub fn buy_price(&self) -> &Money {
+        &self.buy_price
+    }
+
+    /// The bid on the selling venue, or its last price if it did not quote one.
+    pub fn sell_price(&self) -> &Money {
+        &self.sell_price
+    }
+
+    /// The amount of the base currency bought.
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    /// Sale proceeds less the purchase cost, after both taker fees and the withdrawal cost.
+    pub fn net_profit(&self) -> &Money {
+        &self.net_profit
+    }
+
+    /// The net profit as a fraction of the purchase cost, e.g. `0.004` for 0.4%.
+    pub fn net_spread(&self) -> f64 {
+        self.net_spread
+    }
+
+    /// The time of the newer of the two quotes.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+}
+
+impl Display for Opportunity {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: buy on {} at {}, sell on {} at {}, net {} ({:.3}%)",
+            self.pair,
+            self.buy_venue,
+            self.buy_price.to_decimal_string(),
+            self.sell_venue,
+            self.sell_price.to_decimal_string(),
+            self.net_profit.to_decimal_string(),
+            self.net_spread * 100.0
+        )
+    }
+}
@@ -0,0 +1,528 @@
+//! Strategy backtesting.
+//!
+//! A `Backtest` replays historical candles of a currency pair through a `Strategy`. After each
+//! candle closes the strategy sees it together with the simulated `Account` and returns orders,
+//! which are filled against the next candle: market orders at its open moved against the trader
+//! by the slippage, limit orders at their limit or a better open if the candle reaches it. Orders
+//! that do not fill expire. Every fill pays a fee proportional to its notional value.
+//!
+//! The `BacktestReport` holds the equity curve marked at every close, the list of trades, and
+//! the total return, maximum drawdown and Sharpe ratio derived from them.
+
+pub mod order;
+pub mod report;
+pub mod side;
+pub mod strategy;
+pub mod trade;
+
+use std::cmp::Ordering;
+
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::currency::{Currency, CurrencyPair};
+use super::money::{
+    apply_rate, cmp_products, ensure_currency, from_wide, mul_div_round, out_of_range, Money,
+};
+use super::price::{Candle, PricePoint, PriceSeries};
+
+pub use order::{Order, OrderSize};
+pub use report::BacktestReport;
+pub use side::Side;
+pub use strategy::{Account, SmaCrossover, Strategy};
+pub use trade::Trade;
+
+const DEFAULT_FEE_RATE: f64 = 0.001;
+const DEFAULT_PERIODS_PER_YEAR: f64 = 365.0;
+
+/// What happened to an order at the candle after it was placed.
+enum Fill {
+    Filled(Trade),
+    /// A limit order whose price was not reached.
+    Expired,
+    /// An order that could not be paid for or covered by holdings.
+    Rejected,
+}
+
+pub struct Backtest {
+    pair: CurrencyPair,
+    initial_cash: Money,
+    fee_rate: f64,
+    slippage: f64,
+    periods_per_year: f64,
+}
+
+impl Backtest {
+    pub fn new(pair: CurrencyPair, initial_cash: Money) -> BacktestBuilder {
+        BacktestBuilder::new(pair, initial_cash)
+    }
+
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    /// Replays the candles, oldest first, through the strategy.
+    ///
+    /// Fails with `ErrorCode::Invalid` if a candle or order is in the wrong currency or the
+    /// candles are out of order, and with any error the strategy returns.
+    pub fn run(
+        &self,
+        candles: &[Candle],
+        strategy: &mut dyn Strategy,
+    ) -> Result<BacktestReport, Error> {
+        let asset = self.pair.base();
+        let quote = self.pair.quote();
+        let mut cash = self.initial_cash.amount();
+        let mut holdings = 0;
+        let mut equity = PriceSeries::new(quote.clone());
+        let mut trades = Vec::new();
+        let mut rejected = 0;
+        let mut pending: Vec<Order> = Vec::new();
+
+        for candle in candles {
+            ensure_currency(quote, candle.close().currency())?;
+
+            for order in pending.drain(..) {
+                match self.fill(&order, candle, &mut cash, &mut holdings)? {
+                    Fill::Filled(trade) => trades.push(trade),
+                    Fill::Expired => {}
+                    Fill::Rejected => rejected += 1,
+                }
+            }
+
+            let value = value(
+                holdings,
+                candle.close().amount(),
+                asset.decimal_places(),
+                quote,
+            )?;
+            let total = cash
+                .checked_add(value)
+                .ok_or_else(|| out_of_range("equity", quote))?;
+            let total = from_wide(total, quote)?;
+            equity.push(PricePoint::new(candle.timestamp(), total.clone()))?;
+
+            let account = Account::new(
+                Money::from_minor(cash, quote.clone()),
+                Money::from_minor(holdings, asset.clone()),
+                total,
+            );
+            pending = strategy.on_candle(candle, &account)?;
+        }
+
+        Ok(BacktestReport::new(
+            self.pair.clone(),
+            self.initial_cash.clone(),
+            equity,
+            trades,
+            rejected,
+            self.periods_per_year,
+        ))
+    }
+
+    fn fill(
+        &self,
+        order: &Order,
+        candle: &Candle,
+        cash: &mut i128,
+        holdings: &mut i128,
+    ) -> Result<Fill, Error> {
+        let asset = self.pair.base();
+        let quote = self.pair.quote();
+        let decimals = asset.decimal_places();
+        let open = candle.open().amount();
+
+        let price = match (order.side(), order.limit()) {
+            (Side::Buy, None) => (open as f64 * (1.0 + self.slippage)).round() as i128,
+            (Side::Sell, None) => (open as f64 * (1.0 - self.slippage)).round() as i128,
+            (Side::Buy, Some(limit)) => {
+                ensure_currency(quote, limit.currency())?;
+                if candle.low().amount() > limit.amount() {
+                    return Ok(Fill::Expired);
+                }
+                open.min(limit.amount())
+            }
+            (Side::Sell, Some(limit)) => {
+                ensure_currency(quote, limit.currency())?;
+                if candle.high().amount() < limit.amount() {
+                    return Ok(Fill::Expired);
+                }
+                open.max(limit.amount())
+            }
+        };
+        if price <= 0 {
+            return Ok(Fill::Rejected);
+        }
+
+        let quantity = match (order.side(), order.size()) {
+            (_, OrderSize::Quantity(quantity)) => {
+                ensure_currency(asset, quantity.currency())?;
+                quantity.amount()
+            }
+            (Side::Buy, OrderSize::Value(budget)) => {
+                ensure_currency(quote, budget.currency())?;
+                self.affordable(budget.amount(), price, decimals, asset)?
+            }
+            (Side::Sell, OrderSize::Value(target)) => {
+                ensure_currency(quote, target.currency())?;
+                let scale = 10i128.pow(decimals);
+                target
+                    .amount()
+                    .checked_mul(scale)
+                    .ok_or_else(|| out_of_range("quantity", asset))?
+                    / price
+            }
+            (Side::Buy, OrderSize::All) => self.affordable(*cash, price, decimals, asset)?,
+            (Side::Sell, OrderSize::All) => *holdings,
+        };
+        if quantity <= 0 {
+            return Ok(Fill::Rejected);
+        }
+
+        let notional = value(quantity, price, decimals, quote)?;
+        let fee = self.fee(notional, quote)?;
+        let cash_out_of_range = || out_of_range("cash", quote);
+        match order.side() {
+            Side::Buy => {
+                let cost = notional.checked_add(fee).ok_or_else(cash_out_of_range)?;
+                if cost > *cash {
+                    return Ok(Fill::Rejected);
+                }
+                *holdings = holdings
+                    .checked_add(quantity)
+                    .ok_or_else(|| out_of_range("holdings", asset))?;
+                *cash -= cost;
+            }
+            Side::Sell if quantity > *holdings => return Ok(Fill::Rejected),
+            Side::Sell => {
+                *cash = notional
+                    .checked_sub(fee)
+                    .and_then(|proceeds| cash.checked_add(proceeds))
+                    .ok_or_else(cash_out_of_range)?;
+                *holdings -= quantity;
+            }
+        }
+
+        Ok(Fill::Filled(Trade::new(
+            candle.timestamp(),
+            order.side(),
+            Money::from_minor(quantity, asset.clone()),
+            Money::from_minor(price, quote.clone()),
+            from_wide(notional, quote)?,
+            from_wide(fee, quote)?,
+        )))
+    }
+
+    /// The largest quantity whose cost at `price`, fee included, fits in `budget`.
+    fn affordable(
+        &self,
+        budget: i128,
+        price: i128,
+        decimals: u32,
+        asset: &Currency,
+    ) -> Result<i128, Error> {
+        let scale = 10i128.pow(decimals);
+        // A cost out of range is more than any budget.
+        let affords = |quantity| {
+            let notional = mul_div_round(quantity, price, scale);
+            notional
+                .and_then(|notional| notional.checked_add(apply_rate(notional, self.fee_rate)?))
+                .is_some_and(|cost| cost <= budget)
+        };
+
+        // Without the fee the budget buys at most this much, so the answer is found in at most
+        // 128 halvings.
+        let mut low = 0;
+        let mut high = mul_div_round(budget, scale, price)
+            .ok_or_else(|| out_of_range("quantity", asset))?
+            .max(0);
+        if cmp_products(high, price, budget, scale) == Ordering::Greater {
+            high -= 1;
+        }
+        while low < high {
+            let middle = low + (high - low + 1) / 2;
+            if affords(middle) {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        Ok(low)
+    }
+
+    fn fee(&self, notional: i128, quote: &Currency) -> Result<i128, Error> {
+        apply_rate(notional, self.fee_rate).ok_or_else(|| out_of_range("fee", quote))
+    }
+}
+
+pub struct BacktestBuilder {
+    pair: CurrencyPair,
+    initial_cash: Money,
+    fee_rate: f64,
+    slippage: f64,
+    periods_per_year: f64,
+}
+
+impl BacktestBuilder {
+    pub fn new(pair: CurrencyPair, initial_cash: Money) -> Self {
+        Self {
+            pair,
+            initial_cash,
+            fee_rate: DEFAULT_FEE_RATE,
+            slippage: 0.0,
+            periods_per_year: DEFAULT_PERIODS_PER_YEAR,
+        }
+    }
+
+    /// The fee charged on every fill as a fraction of its notional value. Defaults to `0.001`
+    /// (0.1%).
+    pub fn fee_rate(mut self, fee_rate: f64) -> Self {
+        self.fee_rate = fee_rate;
+        self
+    }
+
+    /// How far market orders fill from the open against the trader, as a fraction of the
+    /// price. Defaults to zero.
+    pub fn slippage(mut self, slippage: f64) -> Self {
+        self.slippage = slippage;
+        self
+    }
+
+    /// The number of candles in a year, used to annualize the Sharpe ratio. Defaults to 365 for
+    /// daily candles.
+    pub fn periods_per_year(mut self, periods_per_year: f64) -> Self {
+        self.periods_per_year = periods_per_year;
+        self
+    }
+
+    /// Fails with `ErrorCode::Invalid` if the initial cash is not a non-negative amount of the
+    /// pair's quote currency or a rate is outside `0..1`.
+    pub fn build(self) -> Result<Backtest, Error> {
+        ensure_currency(self.pair.quote(), self.initial_cash.currency())?;
+
+        let invalid = |setting: &str, value: f64| {
+            Error::new("Invalid backtest setting", ErrorCode::Invalid).with_meta(
+                ErrorMeta::new()
+                    .add("setting", setting)
+                    .add("value", value.to_string().as_str())
+                    .build(),
+            )
+        };
+        if self.initial_cash.is_negative() {
+            return Err(invalid("initial_cash", self.initial_cash.amount() as f64));
+        }
+        if !(0.0..1.0).contains(&self.fee_rate) {
+            return Err(invalid("fee_rate", self.fee_rate));
+        }
+        if !(0.0..1.0).contains(&self.slippage) {
+            return Err(invalid("slippage", self.slippage));
+        }
+        if !(self.periods_per_year.is_finite() && self.periods_per_year > 0.0) {
+            return Err(invalid("periods_per_year", self.periods_per_year));
+        }
+
+        Ok(Backtest {
+            pair: self.pair,
+            initial_cash: self.initial_cash,
+            fee_rate: self.fee_rate,
+            slippage: self.slippage,
+            periods_per_year: self.periods_per_year,
+        })
+    }
+}
+
+/// The value in quote minor units of `quantity` asset minor units at `price` per whole unit.
+fn value(quantity: i128, price: i128, decimals: u32, quote: &Currency) -> Result<i128, Error> {
+    mul_div_round(quantity, price, 10i128.pow(decimals)).ok_or_else(|| out_of_range("value", quote))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use super::*;
+    use crate::test_support::{btc, pair, usd};
+
+    fn day(day: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap() + Duration::days(day)
+    }
+
+    fn candle(day_: i64, open: i128, high: i128, low: i128, close: i128) -> Candle {
+        Candle::new(
+            day(day_),
+            usd(&open.to_string()),
+            usd(&high.to_string()),
+            usd(&low.to_string()),
+            usd(&close.to_string()),
+        )
+        .unwrap()
+    }
+
+    fn flat(prices: &[i128]) -> Vec<Candle> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(day, price)| candle(day as i64, *price, *price, *price, *price))
+            .collect()
+    }
+
+    /// Places scripted orders after the candle with the same index.
+    struct Script(Vec<Vec<Order>>);
+
+    impl Strategy for Script {
+        fn on_candle(&mut self, _: &Candle, _: &Account) -> Result<Vec<Order>, Error> {
+            Ok(if self.0.is_empty() {
+                Vec::new()
+            } else {
+                self.0.remove(0)
+            })
+        }
+    }
+
+    #[test]
+    fn test_buy_and_hold() {
+        let backtest = Backtest::new(pair(), usd("1000"))
+            .fee_rate(0.0)
+            .build()
+            .unwrap();
+        let mut strategy = Script(vec![vec![Order::market_buy(OrderSize::All)]]);
+        let report = backtest
+            .run(&flat(&[100, 100, 120, 90, 150]), &mut strategy)
+            .unwrap();
+
+        assert_eq!(report.trades().len(), 1);
+        assert_eq!(report.trades()[0].timestamp(), day(1));
+        assert_eq!(report.trades()[0].quantity(), &btc("10"));
+        assert_eq!(report.final_equity(), &usd("1500"));
+        assert!((report.total_return() - 0.5).abs() < 1e-12);
+        assert!((report.max_drawdown() - 0.25).abs() < 1e-12);
+
+        let equity: Vec<i128> = report.equity().iter().map(|p| p.price().amount()).collect();
+        assert_eq!(equity, vec![100_000, 100_000, 120_000, 90_000, 150_000]);
+    }
+
+    #[test]
+    fn test_fees_and_slippage() {
+        let backtest = Backtest::new(pair(), usd("1000"))
+            .fee_rate(0.001)
+            .slippage(0.01)
+            .build()
+            .unwrap();
+        let mut strategy = Script(vec![
+            vec![Order::market_buy(OrderSize::Quantity(btc("1")))],
+            vec![Order::market_sell(OrderSize::All)],
+        ]);
+        let report = backtest
+            .run(&flat(&[100, 100, 100]), &mut strategy)
+            .unwrap();
+
+        let buy = &report.trades()[0];
+        assert_eq!(buy.price(), &usd("101"));
+        assert_eq!(buy.fee().amount(), 10);
+        let sell = &report.trades()[1];
+        assert_eq!(sell.price(), &usd("99"));
+        assert_eq!(sell.fee().amount(), 10);
+        assert_eq!(report.final_equity().amount(), 100_000 - 200 - 20);
+    }
+
+    #[test]
+    fn test_all_in_with_eighteen_decimals() {
+        let pair = CurrencyPair::parse("ETH/USD").unwrap();
+        let dollars = |cents| Money::from_minor(cents, pair.quote().clone());
+        let budget = 100_000_400;
+        let backtest = Backtest::new(pair.clone(), dollars(budget))
+            .fee_rate(0.001)
+            .build()
+            .unwrap();
+        let price = dollars(300_007);
+        let candles: Vec<Candle> = (0..2)
+            .map(|day_| {
+                Candle::new(
+                    day(day_),
+                    price.clone(),
+                    price.clone(),
+                    price.clone(),
+                    price.clone(),
+                )
+                .unwrap()
+            })
+            .collect();
+        let mut strategy = Script(vec![vec![Order::market_buy(OrderSize::All)]]);
+        let report = backtest.run(&candles, &mut strategy).unwrap();
+
+        let buy = &report.trades()[0];
+        let cost = buy.notional().amount() + buy.fee().amount();
+        assert!(cost <= budget);
+        assert!(budget - cost <= 1);
+    }
+
+    #[test]
+    fn test_limit_orders_and_rejections() {
+        let backtest = Backtest::new(pair(), usd("1000"))
+            .fee_rate(0.0)
+            .build()
+            .unwrap();
+        let mut strategy = Script(vec![
+            vec![
+                Order::limit_buy(OrderSize::Quantity(btc("1")), usd("90")),
+                Order::limit_buy(OrderSize::Quantity(btc("1")), usd("80")),
+                Order::market_buy(OrderSize::Quantity(btc("100"))),
+            ],
+            vec![Order::limit_sell(OrderSize::All, usd("130"))],
+        ]);
+        let candles = vec![
+            candle(0, 100, 100, 100, 100),
+            candle(1, 100, 105, 85, 95),
+            candle(2, 140, 150, 135, 145),
+        ];
+        let report = backtest.run(&candles, &mut strategy).unwrap();
+
+        assert_eq!(report.trades().len(), 2);
+        assert_eq!(report.trades()[0].price(), &usd("90"));
+        assert_eq!(report.trades()[1].side(), Side::Sell);
+        assert_eq!(report.trades()[1].price(), &usd("140"));
+        assert_eq!(report.rejected(), 1);
+        assert_eq!(report.final_equity(), &usd("1050"));
+    }
+
+    #[test]
+    fn test_sma_crossover() {
+        let prices = [100, 98, 96, 94, 92, 95, 99, 104, 110, 108, 100, 92, 85, 80];
+        let mut series = PriceSeries::new(pair().quote().clone());
+        for (hour, price) in prices.iter().enumerate() {
+            for minute in [0, 30] {
+                let timestamp = day(0) + Duration::hours(hour as i64) + Duration::minutes(minute);
+                series
+                    .push(PricePoint::new(timestamp, usd(&price.to_string())))
+                    .unwrap();
+            }
+        }
+        let candles = series.candles(Duration::hours(1)).unwrap();
+        assert_eq!(candles.len(), prices.len());
+
+        let backtest = Backtest::new(pair(), usd("10000"))
+            .periods_per_year(24.0 * 365.0)
+            .build()
+            .unwrap();
+        let report = backtest
+            .run(&candles, &mut SmaCrossover::new(2, 4))
+            .unwrap();
+
+        let sides: Vec<Side> = report.trades().iter().map(|t| t.side()).collect();
+        assert_eq!(sides, vec![Side::Buy, Side::Sell]);
+        assert!(report.sharpe_ratio().is_some());
+    }
+
+    #[test]
+    fn test_rejects_invalid_settings() {
+        let error = Backtest::new(pair(), usd("1000"))
+            .fee_rate(1.5)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("setting"), Some("fee_rate"));
+
+        let euros = Money::from_minor(100, CurrencyPair::parse("BTC/EUR").unwrap().quote().clone());
+        assert!(Backtest::new(pair(), euros).build().is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use super::side::Side;
+use crate::money::Money;
+
+/// How much an order trades.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderSize {
+    /// An amount of the asset.
+    Quantity(Money),
+    /// As much of the asset as an amount of cash buys or raises, fees included for buys.
+    Value(Money),
+    /// All available cash for a buy, or the whole holding for a sell.
+    All,
+}
+
+/// An order a strategy places after seeing a candle. It is filled at the next candle or expires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    side: Side,
+    size: OrderSize,
+    limit: Option<Money>,
+}
+
+impl Order {
+    /// Buys at the next candle's open, plus slippage.
+    pub fn market_buy(size: OrderSize) -> Self {
+        Self {
+            side: Side::Buy,
+            size,
+            limit: None,
+        }
+    }
+
+    /// Sells at the next candle's open, less slippage.
+    pub fn market_sell(size: OrderSize) -> Self {
+        Self {
+            side: Side::Sell,
+            size,
+            limit: None,
+        }
+    }
+
+    /// Buys if the next candle trades at or below `price`.
+    pub fn limit_buy(size: OrderSize, price: Money) -> Self {
+        Self {
+            side: Side::Buy,
+            size,
+            limit: Some(price),
+        }
+    }
+
+    /// Sells if the next candle trades at or above `price`.
+    pub fn limit_sell(size: OrderSize, price: Money) -> Self {
+        Self {
+            side: Side::Sell,
+            size,
+            limit: Some(price),
+        }
+    }
+
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    pub fn size(&self) -> &OrderSize {
+        &self.size
+    }
+
+    /// The limit price, or `None` for a market order.
+    pub fn limit(&self) -> Option<&Money> {
+        self.limit.as_ref()
+    }
+}
@@ -0,0 +1,115 @@
+use crate::currency::CurrencyPair;
+use crate::money::Money;
+use crate::price::PriceSeries;
+
+use super::trade::Trade;
+
+/// The outcome of a backtest.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct BacktestReport {
+    pair: CurrencyPair,
+    initial_cash: Money,
+    equity: PriceSeries,
+    trades: Vec<Trade>,
+    rejected: usize,
+    periods_per_year: f64,
+}
+
+impl BacktestReport {
+    pub(crate) fn new(
+        pair: CurrencyPair,
+        initial_cash: Money,
+        equity: PriceSeries,
+        trades: Vec<Trade>,
+        rejected: usize,
+        periods_per_year: f64,
+    ) -> Self {
+        Self {
+            pair,
+            initial_cash,
+            equity,
+            trades,
+            rejected,
+            periods_per_year,
+        }
+    }
+
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    pub fn initial_cash(&self) -> &Money {
+        &self.initial_cash
+    }
+
+    /// Account equity at the close of every candle.
+    pub fn equity(&self) -> &PriceSeries {
+        &self.equity
+    }
+
+    pub fn final_equity(&self) -> &Money {
+        self.equity
+            .last()
+            .map_or(&self.initial_cash, |point| point.price())
+    }
+
+    pub fn trades(&self) -> &[Trade] {
+        &self.trades
+    }
+
+    /// Orders that could not be filled for lack of cash or holdings.
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+
+    /// The change in equity over the whole backtest as a fraction, e.g. `0.25` for 25%.
+    pub fn total_return(&self) -> f64 {
+        let initial = self.initial_cash.amount() as f64;
+        if initial == 0.0 {
+            return 0.0;
+        }
+        self.final_equity().amount() as f64 / initial - 1.0
+    }
+
+    /// The largest fall of equity from a previous peak, as a fraction of the peak.
+    pub fn max_drawdown(&self) -> f64 {
+        let mut peak = self.initial_cash.amount() as f64;
+        let mut deepest: f64 = 0.0;
+        for point in &self.equity {
+            let equity = point.price().amount() as f64;
+            peak = peak.max(equity);
+            if peak > 0.0 {
+                deepest = deepest.max((peak - equity) / peak);
+            }
+        }
+        deepest
+    }
+
+    /// The annualized Sharpe ratio of the per-candle returns, with a risk-free rate of zero.
+    ///
+    /// Returns `None` with fewer than two returns or when returns do not vary.
+    pub fn sharpe_ratio(&self) -> Option<f64> {
+        let mut previous = self.initial_cash.amount() as f64;
+        let mut returns = Vec::with_capacity(self.equity.len());
+        for point in &self.equity {
+            let equity = point.price().amount() as f64;
+            if previous != 0.0 {
+                returns.push(equity / previous - 1.0);
+            }
+            previous = equity;
+        }
+        if returns.len() < 2 {
+            return None;
+        }
+
+        let count = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / count;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (count - 1.0);
+        let deviation = variance.sqrt();
+        if deviation == 0.0 {
+            return None;
+        }
+        Some(mean / deviation * self.periods_per_year.sqrt())
+    }
+}
@@ -0,0 +1,17 @@
+/// The direction of an order or trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[macros::json(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    /// The other side of a trade.
+    pub fn opposite(&self) -> Self {
+        match self {
+            Self::Buy => Self::Sell,
+            Self::Sell => Self::Buy,
+        }
+    }
+}
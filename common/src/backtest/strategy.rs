@@ -0,0 +1,92 @@
+use std::cmp::Ordering;
+
+use utils::errors::Error;
+
+use super::order::{Order, OrderSize};
+use crate::indicators::{Indicator, Sma};
+use crate::money::Money;
+use crate::price::Candle;
+
+/// The state of the simulated account a strategy trades with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Account {
+    cash: Money,
+    holdings: Money,
+    equity: Money,
+}
+
+impl Account {
+    pub(crate) fn new(cash: Money, holdings: Money, equity: Money) -> Self {
+        Self {
+            cash,
+            holdings,
+            equity,
+        }
+    }
+
+    /// Cash available in the quote currency.
+    pub fn cash(&self) -> &Money {
+        &self.cash
+    }
+
+    /// The amount of the asset held.
+    pub fn holdings(&self) -> &Money {
+        &self.holdings
+    }
+
+    /// Cash plus holdings valued at the last close.
+    pub fn equity(&self) -> &Money {
+        &self.equity
+    }
+}
+
+/// A trading strategy driven by a backtest.
+pub trait Strategy {
+    /// Called once per candle after it has closed, with the account as of that close. The
+    /// returned orders are filled at the next candle or expire.
+    fn on_candle(&mut self, candle: &Candle, account: &Account) -> Result<Vec<Order>, Error>;
+}
+
+/// Goes all in when the fast moving average of closes crosses above the slow one and sells
+/// everything when it crosses back below.
+pub struct SmaCrossover {
+    fast: Sma,
+    slow: Sma,
+    previous: Option<Ordering>,
+}
+
+impl SmaCrossover {
+    pub fn new(fast: usize, slow: usize) -> Self {
+        Self {
+            fast: Sma::new(fast),
+            slow: Sma::new(slow),
+            previous: None,
+        }
+    }
+}
+
+impl Strategy for SmaCrossover {
+    fn on_candle(&mut self, candle: &Candle, account: &Account) -> Result<Vec<Order>, Error> {
+        let point = candle.close_point();
+        let (Some(fast), Some(slow)) = (self.fast.next(&point)?, self.slow.next(&point)?) else {
+            return Ok(Vec::new());
+        };
+
+        let current = fast.amount().cmp(&slow.amount());
+        let previous = self.previous.replace(current);
+        let orders = match (previous, current) {
+            (Some(Ordering::Less | Ordering::Equal), Ordering::Greater)
+                if !account.cash().is_zero() =>
+            {
+                vec![Order::market_buy(OrderSize::All)]
+            }
+            (Some(Ordering::Greater | Ordering::Equal), Ordering::Less)
+                if !account.holdings().is_zero() =>
+            {
+                vec![Order::market_sell(OrderSize::All)]
+            }
+            _ => Vec::new(),
+        };
+        Ok(orders)
+    }
+}
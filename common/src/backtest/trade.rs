@@ -0,0 +1,64 @@
+use chrono::{DateTime, Utc};
+
+use super::side::Side;
+use crate::money::Money;
+
+/// A filled order.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Trade {
+    timestamp: DateTime<Utc>,
+    side: Side,
+    quantity: Money,
+    price: Money,
+    notional: Money,
+    fee: Money,
+}
+
+impl Trade {
+    pub(crate) fn new(
+        timestamp: DateTime<Utc>,
+        side: Side,
+        quantity: Money,
+        price: Money,
+        notional: Money,
+        fee: Money,
+    ) -> Self {
+        Self {
+            timestamp,
+            side,
+            quantity,
+            price,
+            notional,
+            fee,
+        }
+    }
+
+    /// The start of the candle the order was filled in.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    /// The amount of the asset traded.
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    /// The fill price of one whole unit of the asset, slippage included.
+    pub fn price(&self) -> &Money {
+        &self.price
+    }
+
+    /// The quantity times the price, before fees.
+    pub fn notional(&self) -> &Money {
+        &self.notional
+    }
+
+    pub fn fee(&self) -> &Money {
+        &self.fee
+    }
+}
@@ -1,6 +1,6 @@
 use super::currency::Currency;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default, macros::Builder)]
 #[macros::json]
 pub struct Bitcoin<'a> {
     name: &'a str,
@@ -13,7 +13,7 @@ impl<'a> Bitcoin<'a> {
     }
 
     pub fn name(&self) -> &str {
-        &self.name
+        self.name
     }
 
     pub fn price(&self) -> &Currency {
@@ -21,42 +21,33 @@ impl<'a> Bitcoin<'a> {
     }
 }
 
-impl<'a> Default for Bitcoin<'a> {
-    fn default() -> Self {
-        Self {
-            name: "",
-            price: Currency::default(),
-        }
-    }
-}
-
-pub struct BitcoinBuilder<'a> {
-    name: Option<&'a str>,
-    price: Option<Currency>,
-}
-
-impl<'a> BitcoinBuilder<'a> {
-    pub fn new() -> Self {
-        Self {
-            name: None,
-            price: None,
-        }
-    }
-
-    pub fn name(mut self, name: &'a str) -> Self {
-        self.name = Some(name);
-        self
-    }
-
-    pub fn price(mut self, price: Currency) -> Self {
-        self.price = Some(price);
-        self
-    }
-
-    pub fn build(self) -> Bitcoin<'a> {
-        Bitcoin {
-            name: self.name.unwrap(),
-            price: self.price.unwrap(),
-        }
+#[cfg(test)]
+mod tests {
+    use utils::errors::ErrorCode;
+
+    use super::*;
+    use crate::currency::code::CurrencyCode;
+
+    #[test]
+    fn test_builder() {
+        let price = Currency::from(CurrencyCode::USD);
+        let bitcoin = Bitcoin::new()
+            .name("Bitcoin")
+            .price(price.clone())
+            .build()
+            .unwrap();
+        assert_eq!(bitcoin.name(), "Bitcoin");
+        assert_eq!(bitcoin.price(), &price);
+    }
+
+    #[test]
+    fn test_builder_missing_fields() {
+        let error = Bitcoin::new().build().unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("type"), Some("Bitcoin"));
+        assert_eq!(error.meta_value("fields"), Some("name, price"));
+
+        let error = Bitcoin::new().name("Bitcoin").build().unwrap_err();
+        assert_eq!(error.meta_value("fields"), Some("price"));
     }
 }
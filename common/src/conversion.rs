@@ -0,0 +1,443 @@
+//! Currency conversion with an audit trail.
+//!
+//! A `Converter` converts amounts with a set of quotes. When no quote covers the two currencies
+//! directly, or inverted, it triangulates through other currencies using the fewest quotes. The
+//! resulting `Conversion` records every rate applied: which source quoted it and when, the
+//! amounts before and after, and how much rounding to minor units changed the result, so a
+//! converted figure can always be traced back to the quotes behind it.
+//!
+//! When a conversion fails the error carries the currencies involved and, in the `trail`
+//! metadata, the JSON of the steps completed so far.
+
+pub mod step;
+
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+use utils::json::JSON;
+
+use super::currency::code::CurrencyCode;
+use super::currency::Currency;
+use super::money::{ensure_currency, format_minor, from_wide, mul_div_round, Money};
+use super::price::Quote;
+
+pub use step::ConversionStep;
+
+const DEFAULT_MAX_HOPS: usize = 3;
+/// Extra decimal places of the exact results recorded in steps, where they fit.
+const EXACT_DECIMALS: u32 = 6;
+
+/// An amount converted to another currency, with the rates applied along the way.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Conversion {
+    from: Money,
+    to: Money,
+    steps: Vec<ConversionStep>,
+}
+
+impl Conversion {
+    /// The amount converted.
+    pub fn from(&self) -> &Money {
+        &self.from
+    }
+
+    /// The converted amount.
+    pub fn to(&self) -> &Money {
+        &self.to
+    }
+
+    /// The rates applied, in order. Empty when converting to the same currency.
+    pub fn steps(&self) -> &[ConversionStep] {
+        &self.steps
+    }
+
+    /// Whether the conversion went through other currencies.
+    pub fn is_triangulated(&self) -> bool {
+        self.steps.len() > 1
+    }
+
+    /// The timestamp of the oldest quote used.
+    pub fn oldest_rate(&self) -> Option<DateTime<Utc>> {
+        self.steps.iter().map(|step| step.timestamp()).min()
+    }
+}
+
+/// Converts amounts with the latest quote of every pair it was given.
+#[derive(Debug, Clone)]
+pub struct Converter {
+    quotes: Vec<Quote>,
+    max_hops: usize,
+}
+
+impl Converter {
+    pub fn new() -> ConverterBuilder {
+        ConverterBuilder::new()
+    }
+
+    pub fn quotes(&self) -> &[Quote] {
+        &self.quotes
+    }
+
+    /// Converts the amount to the currency.
+    ///
+    /// Fails with `ErrorCode::NotFound` if no chain of at most `max_hops` quotes links the two
+    /// currencies, and with `ErrorCode::Invalid` if an amount overflows along the way.
+    pub fn convert(&self, amount: &Money, to: &Currency) -> Result<Conversion, Error> {
+        let path = self
+            .path(amount.currency().code(), to.code())
+            .ok_or_else(|| {
+                failure(
+                    Error::new("No conversion path", ErrorCode::NotFound),
+                    amount,
+                    to,
+                    &[],
+                )
+            })?;
+
+        let mut steps: Vec<ConversionStep> = Vec::with_capacity(path.len());
+        let mut current = amount.clone();
+        for (index, inverted) in path {
+            let step = apply(&self.quotes[index], inverted, &current)
+                .map_err(|err| failure(err, amount, to, &steps))?;
+            current = step.output().clone();
+            steps.push(step);
+        }
+
+        Ok(Conversion {
+            from: amount.clone(),
+            to: current,
+            steps,
+        })
+    }
+
+    /// The shortest chain of quotes from one currency to the other, as quote indices and
+    /// whether each is applied inverted. Forward quotes are preferred over inverted ones.
+    fn path(&self, from: &CurrencyCode, to: &CurrencyCode) -> Option<Vec<(usize, bool)>> {
+        let mut previous: HashMap<CurrencyCode, (CurrencyCode, usize, bool)> = HashMap::new();
+        let mut queue = VecDeque::from([(*from, 0)]);
+
+        while let Some((code, hops)) = queue.pop_front() {
+            if code == *to {
+                let mut path = Vec::with_capacity(hops);
+                let mut cursor = code;
+                while cursor != *from {
+                    let (before, index, inverted) = previous[&cursor];
+                    path.push((index, inverted));
+                    cursor = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if hops == self.max_hops {
+                continue;
+            }
+
+            for inverted in [false, true] {
+                for (index, quote) in self.quotes.iter().enumerate() {
+                    let (start, end) = match inverted {
+                        false => (quote.pair().base().code(), quote.pair().quote().code()),
+                        true => (quote.pair().quote().code(), quote.pair().base().code()),
+                    };
+                    if *start != code || *end == *from || previous.contains_key(end) {
+                        continue;
+                    }
+                    previous.insert(*end, (code, index, inverted));
+                    queue.push_back((*end, hops + 1));
+                }
+            }
+        }
+        None
+    }
+}
+
+pub struct ConverterBuilder {
+    quotes: Vec<Quote>,
+    max_hops: usize,
+}
+
+impl Default for ConverterBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConverterBuilder {
+    pub fn new() -> Self {
+        Self {
+            quotes: Vec::new(),
+            max_hops: DEFAULT_MAX_HOPS,
+        }
+    }
+
+    /// A quote to convert with. Of several quotes for the same pair the latest is used.
+    pub fn quote(mut self, quote: Quote) -> Self {
+        self.quotes.push(quote);
+        self
+    }
+
+    pub fn quotes(mut self, quotes: impl IntoIterator<Item = Quote>) -> Self {
+        self.quotes.extend(quotes);
+        self
+    }
+
+    /// The most quotes chained in one conversion. Defaults to 3.
+    pub fn max_hops(mut self, max_hops: usize) -> Self {
+        self.max_hops = max_hops;
+        self
+    }
+
+    /// Fails with `ErrorCode::Invalid` if a quote's price is not positive or not in the pair's
+    /// quote currency.
+    pub fn build(self) -> Result<Converter, Error> {
+        let mut latest: Vec<Quote> = Vec::with_capacity(self.quotes.len());
+        for quote in self.quotes {
+            ensure_currency(quote.pair().quote(), quote.price().currency())?;
+            if quote.price().amount() <= 0 {
+                return Err(
+                    Error::new("Quote prices must be positive", ErrorCode::Invalid).with_meta(
+                        ErrorMeta::new()
+                            .add("pair", quote.pair().to_string().as_str())
+                            .add("source", quote.source())
+                            .build(),
+                    ),
+                );
+            }
+
+            match latest.iter_mut().find(|q| q.pair() == quote.pair()) {
+                Some(existing) if existing.timestamp() < quote.timestamp() => *existing = quote,
+                Some(_) => {}
+                None => latest.push(quote),
+            }
+        }
+
+        Ok(Converter {
+            quotes: latest,
+            max_hops: self.max_hops,
+        })
+    }
+}
+
+/// Applies a quote to an amount in its base currency, or in its quote currency if inverted.
+fn apply(quote: &Quote, inverted: bool, amount: &Money) -> Result<ConversionStep, Error> {
+    let pair = quote.pair();
+    let (from, to) = match inverted {
+        false => (pair.base(), pair.quote()),
+        true => (pair.quote(), pair.base()),
+    };
+    ensure_currency(from, amount.currency())?;
+
+    let unit = 10i128.pow(pair.base().decimal_places());
+    let price = quote.price().amount();
+    let (factor, denominator) = match inverted {
+        false => (price, unit),
+        true => (unit, price),
+    };
+    let rounded = mul_div_round(amount.amount(), factor, denominator).ok_or_else(|| {
+        Error::new("Amount out of range", ErrorCode::Invalid).with_meta(
+            ErrorMeta::new()
+                .add("amount", amount.to_decimal_string().as_str())
+                .add("pair", pair.to_string().as_str())
+                .build(),
+        )
+    })?;
+
+    // The exact result with as many of the extra decimal places as fit, down to none for
+    // results near the limit, where the output is the exact result.
+    let (exact, adjustment, extra) = (0..=EXACT_DECIMALS)
+        .rev()
+        .find_map(|extra| {
+            let scale = 10i128.pow(extra);
+            let exact = factor
+                .checked_mul(scale)
+                .and_then(|factor| mul_div_round(amount.amount(), factor, denominator))?;
+            let adjustment = rounded.checked_mul(scale)?.checked_sub(exact)?;
+            Some((exact, adjustment, extra))
+        })
+        .unwrap_or((rounded, 0, 0));
+
+    let decimals = to.decimal_places() + extra;
+    Ok(ConversionStep::new(
+        quote.source(),
+        pair.clone(),
+        quote.price().clone(),
+        inverted,
+        quote.timestamp(),
+        amount.clone(),
+        from_wide(rounded, to)?,
+        format_minor(exact, decimals),
+        format_minor(adjustment, decimals),
+    ))
+}
+
+/// Adds the currencies and the steps completed so far to a conversion error.
+fn failure(error: Error, amount: &Money, to: &Currency, steps: &[ConversionStep]) -> Error {
+    let trail = steps.to_vec().to_json().unwrap_or_default();
+    let mut meta = ErrorMeta::new();
+    for (key, value) in error.meta().into_iter().flatten() {
+        meta.add(key, value);
+    }
+    error.with_meta(
+        meta.add("from", amount.to_string().as_str())
+            .add("to", to.code().to_string())
+            .add("trail", trail.as_str())
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::currency::CurrencyPair;
+    use crate::test_support::currency;
+
+    fn time(minutes: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    fn quote(source: &str, pair: &str, price: &str, minutes: i64) -> Quote {
+        let pair = CurrencyPair::parse(pair).unwrap();
+        let price = Money::parse(price, pair.quote().clone()).unwrap();
+        Quote::new(source, pair, price, time(minutes))
+    }
+
+    fn converter() -> Converter {
+        Converter::new()
+            .quote(quote("kraken", "BTC/USD", "40000.00", 0))
+            .quote(quote("coinbase", "BTC/USD", "40100.00", 5))
+            .quote(quote("ecb", "EUR/USD", "1.10", 2))
+            .quote(quote("kraken", "ETH/BTC", "0.05000000", 1))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_direct_and_inverted() {
+        let converter = converter();
+        let btc = Money::parse("0.5", currency(CurrencyCode::BTC)).unwrap();
+        let conversion = converter
+            .convert(&btc, &currency(CurrencyCode::USD))
+            .unwrap();
+        assert_eq!(conversion.to().to_decimal_string(), "20050.00");
+        let step = &conversion.steps()[0];
+        assert_eq!(step.source(), "coinbase");
+        assert!(!step.inverted());
+        assert_eq!(step.timestamp(), time(5));
+        assert_eq!(step.adjustment(), "0.00000000");
+
+        let usd = Money::parse("100.00", currency(CurrencyCode::USD)).unwrap();
+        let conversion = converter
+            .convert(&usd, &currency(CurrencyCode::BTC))
+            .unwrap();
+        let step = &conversion.steps()[0];
+        assert!(step.inverted());
+        // 100 / 40100 = 0.00249376558...
+        assert_eq!(conversion.to().amount(), 249_377);
+        assert_eq!(step.exact(), "0.00249376558603");
+        assert_eq!(step.adjustment(), "0.00000000441397");
+    }
+
+    #[test]
+    fn test_triangulation() {
+        let converter = converter();
+        let eth = Money::parse("2", currency(CurrencyCode::ETH)).unwrap();
+        let conversion = converter
+            .convert(&eth, &currency(CurrencyCode::EUR))
+            .unwrap();
+        assert!(conversion.is_triangulated());
+
+        let path: Vec<_> = conversion
+            .steps()
+            .iter()
+            .map(|step| (step.pair().to_string(), step.inverted()))
+            .collect();
+        assert_eq!(
+            path,
+            vec![
+                ("ETH/BTC".to_string(), false),
+                ("BTC/USD".to_string(), false),
+                ("EUR/USD".to_string(), true),
+            ]
+        );
+        // 2 ETH = 0.1 BTC = 4010 USD = 3645.45 EUR
+        assert_eq!(conversion.to().to_decimal_string(), "3645.45");
+        assert_eq!(conversion.oldest_rate(), Some(time(1)));
+
+        let same = converter
+            .convert(&eth, &currency(CurrencyCode::ETH))
+            .unwrap();
+        assert!(same.steps().is_empty());
+        assert_eq!(same.to(), &eth);
+
+        let json = conversion.to_json().unwrap();
+        assert_eq!(Conversion::from_json(&json).unwrap(), conversion);
+    }
+
+    #[test]
+    fn test_large_amounts() {
+        let converter = Converter::new()
+            .quote(quote("kraken", "ETH/USDC", "3000", 0))
+            .build()
+            .unwrap();
+        let eth = Money::parse("100000", currency(CurrencyCode::ETH)).unwrap();
+        let conversion = converter
+            .convert(&eth, &currency(CurrencyCode::USDC))
+            .unwrap();
+        assert_eq!(conversion.to().to_decimal_string(), "300000000.000000");
+        assert_eq!(conversion.steps()[0].exact(), "300000000.000000000000");
+
+        // Too large for all the extra decimal places, but not for the output.
+        let converter = Converter::new()
+            .quote(quote("kraken", "BTC/USD", "10000000000", 0))
+            .build()
+            .unwrap();
+        let btc = Money::from_minor(10i128.pow(30), currency(CurrencyCode::BTC));
+        let conversion = converter
+            .convert(&btc, &currency(CurrencyCode::USD))
+            .unwrap();
+        let step = &conversion.steps()[0];
+        assert_eq!(conversion.to().amount(), 10i128.pow(34));
+        assert_eq!(
+            step.exact(),
+            format!("{}0000", conversion.to().to_decimal_string())
+        );
+        assert_eq!(step.adjustment(), "0.000000");
+    }
+
+    #[test]
+    fn test_failures() {
+        let converter = Converter::new()
+            .quote(quote("kraken", "ETH/BTC", "0.05000000", 1))
+            .quote(quote("ecb", "EUR/USD", "1.10", 2))
+            .build()
+            .unwrap();
+        let eth = Money::parse("1", currency(CurrencyCode::ETH)).unwrap();
+        let error = converter
+            .convert(&eth, &currency(CurrencyCode::USD))
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::NotFound);
+        assert_eq!(error.meta_value("to"), Some("USD"));
+        assert_eq!(error.meta_value("trail"), Some("[]"));
+
+        let limited = Converter::new()
+            .quotes(converter.quotes().to_vec())
+            .quote(quote("kraken", "BTC/USD", "40000.00", 0))
+            .max_hops(1)
+            .build()
+            .unwrap();
+        assert!(limited.convert(&eth, &currency(CurrencyCode::USD)).is_err());
+
+        let huge = Money::from_minor(i128::MAX / 10, currency(CurrencyCode::BTC));
+        let error = limited
+            .convert(&huge, &currency(CurrencyCode::ETH))
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("pair"), Some("ETH/BTC"));
+
+        let free = quote("bad", "BTC/USD", "0", 0);
+        assert!(Converter::new().quote(free).build().is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+
+use crate::currency::CurrencyPair;
+use crate::money::Money;
+
+/// One rate applied during a conversion: the quote used, the amount it was applied to and the
+/// rounded result, along with the unrounded result and the adjustment rounding made to it.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct ConversionStep {
+    source: Box<str>,
+    pair: CurrencyPair,
+    rate: Money,
+    inverted: bool,
+    timestamp: DateTime<Utc>,
+    input: Money,
+    output: Money,
+    exact: String,
+    adjustment: String,
+}
+
+impl ConversionStep {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        source: &str,
+        pair: CurrencyPair,
+        rate: Money,
+        inverted: bool,
+        timestamp: DateTime<Utc>,
+        input: Money,
+        output: Money,
+        exact: String,
+        adjustment: String,
+    ) -> Self {
+        Self {
+            source: source.into(),
+            pair,
+            rate,
+            inverted,
+            timestamp,
+            input,
+            output,
+            exact,
+            adjustment,
+        }
+    }
+
+    /// The source of the quote.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The pair of the quote, which is the reverse of the step when `inverted` is set.
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    /// The quoted price of one unit of the pair's base.
+    pub fn rate(&self) -> &Money {
+        &self.rate
+    }
+
+    /// Whether the amount was in the pair's quote currency and divided by the rate.
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// When the quote was taken.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    pub fn input(&self) -> &Money {
+        &self.input
+    }
+
+    pub fn output(&self) -> &Money {
+        &self.output
+    }
+
+    /// The result before rounding to minor units, with six more decimal places than the
+    /// currency has, or fewer for results too large to hold them.
+    pub fn exact(&self) -> &str {
+        &self.exact
+    }
+
+    /// The output minus the exact result, at the same precision.
+    pub fn adjustment(&self) -> &str {
+        &self.adjustment
+    }
+}
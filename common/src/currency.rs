@@ -1,13 +1,23 @@
 pub mod code;
+pub mod locale;
 pub mod name;
+pub mod pair;
+pub mod resolver;
 pub mod symbol;
+pub mod token;
+
+use utils::errors::{Error, ErrorCode, ErrorMeta};
 
 use code::CurrencyCode;
 use name::CurrencyName;
 use symbol::CurrencySymbol;
 
-#[derive(Debug, Clone, PartialEq)]
-#[macros::json]
+pub use locale::{Locale, PluralCategory};
+pub use pair::CurrencyPair;
+pub use resolver::CurrencyResolver;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[macros::json(try_from = "CurrencyRepr")]
 pub struct Currency {
     code: CurrencyCode,
     name: CurrencyName,
@@ -30,24 +40,90 @@ impl Currency {
     pub fn symbol(&self) -> &CurrencySymbol {
         &self.symbol
     }
+
+    pub fn decimal_places(&self) -> u32 {
+        self.symbol.get_decimal_places()
+    }
 }
 
-impl Default for Currency {
-    fn default() -> Self {
+impl Currency {
+    /// The currency the parts agree on, with the missing ones derived from the others.
+    fn from_parts(
+        code: Option<CurrencyCode>,
+        name: Option<CurrencyName>,
+        symbol: Option<CurrencySymbol>,
+    ) -> Result<Self, Error> {
+        let implied = [
+            code,
+            name.as_ref().map(|name| name.get_code()),
+            symbol.map(|symbol| symbol.get_code()),
+        ];
+        let Some(currency) = implied.iter().flatten().next().copied() else {
+            return Err(Error::new("Missing currency code", ErrorCode::Invalid));
+        };
+
+        if implied.iter().flatten().any(|other| *other != currency) {
+            let mut meta = ErrorMeta::new();
+            if let Some(code) = &code {
+                meta.add("code", code.to_string());
+            }
+            if let Some(name) = &name {
+                meta.add("name", name.to_string());
+            }
+            if let Some(symbol) = &symbol {
+                meta.add("symbol", symbol.get_symbol());
+            }
+            return Err(
+                Error::new("Inconsistent currency", ErrorCode::Invalid).with_meta(meta.build())
+            );
+        }
+
+        Ok(currency.into())
+    }
+}
+
+impl From<CurrencyCode> for Currency {
+    fn from(code: CurrencyCode) -> Self {
         Self {
-            code: CurrencyCode::default(),
-            name: CurrencyName::default(),
-            symbol: CurrencySymbol::default(),
+            code,
+            name: code.get_name(),
+            symbol: code.get_symbol(),
         }
     }
 }
 
+/// How `Currency` is read from JSON: the name and symbol may be left out, but any that are given
+/// must agree with the code.
+#[derive(serde::Deserialize)]
+struct CurrencyRepr {
+    code: CurrencyCode,
+    #[serde(default)]
+    name: Option<CurrencyName>,
+    #[serde(default)]
+    symbol: Option<CurrencySymbol>,
+}
+
+impl TryFrom<CurrencyRepr> for Currency {
+    type Error = Error;
+
+    fn try_from(repr: CurrencyRepr) -> Result<Self, Error> {
+        Currency::from_parts(Some(repr.code), repr.name, repr.symbol)
+    }
+}
+
+/// Builds a `Currency` from any of its code, name and symbol, deriving the rest.
 pub struct CurrencyBuilder<'a> {
     code: Option<&'a str>,
     name: Option<&'a str>,
     symbol: Option<&'a str>,
 }
 
+impl<'a> Default for CurrencyBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> CurrencyBuilder<'a> {
     pub fn new() -> Self {
         Self {
@@ -72,24 +148,129 @@ impl<'a> CurrencyBuilder<'a> {
         self
     }
 
-    pub fn build(self) -> Result<Currency, &'static str> {
-        let code = self.code.ok_or("Missing code")?;
-        let name = self.name.ok_or("Missing name")?;
-        let symbol = self.symbol.ok_or("Missing symbol")?;
+    /// Fails with `ErrorCode::Invalid` if none of the code, name and symbol is given, one of
+    /// them is unknown, or they belong to different currencies.
+    pub fn build(self) -> Result<Currency, Error> {
+        let code = self
+            .code
+            .map(|code| {
+                code.parse::<CurrencyCode>()
+                    .map_err(|_| unknown("code", code))
+            })
+            .transpose()?;
+        let name = self
+            .name
+            .map(|name| {
+                name.parse::<CurrencyName>()
+                    .map_err(|_| unknown("name", name))
+            })
+            .transpose()?;
+        let symbol = self
+            .symbol
+            .map(|symbol| {
+                symbol
+                    .parse::<CurrencySymbol>()
+                    .map_err(|_| unknown("symbol", symbol))
+            })
+            .transpose()?;
 
-        Ok(Currency {
-            code: CurrencyCode::new()
-                .currency_code(code)
-                .build()
-                .ok_or("Invalid code")?,
-            name: CurrencyName::new()
-                .currency_name(name)
+        Currency::from_parts(code, name, symbol)
+    }
+}
+
+fn unknown(field: &str, value: &str) -> Error {
+    Error::new("Unknown currency", ErrorCode::Invalid).with_meta(
+        ErrorMeta::new()
+            .add("field", field)
+            .add("value", value)
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use utils::json::JSON;
+
+    use super::*;
+
+    #[test]
+    fn test_derives_missing_parts() {
+        let usd = Currency::new().code("USD").build().unwrap();
+        assert_eq!(usd, Currency::from(CurrencyCode::USD));
+        assert_eq!(
+            Currency::new().name("Euro").build().unwrap().code(),
+            &CurrencyCode::EUR
+        );
+        assert_eq!(
+            Currency::new()
+                .symbol("Ξ")
                 .build()
-                .ok_or("Invalid name")?,
-            symbol: CurrencySymbol::new()
-                .symbol(symbol)
+                .unwrap()
+                .decimal_places(),
+            18
+        );
+        assert_eq!(
+            Currency::new()
+                .code("BTC")
+                .name("Bitcoin")
+                .symbol("₿")
                 .build()
-                .ok_or("Invalid symbol")?,
-        })
+                .unwrap(),
+            Currency::from(CurrencyCode::BTC)
+        );
+    }
+
+    #[test]
+    fn test_rejects_inconsistent_parts() {
+        let error = Currency::new()
+            .code("USD")
+            .name("Euro")
+            .symbol("GBP")
+            .build()
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("code"), Some("USD"));
+        assert_eq!(error.meta_value("name"), Some("Euro"));
+        assert_eq!(error.meta_value("symbol"), Some("£"));
+
+        let error = Currency::new().code("XYZ").build().unwrap_err();
+        assert_eq!(error.meta_value("field"), Some("code"));
+        assert!(Currency::new().build().is_err());
+    }
+
+    #[test]
+    fn test_json_is_validated() {
+        let usd = Currency::from(CurrencyCode::USD);
+        let json = usd.to_json().unwrap();
+        assert_eq!(Currency::from_json(&json).unwrap(), usd);
+        assert_eq!(Currency::from_json(r#"{"code":"USD"}"#).unwrap(), usd);
+
+        let error =
+            Currency::from_json(r#"{"code":"USD","name":"Euro","symbol":"GBP"}"#).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::JsonParse);
+    }
+
+    #[test]
+    fn test_string_forms() {
+        assert_eq!("usd".parse::<CurrencyCode>().unwrap(), CurrencyCode::USD);
+        assert_eq!(CurrencyCode::try_from("Usdt").unwrap(), CurrencyCode::USDT);
+        assert_eq!(CurrencyCode::ALL.len(), 8);
+        assert_eq!(CurrencyCode::GBP.to_string(), "GBP");
+
+        assert_eq!(CurrencyName::UsdCoin.as_str(), "USD Coin");
+        assert_eq!(format!("{}", CurrencyName::UsdCoin), "USD Coin");
+        assert!("dollar".parse::<CurrencyName>().is_err());
+
+        assert_eq!("€".parse::<CurrencySymbol>().unwrap(), CurrencySymbol::EUR);
+        assert_eq!(
+            "EUR".parse::<CurrencySymbol>().unwrap(),
+            CurrencySymbol::EUR
+        );
+        assert_eq!(CurrencySymbol::EUR.to_string(), "€");
+
+        let error = "XYZ".parse::<CurrencyCode>().unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("type"), Some("CurrencyCode"));
+        assert_eq!(error.meta_value("value"), Some("XYZ"));
     }
 }
@@ -1,27 +1,47 @@
+use alloc::string::String;
+
+use utils::errors::Error;
+
 use super::name::CurrencyName;
 use super::symbol::CurrencySymbol;
+use super::token::{self, Token, TokenId};
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-#[macros::json]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, macros::StrEnum)]
+#[macros::json(into = "String", try_from = "String")]
+#[str_enum(case_insensitive)]
 pub enum CurrencyCode {
+    #[default]
     USD,
     BTC,
     EUR,
     GBP,
-    // Add other currency codes as needed
+    ETH,
+    LTC,
+    USDT,
+    USDC,
+    /// A token registered at runtime, see `currency::token`.
+    #[str_enum(
+        parse = |code: &str| token::find(|token| token.code().eq_ignore_ascii_case(code)),
+        as_str = |id: &TokenId| id.token().code()
+    )]
+    Token(TokenId),
 }
 
-impl<'a> CurrencyCode {
-    pub fn new() -> CurrencyCodeBuilder<'a> {
-        CurrencyCodeBuilder::new()
+impl CurrencyCode {
+    pub fn to_string(&self) -> &'static str {
+        self.as_str()
+    }
+
+    /// Whether the currency is issued by a government, as opposed to a cryptocurrency.
+    pub fn is_fiat(&self) -> bool {
+        matches!(self, Self::USD | Self::EUR | Self::GBP)
     }
 
-    pub fn to_string(&self) -> &str {
+    /// The definition of a runtime token, or `None` for the built-in currencies.
+    pub fn token(&self) -> Option<&'static Token> {
         match self {
-            Self::USD => "USD",
-            Self::BTC => "BTC",
-            Self::EUR => "EUR",
-            Self::GBP => "GBP",
+            Self::Token(id) => Some(id.token()),
+            _ => None,
         }
     }
 
@@ -31,6 +51,11 @@ impl<'a> CurrencyCode {
             Self::BTC => CurrencySymbol::BTC,
             Self::EUR => CurrencySymbol::EUR,
             Self::GBP => CurrencySymbol::GBP,
+            Self::ETH => CurrencySymbol::ETH,
+            Self::LTC => CurrencySymbol::LTC,
+            Self::USDT => CurrencySymbol::USDT,
+            Self::USDC => CurrencySymbol::USDC,
+            Self::Token(id) => CurrencySymbol::Token(*id),
         }
     }
 
@@ -40,70 +65,33 @@ impl<'a> CurrencyCode {
             Self::BTC => CurrencyName::Bitcoin,
             Self::EUR => CurrencyName::Euro,
             Self::GBP => CurrencyName::Pound,
+            Self::ETH => CurrencyName::Ether,
+            Self::LTC => CurrencyName::Litecoin,
+            Self::USDT => CurrencyName::Tether,
+            Self::USDC => CurrencyName::UsdCoin,
+            Self::Token(id) => CurrencyName::Token(*id),
         }
     }
 }
 
-impl Default for CurrencyCode {
-    fn default() -> Self {
-        Self::USD
+impl From<CurrencyCode> for String {
+    fn from(code: CurrencyCode) -> Self {
+        code.as_str().into()
     }
 }
 
-pub struct CurrencyCodeBuilder<'a> {
-    currency_code: Option<&'a str>,
-}
-
-impl<'a> CurrencyCodeBuilder<'a> {
-    fn new() -> Self {
-        Self {
-            currency_code: None,
-        }
-    }
-
-    pub fn currency_code(mut self, currency_code: &'a str) -> Self {
-        self.currency_code = Some(currency_code);
-        self
-    }
-
-    pub fn build(self) -> Option<CurrencyCode> {
-        match self.currency_code {
-            Some(currency_code) => match currency_code {
-                "USD" => Some(CurrencyCode::USD),
-                "BTC" => Some(CurrencyCode::BTC),
-                "EUR" => Some(CurrencyCode::EUR),
-                "GBP" => Some(CurrencyCode::GBP),
-                _ => None,
-            },
-            None => None,
-        }
-    }
-}
+impl TryFrom<String> for CurrencyCode {
+    type Error = Error;
 
-pub fn is_valid(currency_code: &str) -> bool {
-    match currency_code {
-        "USD" => true,
-        "BTC" => true,
-        "EUR" => true,
-        "GBP" => true,
-        _ => false,
+    fn try_from(code: String) -> Result<Self, Error> {
+        code.parse()
     }
 }
 
 pub fn get_currency_code_from_symbol(currency_symbol: CurrencySymbol) -> Option<CurrencyCode> {
-    match currency_symbol {
-        CurrencySymbol::USD => Some(CurrencyCode::USD),
-        CurrencySymbol::BTC => Some(CurrencyCode::BTC),
-        CurrencySymbol::EUR => Some(CurrencyCode::EUR),
-        CurrencySymbol::GBP => Some(CurrencyCode::GBP),
-    }
+    Some(currency_symbol.get_code())
 }
 
 pub fn get_currency_code_from_name(currency_name: CurrencyName) -> Option<CurrencyCode> {
-    match currency_name {
-        CurrencyName::Dollar => Some(CurrencyCode::USD),
-        CurrencyName::Bitcoin => Some(CurrencyCode::BTC),
-        CurrencyName::Euro => Some(CurrencyCode::EUR),
-        CurrencyName::Pound => Some(CurrencyCode::GBP),
-    }
+    Some(currency_name.get_code())
 }
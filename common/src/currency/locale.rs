@@ -0,0 +1,274 @@
+//! Localized currency names.
+//!
+//! Customer-facing text names amounts in the reader's language, which means picking the right
+//! plural form: "1 euro" and "3 euros" in English, "3 Euro" in German, "2 dolary" but
+//! "5 dolarów" in Polish. Forms are chosen with the CLDR plural rules of each locale, from the
+//! number as it is written, so "1.5" takes the form used for fractions.
+//!
+//! Currencies without a translation fall back to their English name.
+
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::code::CurrencyCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[macros::json(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+    Pl,
+    Ru,
+}
+
+/// The CLDR plural categories used by the supported locales.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluralCategory {
+    One,
+    Few,
+    Many,
+    Other,
+}
+
+impl Locale {
+    /// Parses a language tag such as `"fr"`, `"de-AT"` or `"pl_PL"`, ignoring the region. Fails
+    /// with `ErrorCode::Invalid` for unsupported languages.
+    pub fn parse(tag: &str) -> Result<Self, Error> {
+        let language = tag
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match language.as_str() {
+            "en" => Ok(Self::En),
+            "de" => Ok(Self::De),
+            "fr" => Ok(Self::Fr),
+            "es" => Ok(Self::Es),
+            "pl" => Ok(Self::Pl),
+            "ru" => Ok(Self::Ru),
+            _ => Err(Error::new("Unsupported locale", ErrorCode::Invalid)
+                .with_meta(ErrorMeta::new().add("locale", tag).build())),
+        }
+    }
+
+    pub fn tag(&self) -> &str {
+        match self {
+            Self::En => "en",
+            Self::De => "de",
+            Self::Fr => "fr",
+            Self::Es => "es",
+            Self::Pl => "pl",
+            Self::Ru => "ru",
+        }
+    }
+
+    /// The character separating whole units from the fraction.
+    pub fn decimal_separator(&self) -> char {
+        match self {
+            Self::En => '.',
+            _ => ',',
+        }
+    }
+
+    /// The plural category of a decimal number written with a `.` separator, e.g. `"3"` or
+    /// `"1.50"`. Trailing fraction digits count, as in CLDR: "1.0" is not "one" in English.
+    /// Anything that is not such a number is "other".
+    pub fn plural_category(&self, number: &str) -> PluralCategory {
+        let digits = number.trim().trim_start_matches(['-', '+']);
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if !whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|digit| digit.is_ascii_digit())
+        {
+            return PluralCategory::Other;
+        }
+        // Only the last digits of large numbers matter to the rules.
+        let tail = &whole[whole.len().saturating_sub(7)..];
+        let i: u64 = tail.parse().unwrap_or(0);
+        let large = whole.trim_start_matches('0').len() > tail.len();
+        let integer = fraction.is_empty();
+        let millions = integer && (i != 0 || large) && i.is_multiple_of(1_000_000);
+
+        match self {
+            Self::En | Self::De if integer && i == 1 && !large => PluralCategory::One,
+            Self::En | Self::De => PluralCategory::Other,
+            Self::Fr if i <= 1 && !large => PluralCategory::One,
+            Self::Es if i == 1 && !large && fraction.bytes().all(|d| d == b'0') => {
+                PluralCategory::One
+            }
+            Self::Fr | Self::Es if millions => PluralCategory::Many,
+            Self::Fr | Self::Es => PluralCategory::Other,
+            Self::Pl | Self::Ru if !integer => PluralCategory::Other,
+            Self::Pl if i == 1 && !large => PluralCategory::One,
+            Self::Ru if i % 10 == 1 && i % 100 != 11 => PluralCategory::One,
+            Self::Pl | Self::Ru
+                if (2..=4).contains(&(i % 10)) && !(12..=14).contains(&(i % 100)) =>
+            {
+                PluralCategory::Few
+            }
+            Self::Pl | Self::Ru => PluralCategory::Many,
+        }
+    }
+}
+
+/// The name of a currency in a locale for a plural category.
+pub(crate) fn name(code: CurrencyCode, locale: Locale, category: PluralCategory) -> &'static str {
+    let index = match category {
+        PluralCategory::One => 0,
+        PluralCategory::Few => 1,
+        PluralCategory::Many => 2,
+        PluralCategory::Other => 3,
+    };
+    match forms(code, locale) {
+        Some(forms) => forms[index],
+        None => fallback(code, category),
+    }
+}
+
+/// The one, few, many and other forms of a currency's name. Locales without few or many
+/// categories repeat the other form.
+fn forms(code: CurrencyCode, locale: Locale) -> Option<[&'static str; 4]> {
+    use CurrencyCode::*;
+    use Locale::*;
+
+    let forms = match (code, locale) {
+        (USD, En) => ["dollar", "dollars", "dollars", "dollars"],
+        (USD, De) => ["Dollar", "Dollar", "Dollar", "Dollar"],
+        (USD, Fr) => ["dollar", "dollars", "dollars", "dollars"],
+        (USD, Es) => ["dólar", "dólares", "dólares", "dólares"],
+        (USD, Pl) => ["dolar", "dolary", "dolarów", "dolara"],
+        (USD, Ru) => ["доллар", "доллара", "долларов", "доллара"],
+        (EUR, En) => ["euro", "euros", "euros", "euros"],
+        (EUR, De) => ["Euro", "Euro", "Euro", "Euro"],
+        (EUR, Fr) => ["euro", "euros", "euros", "euros"],
+        (EUR, Es) => ["euro", "euros", "euros", "euros"],
+        (EUR, Pl) => ["euro", "euro", "euro", "euro"],
+        (EUR, Ru) => ["евро", "евро", "евро", "евро"],
+        (GBP, En) => ["pound", "pounds", "pounds", "pounds"],
+        (GBP, De) => ["Pfund", "Pfund", "Pfund", "Pfund"],
+        (GBP, Fr) => ["livre", "livres", "livres", "livres"],
+        (GBP, Es) => ["libra", "libras", "libras", "libras"],
+        (GBP, Pl) => ["funt", "funty", "funtów", "funta"],
+        (GBP, Ru) => ["фунт", "фунта", "фунтов", "фунта"],
+        (BTC, En) => ["bitcoin", "bitcoin", "bitcoin", "bitcoin"],
+        (BTC, De) => ["Bitcoin", "Bitcoin", "Bitcoin", "Bitcoin"],
+        (BTC, Fr) => ["bitcoin", "bitcoins", "bitcoins", "bitcoins"],
+        (BTC, Es) => ["bitcoin", "bitcoins", "bitcoins", "bitcoins"],
+        (BTC, Pl) => ["bitcoin", "bitcoiny", "bitcoinów", "bitcoina"],
+        (BTC, Ru) => ["биткоин", "биткоина", "биткоинов", "биткоина"],
+        (ETH, En) => ["ether", "ether", "ether", "ether"],
+        (ETH, De) => ["Ether", "Ether", "Ether", "Ether"],
+        (ETH, Fr) => ["ether", "ethers", "ethers", "ethers"],
+        (ETH, Es) => ["ether", "ethers", "ethers", "ethers"],
+        (ETH, Pl) => ["ether", "ethery", "etherów", "ethera"],
+        (ETH, Ru) => ["эфир", "эфира", "эфиров", "эфира"],
+        (LTC, En) => ["litecoin", "litecoins", "litecoins", "litecoins"],
+        (LTC, De) => ["Litecoin", "Litecoin", "Litecoin", "Litecoin"],
+        (LTC, Fr) => ["litecoin", "litecoins", "litecoins", "litecoins"],
+        (LTC, Es) => ["litecoin", "litecoins", "litecoins", "litecoins"],
+        (LTC, Pl) => ["litecoin", "litecoiny", "litecoinów", "litecoina"],
+        (LTC, Ru) => ["лайткоин", "лайткоина", "лайткоинов", "лайткоина"],
+        _ => return None,
+    };
+    Some(forms)
+}
+
+/// The English name, singular for "one" and plural otherwise.
+fn fallback(code: CurrencyCode, category: PluralCategory) -> &'static str {
+    let name = code.get_name();
+    match category {
+        PluralCategory::One => name.to_string(),
+        _ => name.to_string_plural(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::Currency;
+    use crate::money::Money;
+
+    fn amount(value: &str, code: CurrencyCode) -> Money {
+        Money::parse(value, Currency::from(code)).unwrap()
+    }
+
+    #[test]
+    fn test_plural_categories() {
+        use PluralCategory::*;
+
+        let cases = [
+            (
+                Locale::En,
+                ["1", "1.0", "2", "0"],
+                [One, Other, Other, Other],
+            ),
+            (
+                Locale::Fr,
+                ["1", "1.5", "2", "1000000"],
+                [One, One, Other, Many],
+            ),
+            (
+                Locale::Es,
+                ["1", "1.0", "2", "3000000"],
+                [One, One, Other, Many],
+            ),
+            (
+                Locale::Pl,
+                ["1", "22", "12", "1.5"],
+                [One, Few, Many, Other],
+            ),
+            (Locale::Ru, ["21", "23", "11", "25"], [One, Few, Many, Many]),
+        ];
+        for (locale, numbers, expected) in cases {
+            for (number, category) in numbers.iter().zip(expected) {
+                assert_eq!(
+                    locale.plural_category(number),
+                    category,
+                    "{:?} {}",
+                    locale,
+                    number
+                );
+            }
+        }
+        assert_eq!(Locale::Pl.plural_category("100000001"), Many);
+        assert_eq!(Locale::En.plural_category("ééééé"), Other);
+        assert_eq!(Locale::Ru.plural_category("1.2.3"), Other);
+    }
+
+    #[test]
+    fn test_localized_amounts() {
+        let eur = |value| amount(value, CurrencyCode::EUR);
+        assert_eq!(eur("3").to_localized_string(Locale::En), "3 euros");
+        assert_eq!(eur("1").to_localized_string(Locale::En), "1 euro");
+        assert_eq!(eur("3").to_localized_string(Locale::De), "3 Euro");
+        assert_eq!(eur("1.50").to_localized_string(Locale::Fr), "1,5 euro");
+
+        let usd = |value| amount(value, CurrencyCode::USD);
+        assert_eq!(usd("2").to_localized_string(Locale::Pl), "2 dolary");
+        assert_eq!(usd("5").to_localized_string(Locale::Pl), "5 dolarów");
+        assert_eq!(usd("0.25").to_localized_string(Locale::Pl), "0,25 dolara");
+        assert_eq!(usd("21").to_localized_string(Locale::Ru), "21 доллар");
+        let eth = amount("3", CurrencyCode::ETH);
+        assert_eq!(eth.to_localized_string(Locale::Pl), "3 ethery");
+        assert_eq!(usd("-2").to_localized_string(Locale::Es), "-2 dólares");
+
+        let usdc = amount("2", CurrencyCode::USDC);
+        assert_eq!(usdc.to_localized_string(Locale::De), "2 USD Coins");
+        assert_eq!(
+            CurrencyCode::GBP
+                .get_name()
+                .localized(Locale::Fr, PluralCategory::One),
+            "livre"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Locale::parse("de-AT").unwrap(), Locale::De);
+        assert_eq!(Locale::parse("PL_pl").unwrap(), Locale::Pl);
+        assert_eq!(Locale::parse("ja").unwrap_err().code(), ErrorCode::Invalid);
+    }
+}
@@ -1,45 +1,60 @@
+use alloc::string::String;
+
+use utils::errors::Error;
+
 use super::code::CurrencyCode;
+use super::locale::{self, Locale, PluralCategory};
 use super::symbol::CurrencySymbol;
+use super::token::{self, TokenId};
 
-#[derive(Debug, Clone, PartialEq)]
-#[macros::json]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, macros::StrEnum)]
+#[macros::json(into = "String", try_from = "String")]
 pub enum CurrencyName {
+    #[default]
     Dollar,
     Bitcoin,
     Euro,
     Pound,
+    Ether,
+    Litecoin,
+    Tether,
+    #[str_enum(rename = "USD Coin")]
+    UsdCoin,
+    /// A token registered at runtime, see `currency::token`.
+    #[str_enum(
+        parse = |name| token::find(|token| token.name() == name),
+        as_str = |id: &TokenId| id.token().name()
+    )]
+    Token(TokenId),
 }
 
-impl<'a> CurrencyName {
-    pub fn new() -> CurrencyNameBuilder<'a> {
-        CurrencyNameBuilder::new()
-    }
-
-    pub fn to_string(&self) -> &str {
-        match self {
-            Self::Dollar => "Dollar",
-            Self::Bitcoin => "Bitcoin",
-            Self::Euro => "Euro",
-            Self::Pound => "Pound",
-        }
+impl CurrencyName {
+    pub fn to_string(&self) -> &'static str {
+        self.as_str()
     }
 
-    pub fn to_string_plural(&self) -> &str {
+    /// The English plural, e.g. "Dollars". See `localized` for other languages.
+    pub fn to_string_plural(&self) -> &'static str {
         match self {
             Self::Dollar => "Dollars",
             Self::Bitcoin => "Bitcoin",
             Self::Euro => "Euros",
             Self::Pound => "Pounds",
+            Self::Ether => "Ether",
+            Self::Litecoin => "Litecoins",
+            Self::Tether => "Tether",
+            Self::UsdCoin => "USD Coins",
+            Self::Token(id) => id.token().name(),
         }
     }
 
+    /// The name in a locale, in the plural form for a number of that category.
+    pub fn localized(&self, locale: Locale, category: PluralCategory) -> &'static str {
+        locale::name(self.get_code(), locale, category)
+    }
+
     pub fn get_symbol(&self) -> CurrencySymbol {
-        match self {
-            Self::Dollar => CurrencySymbol::USD,
-            Self::Bitcoin => CurrencySymbol::BTC,
-            Self::Euro => CurrencySymbol::EUR,
-            Self::Pound => CurrencySymbol::GBP,
-        }
+        self.get_code().get_symbol()
     }
 
     pub fn get_code(&self) -> CurrencyCode {
@@ -48,72 +63,39 @@ impl<'a> CurrencyName {
             Self::Bitcoin => CurrencyCode::BTC,
             Self::Euro => CurrencyCode::EUR,
             Self::Pound => CurrencyCode::GBP,
+            Self::Ether => CurrencyCode::ETH,
+            Self::Litecoin => CurrencyCode::LTC,
+            Self::Tether => CurrencyCode::USDT,
+            Self::UsdCoin => CurrencyCode::USDC,
+            Self::Token(id) => CurrencyCode::Token(*id),
         }
     }
 }
 
-impl Default for CurrencyName {
-    fn default() -> Self {
-        Self::Dollar
+impl From<CurrencyName> for String {
+    fn from(name: CurrencyName) -> Self {
+        name.as_str().into()
     }
 }
 
-pub struct CurrencyNameBuilder<'a> {
-    currency_name: Option<&'a str>,
-}
+impl TryFrom<String> for CurrencyName {
+    type Error = Error;
 
-impl<'a> CurrencyNameBuilder<'a> {
-    fn new() -> Self {
-        Self {
-            currency_name: None,
-        }
-    }
-
-    pub fn currency_name(&mut self, currency_name: &'a str) -> &mut Self {
-        self.currency_name = Some(currency_name);
-        self
-    }
-
-    pub fn build(&self) -> Option<CurrencyName> {
-        match self.currency_name {
-            Some(currency_name) => match currency_name {
-                "Dollar" => Some(CurrencyName::Dollar),
-                "Bitcoin" => Some(CurrencyName::Bitcoin),
-                "Euro" => Some(CurrencyName::Euro),
-                "Pound" => Some(CurrencyName::Pound),
-                _ => None,
-            },
-            None => None,
-        }
-    }
-}
-
-pub fn is_valid(currency_name: &str) -> bool {
-    match currency_name {
-        "Dollar" => true,
-        "Bitcoin" => true,
-        "Euro" => true,
-        "Pound" => true,
-        _ => false,
+    fn try_from(name: String) -> Result<Self, Error> {
+        name.parse()
     }
 }
 
 pub fn get_currency_name_from_code(currency_code: &str) -> Option<CurrencyName> {
-    match currency_code {
-        "USD" => Some(CurrencyName::Dollar),
-        "BTC" => Some(CurrencyName::Bitcoin),
-        "EUR" => Some(CurrencyName::Euro),
-        "GBP" => Some(CurrencyName::Pound),
-        _ => None,
-    }
+    currency_code
+        .parse::<CurrencyCode>()
+        .ok()
+        .map(|code| code.get_name())
 }
 
 pub fn get_currency_name_from_symbol(symbol: &str) -> Option<CurrencyName> {
-    match symbol {
-        "$" => Some(CurrencyName::Dollar),
-        "₿" => Some(CurrencyName::Bitcoin),
-        "€" => Some(CurrencyName::Euro),
-        "£" => Some(CurrencyName::Pound),
-        _ => None,
-    }
+    symbol
+        .parse::<CurrencySymbol>()
+        .ok()
+        .map(|symbol| symbol.get_name())
 }
@@ -0,0 +1,58 @@
+use core::fmt::{Display, Formatter};
+
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::code::CurrencyCode;
+use super::Currency;
+
+/// A market quoting the `base` currency in units of the `quote` currency, e.g. `BTC/USD`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[macros::json]
+pub struct CurrencyPair {
+    base: Currency,
+    quote: Currency,
+}
+
+impl CurrencyPair {
+    pub fn new(base: Currency, quote: Currency) -> Self {
+        Self { base, quote }
+    }
+
+    /// Parses a pair written as `BASE/QUOTE`, e.g. `"BTC/USD"`.
+    pub fn parse(pair: &str) -> Result<Self, Error> {
+        let invalid = || {
+            Error::new("Invalid currency pair", ErrorCode::Invalid)
+                .with_meta(ErrorMeta::new().add("pair", pair).build())
+        };
+
+        let (base, quote) = pair.split_once('/').ok_or_else(invalid)?;
+        let base: CurrencyCode = base.trim().parse().map_err(|_| invalid())?;
+        let quote: CurrencyCode = quote.trim().parse().map_err(|_| invalid())?;
+
+        Ok(Self::new(base.into(), quote.into()))
+    }
+
+    pub fn base(&self) -> &Currency {
+        &self.base
+    }
+
+    pub fn quote(&self) -> &Currency {
+        &self.quote
+    }
+
+    /// The same market quoted the other way around.
+    pub fn inverse(&self) -> Self {
+        Self::new(self.quote.clone(), self.base.clone())
+    }
+}
+
+impl Display for CurrencyPair {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "{}/{}",
+            self.base.code().to_string(),
+            self.quote.code().to_string()
+        )
+    }
+}
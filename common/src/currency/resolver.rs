@@ -0,0 +1,301 @@
+//! Resolution of free-form currency input.
+//!
+//! Users and exchange exports write currencies in many ways: `usd`, `Dollars`, `XBT`, `sats`,
+//! `US$`, `€`. A `CurrencyResolver` accepts codes, names, plurals, symbols and aliases in any
+//! case, for the built-in currencies, registered tokens and aliases added to the resolver. When
+//! nothing matches, the error suggests the closest currencies.
+//!
+//! Aliases name a currency, not a denomination: `sats` resolves to BTC, and amounts are still
+//! read in whole units of it.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::code::CurrencyCode;
+use super::token;
+
+/// The most suggestions returned on failure.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Well-known alternative spellings of the built-in currencies.
+const ALIASES: [(&str, CurrencyCode); 14] = [
+    ("US$", CurrencyCode::USD),
+    ("US Dollar", CurrencyCode::USD),
+    ("US Dollars", CurrencyCode::USD),
+    ("XBT", CurrencyCode::BTC),
+    ("Bitcoins", CurrencyCode::BTC),
+    ("sat", CurrencyCode::BTC),
+    ("sats", CurrencyCode::BTC),
+    ("satoshi", CurrencyCode::BTC),
+    ("satoshis", CurrencyCode::BTC),
+    ("Sterling", CurrencyCode::GBP),
+    ("Pound Sterling", CurrencyCode::GBP),
+    ("Ethereum", CurrencyCode::ETH),
+    ("Ethers", CurrencyCode::ETH),
+    ("USD₮", CurrencyCode::USDT),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct CurrencyResolver {
+    aliases: BTreeMap<String, CurrencyCode>,
+}
+
+impl CurrencyResolver {
+    pub fn new() -> CurrencyResolverBuilder {
+        CurrencyResolverBuilder::new()
+    }
+
+    /// The currency the input names. Fails with `ErrorCode::Invalid` if it names none, with the
+    /// closest matches in the `suggestions` metadata.
+    pub fn resolve(&self, input: &str) -> Result<CurrencyCode, Error> {
+        let key = normalize(input);
+        if let Some(code) = self.candidates().get(&key) {
+            return Ok(*code);
+        }
+
+        let suggestions = self.suggest(input);
+        let suggestions: Vec<&str> = suggestions.iter().map(|code| code.to_string()).collect();
+        Err(
+            Error::new("Unknown currency", ErrorCode::Invalid).with_meta(
+                ErrorMeta::new()
+                    .add("input", input)
+                    .add("suggestions", suggestions.join(", ").as_str())
+                    .build(),
+            ),
+        )
+    }
+
+    /// The currencies closest to the input, best first: those with a spelling starting with it,
+    /// then those within a couple of typos.
+    pub fn suggest(&self, input: &str) -> Vec<CurrencyCode> {
+        let key = normalize(input);
+        if key.is_empty() {
+            return Vec::new();
+        }
+        let tolerance = (key.chars().count() / 3).clamp(1, 2);
+
+        let mut ranked: Vec<(usize, String, CurrencyCode)> = self
+            .candidates()
+            .into_iter()
+            .filter_map(|(spelling, code)| {
+                let rank = if spelling.starts_with(&key) {
+                    0
+                } else {
+                    match distance(&key, &spelling) {
+                        distance if distance <= tolerance => distance,
+                        _ => return None,
+                    }
+                };
+                Some((rank, spelling, code))
+            })
+            .collect();
+        ranked.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+
+        let mut suggestions = Vec::new();
+        for (_, _, code) in ranked {
+            if !suggestions.contains(&code) {
+                suggestions.push(code);
+            }
+        }
+        suggestions.truncate(MAX_SUGGESTIONS);
+        suggestions
+    }
+
+    /// Every accepted spelling, normalized, with the currency it names. Built-in spellings win
+    /// over registered tokens, and aliases added to the resolver over both.
+    fn candidates(&self) -> BTreeMap<String, CurrencyCode> {
+        let mut candidates = BTreeMap::new();
+        for code in CurrencyCode::ALL.into_iter().chain(token::codes()) {
+            for spelling in spellings(code) {
+                candidates.entry(spelling).or_insert(code);
+            }
+        }
+        for (alias, code) in ALIASES {
+            candidates.entry(normalize(alias)).or_insert(code);
+        }
+        candidates.extend(self.aliases.clone());
+        candidates
+    }
+}
+
+pub struct CurrencyResolverBuilder {
+    aliases: BTreeMap<String, CurrencyCode>,
+}
+
+impl CurrencyResolverBuilder {
+    pub fn new() -> Self {
+        Self {
+            aliases: BTreeMap::new(),
+        }
+    }
+
+    /// Another spelling of a currency, matched in any case.
+    pub fn alias(mut self, alias: &str, code: CurrencyCode) -> Self {
+        self.aliases.insert(normalize(alias), code);
+        self
+    }
+
+    /// Fails with `ErrorCode::Conflict` if an alias is already a spelling of another currency.
+    pub fn build(self) -> Result<CurrencyResolver, Error> {
+        let builtin = CurrencyResolver::default().candidates();
+        for (alias, code) in &self.aliases {
+            if let Some(existing) = builtin.get(alias).filter(|existing| *existing != code) {
+                return Err(
+                    Error::new("Alias names another currency", ErrorCode::Conflict).with_meta(
+                        ErrorMeta::new()
+                            .add("alias", alias)
+                            .add("code", code.to_string())
+                            .add("existing", existing.to_string())
+                            .build(),
+                    ),
+                );
+            }
+        }
+
+        Ok(CurrencyResolver {
+            aliases: self.aliases,
+        })
+    }
+}
+
+impl Default for CurrencyResolverBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resolves input with the built-in spellings and registered tokens.
+pub fn resolve(input: &str) -> Result<CurrencyCode, Error> {
+    CurrencyResolver::default().resolve(input)
+}
+
+/// Whether the input is a spelling of a built-in currency, its well-known aliases included.
+///
+/// Unlike `resolve` this does not look at registered tokens, so it can be called while the
+/// token registry is locked.
+pub(crate) fn is_builtin(input: &str) -> bool {
+    let key = normalize(input);
+    CurrencyCode::ALL
+        .into_iter()
+        .any(|code| spellings(code).contains(&key))
+        || ALIASES.iter().any(|(alias, _)| normalize(alias) == key)
+}
+
+/// The normalized code, name, plural name and symbol of a currency.
+fn spellings(code: CurrencyCode) -> [String; 4] {
+    let name = code.get_name();
+    [
+        normalize(code.to_string()),
+        normalize(name.to_string()),
+        normalize(name.to_string_plural()),
+        normalize(code.get_symbol().get_symbol()),
+    ]
+}
+
+/// Lower case with surrounding whitespace trimmed and inner whitespace collapsed.
+pub(crate) fn normalize(input: &str) -> String {
+    input
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// The number of single-character insertions, deletions, substitutions and transpositions of
+/// adjacent characters that turn one string into the other.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolves_spellings() {
+        for (input, code) in [
+            ("usd", CurrencyCode::USD),
+            ("Dollars", CurrencyCode::USD),
+            (" us$ ", CurrencyCode::USD),
+            ("$", CurrencyCode::USD),
+            ("XBT", CurrencyCode::BTC),
+            ("sats", CurrencyCode::BTC),
+            ("₿", CurrencyCode::BTC),
+            ("€", CurrencyCode::EUR),
+            ("EUROS", CurrencyCode::EUR),
+            ("pound  sterling", CurrencyCode::GBP),
+            ("ł", CurrencyCode::LTC),
+            ("usd coin", CurrencyCode::USDC),
+        ] {
+            assert_eq!(resolve(input).unwrap(), code, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let error = resolve("eruo").unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("suggestions"), Some("EUR"));
+
+        let resolver = CurrencyResolver::default();
+        assert_eq!(resolver.suggest("bitcon"), vec![CurrencyCode::BTC]);
+        assert_eq!(resolver.suggest("us")[0], CurrencyCode::USD);
+        assert!(resolver.suggest("zzzzzz").is_empty());
+        assert!(resolver.suggest("").is_empty());
+    }
+
+    #[test]
+    fn test_custom_aliases() {
+        let resolver = CurrencyResolver::new()
+            .alias("Greenback", CurrencyCode::USD)
+            .build()
+            .unwrap();
+        assert_eq!(
+            resolver.resolve("GREENBACKS").unwrap_err().code(),
+            ErrorCode::Invalid
+        );
+        assert_eq!(resolver.resolve("greenback").unwrap(), CurrencyCode::USD);
+        assert_eq!(resolver.suggest("greenbak"), vec![CurrencyCode::USD]);
+
+        let error = CurrencyResolver::new()
+            .alias("Euro", CurrencyCode::GBP)
+            .build()
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Conflict);
+        assert_eq!(error.meta_value("existing"), Some("EUR"));
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("eruo", "euro"), 1);
+        assert_eq!(distance("€", "€"), 0);
+    }
+}
@@ -1,36 +1,47 @@
+use alloc::string::String;
+
+use utils::errors::Error;
+
 use super::code::CurrencyCode;
 use super::name::CurrencyName;
+use super::token::{self, TokenId};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[macros::json]
+/// A currency's glyph, e.g. `€`. Symbols also parse from their currency code, which is what
+/// they are written as in JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, macros::StrEnum)]
+#[macros::json(into = "String", try_from = "String")]
 pub enum CurrencySymbol {
+    #[default]
+    #[str_enum(rename = "$", alias = "USD")]
     USD,
+    #[str_enum(rename = "₿", alias = "BTC")]
     BTC,
+    #[str_enum(rename = "€", alias = "EUR")]
     EUR,
+    #[str_enum(rename = "£", alias = "GBP")]
     GBP,
+    #[str_enum(rename = "Ξ", alias = "ETH")]
+    ETH,
+    #[str_enum(rename = "Ł", alias = "LTC")]
+    LTC,
+    #[str_enum(rename = "₮", alias = "USDT")]
+    USDT,
+    USDC,
+    /// A token registered at runtime, see `currency::token`.
+    #[str_enum(
+        parse = |symbol| token::find(|token| token.code() == symbol || token.symbol() == symbol),
+        as_str = |id: &TokenId| id.token().symbol()
+    )]
+    Token(TokenId),
 }
 
 impl CurrencySymbol {
-    pub fn new<'a>() -> SymbolBuilder<'a> {
-        SymbolBuilder::new()
-    }
-
-    pub fn get_symbol(&self) -> &str {
-        match self {
-            CurrencySymbol::BTC => "₿",
-            CurrencySymbol::USD => "$",
-            CurrencySymbol::EUR => "€",
-            CurrencySymbol::GBP => "£",
-        }
+    pub fn get_symbol(&self) -> &'static str {
+        self.as_str()
     }
 
     pub fn get_name(&self) -> CurrencyName {
-        match self {
-            CurrencySymbol::BTC => CurrencyName::Bitcoin,
-            CurrencySymbol::USD => CurrencyName::Dollar,
-            CurrencySymbol::EUR => CurrencyName::Euro,
-            CurrencySymbol::GBP => CurrencyName::Pound,
-        }
+        self.get_code().get_name()
     }
 
     pub fn get_code(&self) -> CurrencyCode {
@@ -39,6 +50,11 @@ impl CurrencySymbol {
             CurrencySymbol::USD => CurrencyCode::USD,
             CurrencySymbol::EUR => CurrencyCode::EUR,
             CurrencySymbol::GBP => CurrencyCode::GBP,
+            CurrencySymbol::ETH => CurrencyCode::ETH,
+            CurrencySymbol::LTC => CurrencyCode::LTC,
+            CurrencySymbol::USDT => CurrencyCode::USDT,
+            CurrencySymbol::USDC => CurrencyCode::USDC,
+            CurrencySymbol::Token(id) => CurrencyCode::Token(*id),
         }
     }
 
@@ -48,75 +64,34 @@ impl CurrencySymbol {
             CurrencySymbol::USD => 2,
             CurrencySymbol::EUR => 2,
             CurrencySymbol::GBP => 2,
+            CurrencySymbol::ETH => 18,
+            CurrencySymbol::LTC => 8,
+            CurrencySymbol::USDT => 6,
+            CurrencySymbol::USDC => 6,
+            CurrencySymbol::Token(id) => id.token().decimals(),
         }
     }
 }
 
-impl Default for CurrencySymbol {
-    fn default() -> Self {
-        Self::USD
-    }
-}
-
-impl std::fmt::Display for CurrencySymbol {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.get_symbol())
+/// Symbols are written as their currency code in JSON, as before tokens existed.
+impl From<CurrencySymbol> for String {
+    fn from(symbol: CurrencySymbol) -> Self {
+        symbol.get_code().to_string().into()
     }
 }
 
-pub struct SymbolBuilder<'a> {
-    symbol: Option<&'a str>,
-}
-
-impl<'a> SymbolBuilder<'a> {
-    fn new() -> Self {
-        Self { symbol: None }
-    }
+impl TryFrom<String> for CurrencySymbol {
+    type Error = Error;
 
-    pub fn symbol(&mut self, symbol: &'a str) -> &mut Self {
-        self.symbol = Some(symbol);
-        self
-    }
-
-    pub fn build(&self) -> Option<CurrencySymbol> {
-        if let Some(symbol) = self.symbol {
-            match symbol {
-                "USD" => Some(CurrencySymbol::USD),
-                "BTC" => Some(CurrencySymbol::BTC),
-                "EUR" => Some(CurrencySymbol::EUR),
-                "GBP" => Some(CurrencySymbol::GBP),
-                _ => None,
-            }
-        } else {
-            None
-        }
-    }
-}
-
-pub fn is_valid(symbol: &str) -> bool {
-    match symbol {
-        "USD" => true,
-        "BTC" => true,
-        "EUR" => true,
-        "GBP" => true,
-        _ => false,
+    fn try_from(symbol: String) -> Result<Self, Error> {
+        symbol.parse()
     }
 }
 
 pub fn get_symbol_from_code(code: &CurrencyCode) -> CurrencySymbol {
-    match code {
-        CurrencyCode::USD => CurrencySymbol::USD,
-        CurrencyCode::BTC => CurrencySymbol::BTC,
-        CurrencyCode::EUR => CurrencySymbol::EUR,
-        CurrencyCode::GBP => CurrencySymbol::GBP,
-    }
+    code.get_symbol()
 }
 
 pub fn get_symbol_from_name(name: &CurrencyName) -> CurrencySymbol {
-    match name {
-        CurrencyName::Dollar => CurrencySymbol::USD,
-        CurrencyName::Bitcoin => CurrencySymbol::BTC,
-        CurrencyName::Euro => CurrencySymbol::EUR,
-        CurrencyName::Pound => CurrencySymbol::GBP,
-    }
+    name.get_symbol()
 }
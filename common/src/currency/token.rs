@@ -0,0 +1,403 @@
+//! Tokens declared at runtime.
+//!
+//! Assets other than the built-in currencies are registered once, typically at startup from a
+//! JSON config, and are then resolved by the `CurrencyCode`, `CurrencyName` and `CurrencySymbol`
+//! builders like any other currency:
+//!
+//! ```json
+//! [{"code": "DAI", "name": "Dai", "symbol": "◈", "decimals": 18, "chain": "ethereum"}]
+//! ```
+//!
+//! Registered tokens live for the rest of the process. Loading them from JSON needs the `std`
+//! feature; without it tokens can still be registered one by one.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+#[cfg(feature = "std")]
+use utils::json::JSON;
+
+use super::code::CurrencyCode;
+use super::resolver;
+
+/// The most decimal places a token may declare. At 18 the 128 bits `Money` is stored in still
+/// hold about 1.7 × 10^20 whole units; the product of two such amounts does not fit, so prices
+/// and quantities are multiplied in 256 bits and scaled back down before they are stored.
+pub const MAX_DECIMALS: u32 = 18;
+
+static TOKENS: Registry = Registry::new();
+
+/// The registered tokens behind a lock: a `RwLock` with the standard library, and a spin lock
+/// without it.
+#[cfg(feature = "std")]
+struct Registry(std::sync::RwLock<Vec<&'static Token>>);
+
+#[cfg(feature = "std")]
+impl Registry {
+    const fn new() -> Self {
+        Self(std::sync::RwLock::new(Vec::new()))
+    }
+
+    fn read<R>(&self, f: impl FnOnce(&Vec<&'static Token>) -> R) -> R {
+        f(&self
+            .0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+
+    fn write<R>(&self, f: impl FnOnce(&mut Vec<&'static Token>) -> R) -> R {
+        f(&mut self
+            .0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+#[cfg(not(feature = "std"))]
+struct Registry {
+    locked: core::sync::atomic::AtomicBool,
+    tokens: core::cell::UnsafeCell<Vec<&'static Token>>,
+}
+
+// Safety: the tokens are only reached through `write`, which holds the lock.
+#[cfg(not(feature = "std"))]
+unsafe impl Sync for Registry {}
+
+#[cfg(not(feature = "std"))]
+impl Registry {
+    const fn new() -> Self {
+        Self {
+            locked: core::sync::atomic::AtomicBool::new(false),
+            tokens: core::cell::UnsafeCell::new(Vec::new()),
+        }
+    }
+
+    fn read<R>(&self, f: impl FnOnce(&Vec<&'static Token>) -> R) -> R {
+        self.write(|tokens| f(tokens))
+    }
+
+    fn write<R>(&self, f: impl FnOnce(&mut Vec<&'static Token>) -> R) -> R {
+        use core::sync::atomic::Ordering;
+
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        // Safety: the lock is held until the closure returns.
+        let result = f(unsafe { &mut *self.tokens.get() });
+        self.locked.store(false, Ordering::Release);
+        result
+    }
+}
+
+/// Identifies a registered token. Only the registry hands these out, so every id resolves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenId(u16);
+
+impl TokenId {
+    /// The token's definition.
+    pub fn token(&self) -> &'static Token {
+        TOKENS.read(|tokens| tokens[self.0 as usize])
+    }
+}
+
+/// The definition of a token: its code, name, symbol, decimal places and the chain it lives on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[macros::json]
+pub struct Token {
+    code: String,
+    name: String,
+    symbol: String,
+    decimals: u32,
+    chain: String,
+}
+
+impl Token {
+    pub fn new(code: &str, name: &str, symbol: &str, decimals: u32, chain: &str) -> Self {
+        Self {
+            code: code.into(),
+            name: name.into(),
+            symbol: symbol.into(),
+            decimals,
+            chain: chain.into(),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+
+    /// The chain the token is issued on, e.g. `"ethereum"`.
+    pub fn chain(&self) -> &str {
+        &self.chain
+    }
+
+    /// Fails with `ErrorCode::Invalid` if the code is not 2 to 10 upper case letters or digits,
+    /// the name, symbol or chain is blank, or there are more than `MAX_DECIMALS` decimals.
+    fn validate(&self) -> Result<(), Error> {
+        let reason = if !(2..=10).contains(&self.code.len())
+            || !self
+                .code
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        {
+            Some("codes must be 2 to 10 upper case letters or digits")
+        } else if self.name.trim().is_empty() {
+            Some("missing name")
+        } else if self.symbol.trim().is_empty() {
+            Some("missing symbol")
+        } else if self.chain.trim().is_empty() {
+            Some("missing chain")
+        } else if self.decimals > MAX_DECIMALS {
+            Some("too many decimal places")
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => Err(Error::new("Invalid token", ErrorCode::Invalid).with_meta(
+                ErrorMeta::new()
+                    .add("code", self.code.as_str())
+                    .add("reason", reason)
+                    .build(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Which field, if any, is spelled like a built-in currency or another token once case and
+    /// whitespace are ignored, as `resolver` reads them.
+    fn clash(&self, tokens: &[&Token]) -> Option<&'static str> {
+        let taken: Vec<String> = tokens
+            .iter()
+            .flat_map(|token| token.spellings().map(|(_, spelling)| spelling))
+            .collect();
+        self.spellings()
+            .into_iter()
+            .find(|(_, spelling)| resolver::is_builtin(spelling) || taken.contains(spelling))
+            .map(|(field, _)| field)
+    }
+
+    /// The normalized code, name and symbol, which is also the plural name.
+    fn spellings(&self) -> [(&'static str, String); 3] {
+        [
+            ("code", resolver::normalize(&self.code)),
+            ("name", resolver::normalize(&self.name)),
+            ("symbol", resolver::normalize(&self.symbol)),
+        ]
+    }
+}
+
+/// Registers a token, returning its currency code.
+///
+/// Registering an identical definition again returns the existing code. Fails with
+/// `ErrorCode::Invalid` if the definition is invalid, and with `ErrorCode::Conflict` if its
+/// code, name or symbol is already a spelling of a built-in currency or a different token, in
+/// any case.
+pub fn register(token: Token) -> Result<CurrencyCode, Error> {
+    token.validate()?;
+
+    TOKENS.write(|tokens| insert(tokens, token))
+}
+
+fn insert(tokens: &mut Vec<&'static Token>, token: Token) -> Result<CurrencyCode, Error> {
+    if let Some(index) = tokens.iter().position(|existing| **existing == token) {
+        return Ok(CurrencyCode::Token(TokenId(index as u16)));
+    }
+
+    if let Some(field) = token.clash(tokens) {
+        return Err(
+            Error::new("Currency already defined", ErrorCode::Conflict).with_meta(
+                ErrorMeta::new()
+                    .add("code", token.code.as_str())
+                    .add("field", field)
+                    .build(),
+            ),
+        );
+    }
+    if tokens.len() >= u16::MAX as usize {
+        return Err(Error::new("Too many tokens", ErrorCode::Invalid).with_meta(
+            ErrorMeta::new()
+                .add("code", token.code.as_str())
+                .add("limit", u16::MAX.to_string().as_str())
+                .build(),
+        ));
+    }
+
+    tokens.push(Box::leak(Box::new(token)));
+    Ok(CurrencyCode::Token(TokenId(tokens.len() as u16 - 1)))
+}
+
+/// Registers every token in a JSON array of definitions, stopping at the first that fails.
+#[cfg(feature = "std")]
+pub fn load(json: &str) -> Result<Vec<CurrencyCode>, Error> {
+    Vec::<Token>::from_json(json)?
+        .into_iter()
+        .map(register)
+        .collect()
+}
+
+/// Registers the tokens in a JSON config file. Fails with `ErrorCode::NotFound` if the file
+/// cannot be read.
+#[cfg(feature = "std")]
+pub fn load_file(path: impl AsRef<Path>) -> Result<Vec<CurrencyCode>, Error> {
+    let path = path.as_ref();
+    let json = std::fs::read_to_string(path).map_err(|err| {
+        Error::new("Token config not readable", ErrorCode::NotFound).with_meta(
+            ErrorMeta::new()
+                .add("path", path.display().to_string().as_str())
+                .add("reason", err.to_string().as_str())
+                .build(),
+        )
+    })?;
+    load(&json)
+}
+
+/// Every registered token, in registration order.
+pub fn tokens() -> Vec<&'static Token> {
+    TOKENS.read(|tokens| tokens.clone())
+}
+
+/// The currency codes of every registered token, in registration order.
+pub(crate) fn codes() -> Vec<CurrencyCode> {
+    let count = TOKENS.read(|tokens| tokens.len());
+    (0..count)
+        .map(|index| CurrencyCode::Token(TokenId(index as u16)))
+        .collect()
+}
+
+/// The first registered token matching the predicate.
+pub(crate) fn find(predicate: impl Fn(&Token) -> bool) -> Option<TokenId> {
+    TOKENS.read(|tokens| {
+        tokens
+            .iter()
+            .position(|token| predicate(token))
+            .map(|index| TokenId(index as u16))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::name::CurrencyName;
+    use crate::currency::symbol::CurrencySymbol;
+    use crate::currency::Currency;
+    use crate::money::Money;
+
+    #[test]
+    fn test_load_and_resolve() {
+        let codes = load(
+            r#"[{"code": "TSTA", "name": "Test A", "symbol": "ⓐ", "decimals": 12, "chain": "ethereum"},
+                {"code": "TSTB", "name": "Test B", "symbol": "ⓑ", "decimals": 0, "chain": "solana"}]"#,
+        )
+        .unwrap();
+        assert_eq!(codes.len(), 2);
+
+        let code: CurrencyCode = "TSTA".parse().unwrap();
+        assert_eq!(code, codes[0]);
+        assert_eq!("tsta".parse::<CurrencyCode>().unwrap(), code);
+        assert_eq!(code.to_string(), "TSTA");
+        assert!(!code.is_fiat());
+        assert_eq!(code.token().unwrap().chain(), "ethereum");
+
+        let name: CurrencyName = "Test A".parse().unwrap();
+        assert_eq!(name, code.get_name());
+        let symbol: CurrencySymbol = "ⓐ".parse().unwrap();
+        assert_eq!(symbol.get_code(), code);
+        assert_eq!("TSTA".parse::<CurrencySymbol>().unwrap(), symbol);
+
+        let money = Money::parse("1.5", Currency::from(code)).unwrap();
+        assert_eq!(money.amount(), 1_500_000_000_000);
+        assert_eq!(money.to_string(), "ⓐ1.500000000000");
+
+        let json = money.to_json().unwrap();
+        assert!(json.contains(r#""code":"TSTA""#));
+        assert_eq!(Money::from_json(&json).unwrap(), money);
+    }
+
+    #[test]
+    fn test_rejects_clashes() {
+        let token = Token::new("TSTC", "Test C", "ⓒ", 6, "ethereum");
+        let code = register(token.clone()).unwrap();
+        assert_eq!(register(token).unwrap(), code);
+
+        let renamed = Token::new("TSTC", "Test C2", "ⓒ", 6, "ethereum");
+        assert_eq!(register(renamed).unwrap_err().code(), ErrorCode::Conflict);
+
+        let builtin = Token::new("ETH", "Ether2", "E2", 18, "ethereum");
+        let error = register(builtin).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Conflict);
+        assert_eq!(error.meta_value("field"), Some("code"));
+
+        let dollar = Token::new("TSTD", "Test D", "$", 2, "ethereum");
+        assert_eq!(register(dollar).unwrap_err().code(), ErrorCode::Conflict);
+
+        // Spellings clash in any case, plurals and aliases included.
+        for (token, field) in [
+            (Token::new("DLRS", "Dollars", "ⓓ", 2, "ethereum"), "name"),
+            (Token::new("EURX", "EURO", "ⓔ", 2, "ethereum"), "name"),
+            (Token::new("TSTH", "Test H", "xbt", 8, "ethereum"), "symbol"),
+            (Token::new("TSTH", "test  c", "ⓗ", 6, "ethereum"), "name"),
+        ] {
+            let error = register(token).unwrap_err();
+            assert_eq!(error.code(), ErrorCode::Conflict);
+            assert_eq!(error.meta_value("field"), Some(field));
+        }
+        assert_eq!(resolver::resolve("dollars").unwrap(), CurrencyCode::USD);
+        assert_eq!(resolver::resolve("Euro").unwrap(), CurrencyCode::EUR);
+    }
+
+    #[test]
+    fn test_rejects_invalid_tokens() {
+        for token in [
+            Token::new("tste", "Test E", "ⓔ", 6, "ethereum"),
+            Token::new("TSTE", " ", "ⓔ", 6, "ethereum"),
+            Token::new("TSTE", "Test E", "ⓔ", 19, "ethereum"),
+            Token::new("TSTE", "Test E", "ⓔ", 6, ""),
+        ] {
+            assert_eq!(register(token).unwrap_err().code(), ErrorCode::Invalid);
+        }
+        assert!(load("{}").is_err());
+        assert_eq!(
+            load_file("/nonexistent/tokens.json").unwrap_err().code(),
+            ErrorCode::NotFound
+        );
+    }
+
+    #[test]
+    fn test_capacity() {
+        // Token ids are u16s: fill a private registry up to the last free id.
+        let filler: &'static Token =
+            Box::leak(Box::new(Token::new("TSTF", "Test F", "ⓕ", 6, "ethereum")));
+        let mut tokens = vec![filler; u16::MAX as usize - 1];
+
+        let last = Token::new("TSTG", "Test G", "ⓖ", 6, "ethereum");
+        let code = insert(&mut tokens, last).unwrap();
+        assert_eq!(code, CurrencyCode::Token(TokenId(u16::MAX - 1)));
+
+        let overflow = Token::new("TSTH", "Test H", "ⓗ", 6, "ethereum");
+        let error = insert(&mut tokens, overflow).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("code"), Some("TSTH"));
+        assert_eq!(tokens.len(), u16::MAX as usize);
+    }
+}
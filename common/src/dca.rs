@@ -0,0 +1,357 @@
+//! Dollar-cost averaging.
+//!
+//! A `DcaPlan` buys BTC for a fixed fiat amount at a regular interval. Simulating it over a
+//! historical price series buys at the latest price at or before each scheduled time, and
+//! reports the total invested, the BTC accumulated, the average cost and the current value at
+//! the last price of the series, alongside what investing the same total all at once at the
+//! first purchase would be worth now.
+
+pub mod lump_sum;
+pub mod purchase;
+pub mod report;
+
+use chrono::{DateTime, Duration, Utc};
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::currency::code::CurrencyCode;
+use super::currency::Currency;
+use super::money::{ensure_currency, from_wide, mul_div_round, out_of_range, Money};
+use super::price::{PricePoint, PriceSeries};
+
+pub use lump_sum::LumpSum;
+pub use purchase::Purchase;
+pub use report::DcaReport;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DcaPlan {
+    amount: Money,
+    interval: Duration,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+}
+
+impl DcaPlan {
+    pub fn new(amount: Money, interval: Duration) -> DcaPlanBuilder {
+        DcaPlanBuilder::new(amount, interval)
+    }
+
+    /// The fiat amount of every purchase.
+    pub fn amount(&self) -> &Money {
+        &self.amount
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// The purchase times within the series: every interval from the plan's start, or the
+    /// first point, up to the plan's end, or the last point.
+    pub fn schedule(&self, prices: &PriceSeries) -> Vec<DateTime<Utc>> {
+        let (Some(first), Some(last)) = (prices.first(), prices.last()) else {
+            return Vec::new();
+        };
+        let start = self.start.unwrap_or(first.timestamp());
+        let end = self
+            .end
+            .map_or(last.timestamp(), |end| end.min(last.timestamp()));
+
+        let mut schedule = Vec::new();
+        let mut timestamp = start;
+        while timestamp <= end {
+            schedule.push(timestamp);
+            timestamp += self.interval;
+        }
+        schedule
+    }
+
+    /// Simulates the plan over a BTC price series quoted in the plan's currency.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the series is in another currency, and with
+    /// `ErrorCode::NotFound` if it has no price at or before the first purchase.
+    pub fn simulate(&self, prices: &PriceSeries) -> Result<DcaReport, Error> {
+        let fiat = self.amount.currency();
+        ensure_currency(fiat, prices.currency())?;
+        let btc = Currency::from(CurrencyCode::BTC);
+        let schedule = self.schedule(prices);
+        let Some(last) = prices.last() else {
+            return Err(Error::new(
+                "No prices to simulate over",
+                ErrorCode::NotFound,
+            ));
+        };
+        let Some(start) = schedule.first() else {
+            return Err(
+                Error::new("No purchases within the price series", ErrorCode::NotFound).with_meta(
+                    ErrorMeta::new()
+                        .add("last", last.timestamp().to_rfc3339().as_str())
+                        .build(),
+                ),
+            );
+        };
+
+        let mut purchases = Vec::with_capacity(schedule.len());
+        let mut invested: i128 = 0;
+        let mut accumulated: i128 = 0;
+        for timestamp in &schedule {
+            let point = price_at(prices, *timestamp)?;
+            let quantity = quantity(self.amount.amount(), point.price().amount(), &btc)?;
+            invested = invested
+                .checked_add(self.amount.amount())
+                .ok_or_else(|| out_of_range("invested", fiat))?;
+            accumulated = accumulated
+                .checked_add(quantity)
+                .ok_or_else(|| out_of_range("accumulated", &btc))?;
+            purchases.push(Purchase::new(
+                *timestamp,
+                point.price().clone(),
+                self.amount.clone(),
+                from_wide(quantity, &btc)?,
+            ));
+        }
+
+        let scale = 10i128.pow(btc.decimal_places());
+        let current_price = last.price().amount();
+        let average_cost = match accumulated {
+            0 => None,
+            _ => {
+                let average_cost = mul_div_round(invested, scale, accumulated)
+                    .ok_or_else(|| out_of_range("average_cost", fiat))?;
+                Some(from_wide(average_cost, fiat)?)
+            }
+        };
+        let current_value = mul_div_round(accumulated, current_price, scale)
+            .ok_or_else(|| out_of_range("current_value", fiat))?;
+
+        let first = price_at(prices, *start)?;
+        let lump_quantity = quantity(invested, first.price().amount(), &btc)?;
+        let lump_value = mul_div_round(lump_quantity, current_price, scale)
+            .ok_or_else(|| out_of_range("lump_sum_value", fiat))?;
+        let lump_sum = LumpSum::new(
+            *start,
+            first.price().clone(),
+            from_wide(invested, fiat)?,
+            from_wide(lump_quantity, &btc)?,
+            from_wide(lump_value, fiat)?,
+        );
+
+        Ok(DcaReport::new(
+            purchases,
+            from_wide(invested, fiat)?,
+            from_wide(accumulated, &btc)?,
+            average_cost,
+            last.price().clone(),
+            from_wide(current_value, fiat)?,
+            lump_sum,
+        ))
+    }
+}
+
+pub struct DcaPlanBuilder {
+    amount: Money,
+    interval: Duration,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+}
+
+impl DcaPlanBuilder {
+    pub fn new(amount: Money, interval: Duration) -> Self {
+        Self {
+            amount,
+            interval,
+            start: None,
+            end: None,
+        }
+    }
+
+    /// The time of the first purchase. Defaults to the first point of the simulated series.
+    pub fn start(mut self, start: DateTime<Utc>) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// No purchases are made after this time. Defaults to the last point of the simulated
+    /// series.
+    pub fn end(mut self, end: DateTime<Utc>) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    /// Fails with `ErrorCode::Invalid` if the amount is not a positive fiat amount, the interval
+    /// is not positive, or the plan ends before it starts.
+    pub fn build(self) -> Result<DcaPlan, Error> {
+        let invalid = |message: &str| {
+            Error::new(message, ErrorCode::Invalid).with_meta(
+                ErrorMeta::new()
+                    .add("amount", self.amount.to_string().as_str())
+                    .add("interval", self.interval.to_string().as_str())
+                    .build(),
+            )
+        };
+        if !self.amount.currency().code().is_fiat() {
+            return Err(invalid("DCA amounts must be in a fiat currency"));
+        }
+        if self.amount.amount() <= 0 {
+            return Err(invalid("DCA amounts must be positive"));
+        }
+        if self.interval <= Duration::zero() {
+            return Err(invalid("DCA intervals must be positive"));
+        }
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            if end < start {
+                return Err(invalid("DCA plan ends before it starts"));
+            }
+        }
+
+        Ok(DcaPlan {
+            amount: self.amount,
+            interval: self.interval,
+            start: self.start,
+            end: self.end,
+        })
+    }
+}
+
+/// The latest point at or before the timestamp.
+fn price_at(prices: &PriceSeries, timestamp: DateTime<Utc>) -> Result<&PricePoint, Error> {
+    let index = prices
+        .points()
+        .partition_point(|point| point.timestamp() <= timestamp);
+    match index {
+        0 => Err(
+            Error::new("No price at or before purchase", ErrorCode::NotFound).with_meta(
+                ErrorMeta::new()
+                    .add("timestamp", timestamp.to_rfc3339().as_str())
+                    .build(),
+            ),
+        ),
+        _ => Ok(&prices.points()[index - 1]),
+    }
+}
+
+/// The BTC minor units `amount` buys at `price`, rounded down.
+fn quantity(amount: i128, price: i128, btc: &Currency) -> Result<i128, Error> {
+    if price <= 0 {
+        return Ok(0);
+    }
+    amount
+        .checked_mul(10i128.pow(btc.decimal_places()))
+        .map(|scaled| scaled / price)
+        .ok_or_else(|| out_of_range("quantity", btc))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use utils::json::JSON;
+
+    use super::*;
+    use crate::test_support::usd;
+
+    fn day(day: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap() + Duration::days(day)
+    }
+
+    /// Daily prices in whole dollars.
+    fn series(prices: &[i64]) -> PriceSeries {
+        let points = prices
+            .iter()
+            .enumerate()
+            .map(|(d, price)| PricePoint::new(day(d as i64), usd(&price.to_string())))
+            .collect();
+        PriceSeries::from_points(Currency::from(CurrencyCode::USD), points).unwrap()
+    }
+
+    #[test]
+    fn test_weekly_purchases() {
+        // Purchases on days 0, 7 and 14 at 20 000, 10 000 and 25 000.
+        let mut prices = vec![20_000; 7];
+        prices.extend([10_000; 7]);
+        prices.extend([25_000, 25_000, 30_000]);
+        let plan = DcaPlan::new(usd("100"), Duration::days(7)).build().unwrap();
+        let report = plan.simulate(&series(&prices)).unwrap();
+
+        let dates: Vec<_> = report.purchases().iter().map(|p| p.timestamp()).collect();
+        assert_eq!(dates, vec![day(0), day(7), day(14)]);
+        assert_eq!(report.invested(), &usd("300"));
+        // 0.005 + 0.01 + 0.004 BTC
+        assert_eq!(report.accumulated().amount(), 1_900_000);
+        assert_eq!(report.average_cost().unwrap().amount(), 1_578_947);
+        assert_eq!(report.current_price(), &usd("30000"));
+        assert_eq!(report.current_value(), &usd("570"));
+        assert_eq!(report.gain().unwrap(), usd("270"));
+        assert!((report.return_rate() - 0.9).abs() < 1e-12);
+
+        let lump_sum = report.lump_sum();
+        assert_eq!(lump_sum.timestamp(), day(0));
+        assert_eq!(lump_sum.quantity().amount(), 1_500_000);
+        assert_eq!(lump_sum.current_value(), &usd("450"));
+        assert_eq!(report.advantage().unwrap(), usd("120"));
+    }
+
+    #[test]
+    fn test_start_and_end() {
+        let plan = DcaPlan::new(usd("50"), Duration::days(2))
+            .start(day(1) + Duration::hours(12))
+            .end(day(5))
+            .build()
+            .unwrap();
+        let report = plan
+            .simulate(&series(&[100, 200, 400, 800, 1_600, 3_200, 6_400]))
+            .unwrap();
+
+        let prices: Vec<_> = report
+            .purchases()
+            .iter()
+            .map(|p| p.price().clone())
+            .collect();
+        assert_eq!(prices, vec![usd("200"), usd("800")]);
+        assert_eq!(report.current_price(), &usd("6400"));
+        assert_eq!(report.lump_sum().price(), &usd("200"));
+        assert!(report.lump_sum().return_rate() > report.return_rate());
+        assert!(report.advantage().unwrap().is_negative());
+    }
+
+    #[test]
+    fn test_rejects_invalid_plans() {
+        let btc = Money::from_minor(100, Currency::from(CurrencyCode::BTC));
+        assert!(DcaPlan::new(btc, Duration::days(1)).build().is_err());
+        assert!(DcaPlan::new(usd("0"), Duration::days(1)).build().is_err());
+        assert!(DcaPlan::new(usd("10"), Duration::zero()).build().is_err());
+        assert!(DcaPlan::new(usd("10"), Duration::days(1))
+            .start(day(2))
+            .end(day(1))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_missing_prices() {
+        let plan = DcaPlan::new(usd("10"), Duration::days(1))
+            .start(day(-1))
+            .build()
+            .unwrap();
+        let error = plan.simulate(&series(&[100, 100])).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::NotFound);
+
+        let plan = DcaPlan::new(usd("10"), Duration::days(1)).build().unwrap();
+        let empty = PriceSeries::new(Currency::from(CurrencyCode::USD));
+        assert_eq!(
+            plan.simulate(&empty).unwrap_err().code(),
+            ErrorCode::NotFound
+        );
+
+        let euros = PriceSeries::new(Currency::from(CurrencyCode::EUR));
+        assert_eq!(
+            plan.simulate(&euros).unwrap_err().code(),
+            ErrorCode::Invalid
+        );
+    }
+
+    #[test]
+    fn test_report_json_roundtrip() {
+        let plan = DcaPlan::new(usd("25"), Duration::days(1)).build().unwrap();
+        let report = plan.simulate(&series(&[100, 90, 110])).unwrap();
+        let json = report.to_json().unwrap();
+        assert_eq!(DcaReport::from_json(&json).unwrap(), report);
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use utils::errors::Error;
+
+use crate::money::Money;
+
+/// The outcome of investing the whole amount of a plan at its first purchase instead.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct LumpSum {
+    timestamp: DateTime<Utc>,
+    price: Money,
+    invested: Money,
+    quantity: Money,
+    current_value: Money,
+}
+
+impl LumpSum {
+    pub(crate) fn new(
+        timestamp: DateTime<Utc>,
+        price: Money,
+        invested: Money,
+        quantity: Money,
+        current_value: Money,
+    ) -> Self {
+        Self {
+            timestamp,
+            price,
+            invested,
+            quantity,
+            current_value,
+        }
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    pub fn price(&self) -> &Money {
+        &self.price
+    }
+
+    pub fn invested(&self) -> &Money {
+        &self.invested
+    }
+
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    /// The BTC bought valued at the last price of the series.
+    pub fn current_value(&self) -> &Money {
+        &self.current_value
+    }
+
+    /// The current value less the amount invested.
+    pub fn gain(&self) -> Result<Money, Error> {
+        self.current_value.checked_sub(&self.invested)
+    }
+
+    /// The gain as a fraction of the amount invested, e.g. `0.25` for 25%.
+    pub fn return_rate(&self) -> f64 {
+        return_rate(&self.invested, &self.current_value)
+    }
+}
+
+pub(crate) fn return_rate(invested: &Money, value: &Money) -> f64 {
+    if invested.is_zero() {
+        return 0.0;
+    }
+    value.amount() as f64 / invested.amount() as f64 - 1.0
+}
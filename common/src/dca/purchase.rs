@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+
+use crate::money::Money;
+
+/// One recurring purchase of a dollar-cost-averaging plan.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Purchase {
+    timestamp: DateTime<Utc>,
+    price: Money,
+    spent: Money,
+    quantity: Money,
+}
+
+impl Purchase {
+    pub(crate) fn new(
+        timestamp: DateTime<Utc>,
+        price: Money,
+        spent: Money,
+        quantity: Money,
+    ) -> Self {
+        Self {
+            timestamp,
+            price,
+            spent,
+            quantity,
+        }
+    }
+
+    /// The scheduled time of the purchase.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// The latest price at or before the scheduled time.
+    pub fn price(&self) -> &Money {
+        &self.price
+    }
+
+    pub fn spent(&self) -> &Money {
+        &self.spent
+    }
+
+    /// The BTC bought, rounded down to the satoshi.
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+}
@@ -0,0 +1,90 @@
+use utils::errors::Error;
+
+use super::lump_sum::{return_rate, LumpSum};
+use super::purchase::Purchase;
+use crate::money::Money;
+
+/// The outcome of a dollar-cost-averaging simulation.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct DcaReport {
+    purchases: Vec<Purchase>,
+    invested: Money,
+    accumulated: Money,
+    average_cost: Option<Money>,
+    current_price: Money,
+    current_value: Money,
+    lump_sum: LumpSum,
+}
+
+impl DcaReport {
+    pub(crate) fn new(
+        purchases: Vec<Purchase>,
+        invested: Money,
+        accumulated: Money,
+        average_cost: Option<Money>,
+        current_price: Money,
+        current_value: Money,
+        lump_sum: LumpSum,
+    ) -> Self {
+        Self {
+            purchases,
+            invested,
+            accumulated,
+            average_cost,
+            current_price,
+            current_value,
+            lump_sum,
+        }
+    }
+
+    pub fn purchases(&self) -> &[Purchase] {
+        &self.purchases
+    }
+
+    /// The fiat amount spent over all purchases.
+    pub fn invested(&self) -> &Money {
+        &self.invested
+    }
+
+    /// The BTC bought over all purchases.
+    pub fn accumulated(&self) -> &Money {
+        &self.accumulated
+    }
+
+    /// The amount invested per whole BTC accumulated, or `None` if nothing was bought.
+    pub fn average_cost(&self) -> Option<&Money> {
+        self.average_cost.as_ref()
+    }
+
+    /// The last price of the series.
+    pub fn current_price(&self) -> &Money {
+        &self.current_price
+    }
+
+    /// The BTC accumulated valued at the current price.
+    pub fn current_value(&self) -> &Money {
+        &self.current_value
+    }
+
+    /// The current value less the amount invested.
+    pub fn gain(&self) -> Result<Money, Error> {
+        self.current_value.checked_sub(&self.invested)
+    }
+
+    /// The gain as a fraction of the amount invested, e.g. `0.25` for 25%.
+    pub fn return_rate(&self) -> f64 {
+        return_rate(&self.invested, &self.current_value)
+    }
+
+    /// The same total invested all at once at the first purchase.
+    pub fn lump_sum(&self) -> &LumpSum {
+        &self.lump_sum
+    }
+
+    /// How much more the plan is worth now than the lump sum; negative if the lump sum did better.
+    pub fn advantage(&self) -> Result<Money, Error> {
+        self.current_value
+            .checked_sub(self.lump_sum.current_value())
+    }
+}
@@ -0,0 +1,438 @@
+//! Paper-trading exchange.
+//!
+//! An `Exchange` is a local stand-in for a trading venue. Each registered `CurrencyPair` has an
+//! `OrderBook` with its own `FeeSchedule`; limit orders match against it with price-time
+//! priority and rest whatever they cannot fill, market orders take whatever liquidity there is
+//! and cancel the rest, and resting orders can be cancelled. Every match produces a maker and a
+//! taker `Fill`, each charged its side's fee in the quote currency.
+//!
+//! The exchange is used directly from Rust, or through an `ExchangeServer` that exposes it as a
+//! JSON HTTP interface for tests that go through `HttpClient`.
+
+pub mod book;
+pub mod depth;
+pub mod execution;
+pub mod fees;
+pub mod fill;
+pub mod kind;
+pub mod level;
+pub mod liquidity;
+pub mod order;
+pub mod request;
+pub mod server;
+pub mod status;
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::backtest::Side;
+use super::currency::CurrencyPair;
+use super::money::{ensure_currency, Money};
+
+pub use book::OrderBook;
+pub use depth::Depth;
+pub use execution::Execution;
+pub use fees::FeeSchedule;
+pub use fill::Fill;
+pub use kind::OrderKind;
+pub use level::Level;
+pub use liquidity::Liquidity;
+pub use order::ExchangeOrder;
+pub use request::OrderRequest;
+pub use server::ExchangeServer;
+pub use status::OrderStatus;
+
+/// A set of markets, each with its own order book. Order ids are unique across markets.
+#[derive(Debug, Clone, Default)]
+pub struct Exchange {
+    books: HashMap<CurrencyPair, OrderBook>,
+    markets: HashMap<u64, CurrencyPair>,
+    next_id: u64,
+}
+
+impl Exchange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens a market for the pair.
+    ///
+    /// Fails with `ErrorCode::Conflict` if the pair already has a market.
+    pub fn add_market(&mut self, pair: CurrencyPair, fees: FeeSchedule) -> Result<(), Error> {
+        if self.books.contains_key(&pair) {
+            return Err(
+                Error::new("Market already exists", ErrorCode::Conflict).with_meta(
+                    ErrorMeta::new()
+                        .add("pair", pair.to_string().as_str())
+                        .build(),
+                ),
+            );
+        }
+
+        self.books.insert(pair.clone(), OrderBook::new(pair, fees));
+        Ok(())
+    }
+
+    pub fn book(&self, pair: &CurrencyPair) -> Option<&OrderBook> {
+        self.books.get(pair)
+    }
+
+    /// Places a limit order for `quantity` of the base currency at `price` in the quote currency.
+    ///
+    /// Fails with `ErrorCode::NotFound` if the pair has no market, and with
+    /// `ErrorCode::Invalid` if an amount is not positive or in the wrong currency.
+    pub fn limit(
+        &mut self,
+        pair: &CurrencyPair,
+        side: Side,
+        quantity: Money,
+        price: Money,
+    ) -> Result<Execution, Error> {
+        self.submit(pair, side, quantity, Some(price))
+    }
+
+    /// Places a market order for `quantity` of the base currency.
+    ///
+    /// Fails like `limit`.
+    pub fn market(
+        &mut self,
+        pair: &CurrencyPair,
+        side: Side,
+        quantity: Money,
+    ) -> Result<Execution, Error> {
+        self.submit(pair, side, quantity, None)
+    }
+
+    /// Places an order described by an HTTP request body.
+    pub fn place(&mut self, request: &OrderRequest) -> Result<Execution, Error> {
+        let (pair, quantity, price) = request.parse()?;
+        self.submit(&pair, request.side(), quantity, price)
+    }
+
+    /// Cancels a resting order.
+    ///
+    /// Fails with `ErrorCode::NotFound` if there is no such order, and with
+    /// `ErrorCode::Conflict` if it has already been filled or cancelled.
+    pub fn cancel(&mut self, id: u64) -> Result<ExchangeOrder, Error> {
+        match self.markets.get(&id) {
+            Some(pair) => self
+                .books
+                .get_mut(pair)
+                .expect("orders are only placed on open markets")
+                .cancel(id),
+            None => Err(Error::new("Order not found", ErrorCode::NotFound)
+                .with_meta(ErrorMeta::new().add("id", id.to_string().as_str()).build())),
+        }
+    }
+
+    pub fn order(&self, id: u64) -> Option<&ExchangeOrder> {
+        let pair = self.markets.get(&id)?;
+        self.books.get(pair)?.order(id)
+    }
+
+    /// The fills of an order, oldest first.
+    pub fn fills(&self, id: u64) -> Vec<Fill> {
+        self.markets
+            .get(&id)
+            .and_then(|pair| self.books.get(pair))
+            .map(|book| book.fills(id))
+            .unwrap_or_default()
+    }
+
+    /// The best `levels` price levels on each side of a market.
+    ///
+    /// Fails with `ErrorCode::NotFound` if the pair has no market.
+    pub fn depth(&self, pair: &CurrencyPair, levels: usize) -> Result<Depth, Error> {
+        let book = self.books.get(pair).ok_or_else(|| no_market(pair))?;
+        book.depth(levels)
+    }
+
+    fn submit(
+        &mut self,
+        pair: &CurrencyPair,
+        side: Side,
+        quantity: Money,
+        price: Option<Money>,
+    ) -> Result<Execution, Error> {
+        if !self.books.contains_key(pair) {
+            return Err(no_market(pair));
+        }
+        ensure_currency(pair.base(), quantity.currency())?;
+        if let Some(price) = &price {
+            ensure_currency(pair.quote(), price.currency())?;
+        }
+        for amount in std::iter::once(&quantity).chain(price.as_ref()) {
+            if amount.amount() <= 0 {
+                return Err(
+                    Error::new("Order amounts must be positive", ErrorCode::Invalid).with_meta(
+                        ErrorMeta::new()
+                            .add("amount", amount.to_decimal_string().as_str())
+                            .add("currency", amount.currency().code().to_string())
+                            .build(),
+                    ),
+                );
+            }
+        }
+
+        self.next_id += 1;
+        let id = self.next_id;
+        let now = Utc::now();
+        let order = ExchangeOrder::new(id, pair.clone(), side, price, quantity, now);
+        let book = self.books.get_mut(pair).expect("checked above");
+        let execution = book.submit(order, now)?;
+        self.markets.insert(id, pair.clone());
+        Ok(execution)
+    }
+}
+
+fn no_market(pair: &CurrencyPair) -> Error {
+    Error::new("Market not found", ErrorCode::NotFound).with_meta(
+        ErrorMeta::new()
+            .add("pair", pair.to_string().as_str())
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use utils::adapters::http_client::HttpClient;
+    use utils::http::{HttpMethod, HttpRequest};
+    use utils::json::JSON;
+
+    use super::*;
+    use crate::test_support::{btc, pair, usd};
+
+    fn exchange(fees: FeeSchedule) -> Exchange {
+        let mut exchange = Exchange::new();
+        exchange.add_market(pair(), fees).unwrap();
+        exchange
+    }
+
+    #[test]
+    fn test_price_time_priority() {
+        let mut exchange = exchange(FeeSchedule::free());
+        let first = exchange
+            .limit(&pair(), Side::Sell, btc("1"), usd("30100"))
+            .unwrap();
+        let second = exchange
+            .limit(&pair(), Side::Sell, btc("1"), usd("30000"))
+            .unwrap();
+        let third = exchange
+            .limit(&pair(), Side::Sell, btc("1"), usd("30000"))
+            .unwrap();
+
+        let execution = exchange
+            .limit(&pair(), Side::Buy, btc("2.5"), usd("30100"))
+            .unwrap();
+        let makers: Vec<u64> = execution
+            .fills()
+            .iter()
+            .map(|fill| fill.counterparty_id())
+            .collect();
+        assert_eq!(
+            makers,
+            vec![second.order().id(), third.order().id(), first.order().id()]
+        );
+        assert_eq!(execution.fills()[2].quantity(), &btc("0.5"));
+        assert_eq!(execution.fills()[2].price(), &usd("30100"));
+        assert_eq!(execution.order().status(), OrderStatus::Filled);
+
+        let first = exchange.order(first.order().id()).unwrap();
+        assert_eq!(first.status(), OrderStatus::PartiallyFilled);
+        assert_eq!(first.remaining().unwrap(), btc("0.5"));
+        assert_eq!(
+            exchange.book(&pair()).unwrap().best_ask(),
+            Some(usd("30100"))
+        );
+    }
+
+    #[test]
+    fn test_limit_remainder_rests() {
+        let mut exchange = exchange(FeeSchedule::free());
+        exchange
+            .limit(&pair(), Side::Sell, btc("0.4"), usd("30000"))
+            .unwrap();
+        let execution = exchange
+            .limit(&pair(), Side::Buy, btc("1"), usd("30000"))
+            .unwrap();
+        assert_eq!(execution.order().status(), OrderStatus::PartiallyFilled);
+
+        let depth = exchange.depth(&pair(), 5).unwrap();
+        assert!(depth.asks().is_empty());
+        assert_eq!(depth.bids().len(), 1);
+        assert_eq!(depth.bids()[0].price(), &usd("30000"));
+        assert_eq!(depth.bids()[0].quantity(), &btc("0.6"));
+        assert_eq!(depth.bids()[0].orders(), 1);
+    }
+
+    #[test]
+    fn test_market_orders_cancel_remainder() {
+        let mut exchange = exchange(FeeSchedule::free());
+        exchange
+            .limit(&pair(), Side::Buy, btc("0.5"), usd("29000"))
+            .unwrap();
+        exchange
+            .limit(&pair(), Side::Buy, btc("0.5"), usd("29500"))
+            .unwrap();
+
+        let execution = exchange.market(&pair(), Side::Sell, btc("2")).unwrap();
+        let prices: Vec<&Money> = execution.fills().iter().map(|f| f.price()).collect();
+        assert_eq!(prices, vec![&usd("29500"), &usd("29000")]);
+        assert_eq!(execution.order().filled(), &btc("1"));
+        assert_eq!(execution.order().status(), OrderStatus::Cancelled);
+        assert_eq!(exchange.book(&pair()).unwrap().best_bid(), None);
+    }
+
+    #[test]
+    fn test_fees() {
+        let mut exchange = exchange(FeeSchedule::new(0.001, 0.002).unwrap());
+        let maker = exchange
+            .limit(&pair(), Side::Sell, btc("0.5"), usd("30000"))
+            .unwrap();
+        let taker = exchange.market(&pair(), Side::Buy, btc("0.5")).unwrap();
+
+        let taker_fill = &taker.fills()[0];
+        assert_eq!(taker_fill.liquidity(), Liquidity::Taker);
+        assert_eq!(taker_fill.fee(), &usd("30"));
+
+        let maker_fills = exchange.fills(maker.order().id());
+        assert_eq!(maker_fills.len(), 1);
+        assert_eq!(maker_fills[0].liquidity(), Liquidity::Maker);
+        assert_eq!(maker_fills[0].side(), Side::Sell);
+        assert_eq!(maker_fills[0].fee(), &usd("15"));
+
+        assert!(FeeSchedule::new(-0.1, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut exchange = exchange(FeeSchedule::free());
+        let order = exchange
+            .limit(&pair(), Side::Buy, btc("1"), usd("30000"))
+            .unwrap();
+        let id = order.order().id();
+
+        assert_eq!(
+            exchange.cancel(id).unwrap().status(),
+            OrderStatus::Cancelled
+        );
+        assert!(exchange.depth(&pair(), 5).unwrap().bids().is_empty());
+        assert_eq!(exchange.cancel(id).unwrap_err().code(), ErrorCode::Conflict);
+        assert_eq!(exchange.cancel(99).unwrap_err().code(), ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn test_rejects_invalid_orders() {
+        let mut exchange = exchange(FeeSchedule::free());
+        let error = exchange
+            .limit(&pair(), Side::Buy, btc("1"), usd("0"))
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        let error = exchange.market(&pair(), Side::Buy, usd("1")).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+
+        let other = CurrencyPair::parse("BTC/EUR").unwrap();
+        let error = exchange.market(&other, Side::Buy, btc("1")).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::NotFound);
+        assert_eq!(
+            exchange
+                .add_market(pair(), FeeSchedule::default())
+                .unwrap_err()
+                .code(),
+            ErrorCode::Conflict
+        );
+    }
+
+    #[test]
+    fn test_large_orders() {
+        let mut exchange = exchange(FeeSchedule::default());
+        let quantity = btc("100000000000000");
+        let price = usd("100000000000000000");
+        exchange
+            .limit(&pair(), Side::Sell, quantity.clone(), price.clone())
+            .unwrap();
+        let execution = exchange.limit(&pair(), Side::Buy, quantity, price).unwrap();
+        assert_eq!(execution.order().status(), OrderStatus::Filled);
+        assert_eq!(
+            execution.fills()[0].fee(),
+            &usd("20000000000000000000000000000")
+        );
+
+        let quantity = btc("100000000000000000000");
+        let price = usd("10000000000000000000");
+        let ask = exchange
+            .limit(&pair(), Side::Sell, quantity.clone(), price.clone())
+            .unwrap();
+        let error = exchange
+            .limit(&pair(), Side::Buy, quantity, price.clone())
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("operation"), Some("notional"));
+        let resting = exchange.order(ask.order().id()).unwrap();
+        assert_eq!(resting.status(), OrderStatus::Open);
+        assert_eq!(exchange.book(&pair()).unwrap().best_ask(), Some(price));
+    }
+
+    #[macros::async_test]
+    async fn test_http_interface() {
+        let exchange = Arc::new(Mutex::new(exchange(FeeSchedule::free())));
+        let server = ExchangeServer::bind(exchange.clone(), "127.0.0.1:0")
+            .await
+            .unwrap();
+        let client = HttpClient::new().build(0);
+
+        let sell = OrderRequest::limit(&pair(), Side::Sell, &btc("1"), &usd("30000"));
+        let request = HttpRequest::new(&format!("{}/orders", server.url()), HttpMethod::POST)
+            .body(&sell.to_json().unwrap())
+            .build();
+        let response = client.send_request(Arc::new(request)).await.unwrap();
+        assert_eq!(response.status_code(), 201);
+        let resting = Execution::from_json(response.body().as_deref().unwrap()).unwrap();
+        assert_eq!(resting.order().status(), OrderStatus::Open);
+
+        let buy = OrderRequest::market(&pair(), Side::Buy, &btc("0.25"));
+        let request = HttpRequest::new(&format!("{}/orders", server.url()), HttpMethod::POST)
+            .body(&buy.to_json().unwrap())
+            .build();
+        let response = client.send_request(Arc::new(request)).await.unwrap();
+        let execution = Execution::from_json(response.body().as_deref().unwrap()).unwrap();
+        assert_eq!(execution.fills()[0].price(), &usd("30000"));
+
+        let url = format!("{}/books/BTC-USD?depth=1", server.url());
+        let request = HttpRequest::new(&url, HttpMethod::GET).build();
+        let response = client.send_request(Arc::new(request)).await.unwrap();
+        let depth = Depth::from_json(response.body().as_deref().unwrap()).unwrap();
+        assert_eq!(depth.asks()[0].quantity(), &btc("0.75"));
+
+        let url = format!("{}/orders/{}", server.url(), resting.order().id());
+        let request = HttpRequest::new(&url, HttpMethod::DELETE).build();
+        let response = client.send_request(Arc::new(request)).await.unwrap();
+        assert_eq!(response.status_code(), 200);
+        let response = client
+            .send_request(Arc::new(HttpRequest::new(&url, HttpMethod::DELETE).build()))
+            .await
+            .unwrap();
+        assert_eq!(response.status_code(), 409);
+
+        let url = format!("{}/orders/42", server.url());
+        let request = HttpRequest::new(&url, HttpMethod::GET).build();
+        let response = client.send_request(Arc::new(request)).await.unwrap();
+        assert_eq!(response.status_code(), 404);
+        let error = Error::from_json(response.body().as_deref().unwrap()).unwrap();
+        assert_eq!(error.code(), ErrorCode::NotFound);
+
+        assert_eq!(
+            exchange
+                .lock()
+                .unwrap()
+                .order(resting.order().id())
+                .unwrap()
+                .status(),
+            OrderStatus::Cancelled
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::depth::Depth;
+use super::execution::Execution;
+use super::fees::FeeSchedule;
+use super::fill::Fill;
+use super::level::Level;
+use super::liquidity::Liquidity;
+use super::order::ExchangeOrder;
+use crate::backtest::Side;
+use crate::currency::CurrencyPair;
+use crate::money::{apply_rate, from_wide, mul_div_round, out_of_range, Money};
+
+/// The limit order book of one market.
+///
+/// Resting orders are matched by price, then by arrival: the best price fills first, and orders
+/// at the same price fill in the order they were placed. Matches execute at the resting order's
+/// price.
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    pair: CurrencyPair,
+    fees: FeeSchedule,
+    /// Resting order ids by price in quote minor units, oldest first.
+    bids: BTreeMap<i128, VecDeque<u64>>,
+    asks: BTreeMap<i128, VecDeque<u64>>,
+    orders: HashMap<u64, ExchangeOrder>,
+    fills: Vec<Fill>,
+}
+
+impl OrderBook {
+    pub fn new(pair: CurrencyPair, fees: FeeSchedule) -> Self {
+        Self {
+            pair,
+            fees,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            orders: HashMap::new(),
+            fills: Vec::new(),
+        }
+    }
+
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    pub fn fees(&self) -> &FeeSchedule {
+        &self.fees
+    }
+
+    /// The highest resting buy price.
+    pub fn best_bid(&self) -> Option<Money> {
+        let price = self.bids.keys().next_back()?;
+        Some(Money::from_minor(*price, self.pair.quote().clone()))
+    }
+
+    /// The lowest resting sell price.
+    pub fn best_ask(&self) -> Option<Money> {
+        let price = self.asks.keys().next()?;
+        Some(Money::from_minor(*price, self.pair.quote().clone()))
+    }
+
+    /// The best `levels` price levels on each side.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the quantity resting at a level is out of range.
+    pub fn depth(&self, levels: usize) -> Result<Depth, Error> {
+        let bids = self.bids.iter().rev().take(levels);
+        let asks = self.asks.iter().take(levels);
+        Ok(Depth::new(
+            self.pair.clone(),
+            bids.map(|(price, ids)| self.level(*price, ids))
+                .collect::<Result<_, _>>()?,
+            asks.map(|(price, ids)| self.level(*price, ids))
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+
+    /// Every order placed in this market, whatever its status.
+    pub fn order(&self, id: u64) -> Option<&ExchangeOrder> {
+        self.orders.get(&id)
+    }
+
+    /// The fills of an order, oldest first.
+    pub fn fills(&self, id: u64) -> Vec<Fill> {
+        self.fills
+            .iter()
+            .filter(|fill| fill.order_id() == id)
+            .cloned()
+            .collect()
+    }
+
+    /// Matches an order against the book and rests any limit remainder.
+    ///
+    /// Fails with `ErrorCode::Invalid`, leaving the book as it was, if the value or fee of a
+    /// match is out of range.
+    pub(crate) fn submit(
+        &mut self,
+        mut order: ExchangeOrder,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Execution, Error> {
+        let side = order.side();
+        let matches = self.matches(&order)?;
+        let mut fills = Vec::new();
+
+        for Match {
+            maker_id,
+            quantity,
+            price,
+            maker_fee,
+            taker_fee,
+        } in matches
+        {
+            let levels = match side {
+                Side::Buy => &mut self.asks,
+                Side::Sell => &mut self.bids,
+            };
+            let maker = self
+                .orders
+                .get_mut(&maker_id)
+                .expect("resting orders are indexed");
+            maker.fill(quantity)?;
+            if !maker.status().is_open() {
+                let queue = levels
+                    .get_mut(&price)
+                    .expect("price levels are never empty");
+                queue.pop_front();
+                if queue.is_empty() {
+                    levels.remove(&price);
+                }
+            }
+            order.fill(quantity)?;
+
+            let maker_fill = self.fill(
+                maker_id,
+                order.id(),
+                side.opposite(),
+                Liquidity::Maker,
+                (quantity, price, maker_fee),
+                timestamp,
+            );
+            let taker_fill = self.fill(
+                order.id(),
+                maker_id,
+                side,
+                Liquidity::Taker,
+                (quantity, price, taker_fee),
+                timestamp,
+            );
+            self.fills.push(maker_fill);
+            self.fills.push(taker_fill.clone());
+            fills.push(taker_fill);
+        }
+
+        if order.remaining()?.amount() > 0 {
+            match order.price() {
+                Some(price) => {
+                    let levels = match side {
+                        Side::Buy => &mut self.bids,
+                        Side::Sell => &mut self.asks,
+                    };
+                    levels
+                        .entry(price.amount())
+                        .or_default()
+                        .push_back(order.id());
+                }
+                None => order.cancel(),
+            }
+        }
+
+        self.orders.insert(order.id(), order.clone());
+        Ok(Execution::new(order, fills))
+    }
+
+    /// The resting orders `order` would match, best price and oldest first, without touching
+    /// the book.
+    fn matches(&self, order: &ExchangeOrder) -> Result<Vec<Match>, Error> {
+        let quote = self.pair.quote();
+        let scale = 10i128.pow(self.pair.base().decimal_places());
+        let limit = order.price().map(|price| price.amount());
+        let levels: Box<dyn Iterator<Item = (&i128, &VecDeque<u64>)>> = match order.side() {
+            Side::Buy => Box::new(self.asks.iter()),
+            Side::Sell => Box::new(self.bids.iter().rev()),
+        };
+
+        let mut remaining = order.remaining()?.amount();
+        let mut matches = Vec::new();
+        for (&price, queue) in levels {
+            let crosses = match (order.side(), limit) {
+                (_, None) => true,
+                (Side::Buy, Some(limit)) => price <= limit,
+                (Side::Sell, Some(limit)) => price >= limit,
+            };
+            if remaining == 0 || !crosses {
+                break;
+            }
+
+            for &maker_id in queue {
+                if remaining == 0 {
+                    break;
+                }
+                let maker = self
+                    .orders
+                    .get(&maker_id)
+                    .expect("resting orders are indexed");
+                let quantity = remaining.min(maker.remaining()?.amount());
+                remaining -= quantity;
+
+                let notional = mul_div_round(quantity, price, scale)
+                    .ok_or_else(|| out_of_range("notional", quote))?;
+                let fee =
+                    |rate| apply_rate(notional, rate).ok_or_else(|| out_of_range("fee", quote));
+                matches.push(Match {
+                    maker_id,
+                    quantity,
+                    price,
+                    maker_fee: fee(self.fees.maker())?,
+                    taker_fee: fee(self.fees.taker())?,
+                });
+            }
+        }
+        Ok(matches)
+    }
+
+    /// Takes a resting order off the book.
+    ///
+    /// Fails with `ErrorCode::NotFound` if there is no such order, and with
+    /// `ErrorCode::Conflict` if it has already been filled or cancelled.
+    pub(crate) fn cancel(&mut self, id: u64) -> Result<ExchangeOrder, Error> {
+        let meta = || ErrorMeta::new().add("id", id.to_string().as_str()).build();
+        let order = self
+            .orders
+            .get_mut(&id)
+            .ok_or_else(|| Error::new("Order not found", ErrorCode::NotFound).with_meta(meta()))?;
+        if !order.status().is_open() {
+            return Err(
+                Error::new("Order is no longer open", ErrorCode::Conflict).with_meta(meta())
+            );
+        }
+
+        let levels = match order.side() {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        if let Some(price) = order.price().map(|price| price.amount()) {
+            if let Some(queue) = levels.get_mut(&price) {
+                queue.retain(|resting| *resting != id);
+                if queue.is_empty() {
+                    levels.remove(&price);
+                }
+            }
+        }
+
+        order.cancel();
+        Ok(order.clone())
+    }
+
+    fn level(&self, price: i128, ids: &VecDeque<u64>) -> Result<Level, Error> {
+        let base = self.pair.base();
+        let quantity =
+            ids.iter()
+                .filter_map(|id| self.orders.get(id))
+                .try_fold(0i128, |total, order| {
+                    total
+                        .checked_add(order.remaining()?.amount())
+                        .ok_or_else(|| out_of_range("depth", base))
+                })?;
+        Ok(Level::new(
+            Money::from_minor(price, self.pair.quote().clone()),
+            from_wide(quantity, base)?,
+            ids.len(),
+        ))
+    }
+
+    /// A fill of `quantity` base minor units at `price` quote minor units with a `fee` in quote
+    /// minor units.
+    fn fill(
+        &self,
+        order_id: u64,
+        counterparty_id: u64,
+        side: Side,
+        liquidity: Liquidity,
+        (quantity, price, fee): (i128, i128, i128),
+        timestamp: DateTime<Utc>,
+    ) -> Fill {
+        let quote = self.pair.quote();
+        Fill::new(
+            order_id,
+            counterparty_id,
+            side,
+            liquidity,
+            Money::from_minor(quantity, self.pair.base().clone()),
+            Money::from_minor(price, quote.clone()),
+            Money::from_minor(fee, quote.clone()),
+            timestamp,
+        )
+    }
+}
+
+/// A match planned by `OrderBook::matches`: `quantity` base minor units against a resting
+/// order at `price`, with each side's fee in quote minor units.
+struct Match {
+    maker_id: u64,
+    quantity: i128,
+    price: i128,
+    maker_fee: i128,
+    taker_fee: i128,
+}
@@ -0,0 +1,31 @@
+use super::level::Level;
+use crate::currency::CurrencyPair;
+
+/// A snapshot of the best price levels of an order book.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Depth {
+    pair: CurrencyPair,
+    bids: Vec<Level>,
+    asks: Vec<Level>,
+}
+
+impl Depth {
+    pub(crate) fn new(pair: CurrencyPair, bids: Vec<Level>, asks: Vec<Level>) -> Self {
+        Self { pair, bids, asks }
+    }
+
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    /// Buy levels, best (highest) first.
+    pub fn bids(&self) -> &[Level] {
+        &self.bids
+    }
+
+    /// Sell levels, best (lowest) first.
+    pub fn asks(&self) -> &[Level] {
+        &self.asks
+    }
+}
@@ -0,0 +1,24 @@
+use super::fill::Fill;
+use super::order::ExchangeOrder;
+
+/// The result of placing an order: its state afterwards and the fills it took as the taker.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Execution {
+    order: ExchangeOrder,
+    fills: Vec<Fill>,
+}
+
+impl Execution {
+    pub(crate) fn new(order: ExchangeOrder, fills: Vec<Fill>) -> Self {
+        Self { order, fills }
+    }
+
+    pub fn order(&self) -> &ExchangeOrder {
+        &self.order
+    }
+
+    pub fn fills(&self) -> &[Fill] {
+        &self.fills
+    }
+}
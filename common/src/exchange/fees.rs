@@ -0,0 +1,59 @@
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+const DEFAULT_MAKER_RATE: f64 = 0.001;
+const DEFAULT_TAKER_RATE: f64 = 0.002;
+
+/// The fees a market charges, as fractions of each fill's notional value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[macros::json]
+pub struct FeeSchedule {
+    maker: f64,
+    taker: f64,
+}
+
+impl FeeSchedule {
+    /// Fails with `ErrorCode::Invalid` if a rate is outside `0..1`.
+    pub fn new(maker: f64, taker: f64) -> Result<Self, Error> {
+        for (name, rate) in [("maker", maker), ("taker", taker)] {
+            if !(0.0..1.0).contains(&rate) {
+                return Err(
+                    Error::new("Invalid fee rate", ErrorCode::Invalid).with_meta(
+                        ErrorMeta::new()
+                            .add("fee", name)
+                            .add("rate", rate.to_string().as_str())
+                            .build(),
+                    ),
+                );
+            }
+        }
+        Ok(Self { maker, taker })
+    }
+
+    /// A schedule without fees.
+    pub fn free() -> Self {
+        Self {
+            maker: 0.0,
+            taker: 0.0,
+        }
+    }
+
+    /// The rate charged to resting orders when they are matched.
+    pub fn maker(&self) -> f64 {
+        self.maker
+    }
+
+    /// The rate charged to incoming orders that match resting ones.
+    pub fn taker(&self) -> f64 {
+        self.taker
+    }
+}
+
+impl Default for FeeSchedule {
+    /// 0.1% for makers and 0.2% for takers.
+    fn default() -> Self {
+        Self {
+            maker: DEFAULT_MAKER_RATE,
+            taker: DEFAULT_TAKER_RATE,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+
+use super::liquidity::Liquidity;
+use crate::backtest::Side;
+use crate::money::Money;
+
+/// One side of a match between two orders.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Fill {
+    order_id: u64,
+    counterparty_id: u64,
+    side: Side,
+    liquidity: Liquidity,
+    quantity: Money,
+    price: Money,
+    fee: Money,
+    timestamp: DateTime<Utc>,
+}
+
+impl Fill {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        order_id: u64,
+        counterparty_id: u64,
+        side: Side,
+        liquidity: Liquidity,
+        quantity: Money,
+        price: Money,
+        fee: Money,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            order_id,
+            counterparty_id,
+            side,
+            liquidity,
+            quantity,
+            price,
+            fee,
+            timestamp,
+        }
+    }
+
+    pub fn order_id(&self) -> u64 {
+        self.order_id
+    }
+
+    /// The order on the other side of the match.
+    pub fn counterparty_id(&self) -> u64 {
+        self.counterparty_id
+    }
+
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    pub fn liquidity(&self) -> Liquidity {
+        self.liquidity
+    }
+
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    /// The price of the resting order.
+    pub fn price(&self) -> &Money {
+        &self.price
+    }
+
+    /// The fee in the quote currency.
+    pub fn fee(&self) -> &Money {
+        &self.fee
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+}
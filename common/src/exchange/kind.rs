@@ -0,0 +1,9 @@
+/// How an order is priced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[macros::json(rename_all = "lowercase")]
+pub enum OrderKind {
+    /// Fills at its limit price or better; any remainder rests on the book.
+    Limit,
+    /// Fills immediately against the book; any remainder is cancelled.
+    Market,
+}
@@ -0,0 +1,33 @@
+use crate::money::Money;
+
+/// The resting orders at one price.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Level {
+    price: Money,
+    quantity: Money,
+    orders: usize,
+}
+
+impl Level {
+    pub(crate) fn new(price: Money, quantity: Money, orders: usize) -> Self {
+        Self {
+            price,
+            quantity,
+            orders,
+        }
+    }
+
+    pub fn price(&self) -> &Money {
+        &self.price
+    }
+
+    /// The remaining quantity of all orders at the price.
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    pub fn orders(&self) -> usize {
+        self.orders
+    }
+}
@@ -0,0 +1,9 @@
+/// Which side of a match a fill was on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[macros::json(rename_all = "lowercase")]
+pub enum Liquidity {
+    /// The resting order that was matched.
+    Maker,
+    /// The incoming order that matched it.
+    Taker,
+}
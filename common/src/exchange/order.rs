@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use utils::errors::Error;
+
+use super::kind::OrderKind;
+use super::status::OrderStatus;
+use crate::backtest::Side;
+use crate::currency::CurrencyPair;
+use crate::money::{from_wide, out_of_range, Money};
+
+/// An order placed on the exchange and its progress.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct ExchangeOrder {
+    id: u64,
+    pair: CurrencyPair,
+    side: Side,
+    kind: OrderKind,
+    price: Option<Money>,
+    quantity: Money,
+    filled: Money,
+    status: OrderStatus,
+    timestamp: DateTime<Utc>,
+}
+
+impl ExchangeOrder {
+    pub(crate) fn new(
+        id: u64,
+        pair: CurrencyPair,
+        side: Side,
+        price: Option<Money>,
+        quantity: Money,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        let kind = match price {
+            Some(_) => OrderKind::Limit,
+            None => OrderKind::Market,
+        };
+        let filled = Money::zero(quantity.currency().clone());
+        Self {
+            id,
+            pair,
+            side,
+            kind,
+            price,
+            quantity,
+            filled,
+            status: OrderStatus::Open,
+            timestamp,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    pub fn kind(&self) -> OrderKind {
+        self.kind
+    }
+
+    /// The limit price, or `None` for market orders.
+    pub fn price(&self) -> Option<&Money> {
+        self.price.as_ref()
+    }
+
+    /// The quantity of the base currency ordered.
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    pub fn filled(&self) -> &Money {
+        &self.filled
+    }
+
+    /// The quantity not filled yet.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the difference is out of range.
+    pub fn remaining(&self) -> Result<Money, Error> {
+        let currency = self.quantity.currency();
+        let remaining = self
+            .quantity
+            .amount()
+            .checked_sub(self.filled.amount())
+            .ok_or_else(|| out_of_range("remaining", currency))?;
+        from_wide(remaining, currency)
+    }
+
+    pub fn status(&self) -> OrderStatus {
+        self.status
+    }
+
+    /// When the order was placed.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// Records a fill of `quantity` minor units.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the filled quantity is out of range.
+    pub(crate) fn fill(&mut self, quantity: i128) -> Result<(), Error> {
+        let currency = self.filled.currency();
+        let filled = self
+            .filled
+            .amount()
+            .checked_add(quantity)
+            .ok_or_else(|| out_of_range("filled", currency))?;
+        self.filled = from_wide(filled, currency)?;
+        self.status = if self.filled.amount() >= self.quantity.amount() {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+        Ok(())
+    }
+
+    pub(crate) fn cancel(&mut self) {
+        self.status = OrderStatus::Cancelled;
+    }
+}
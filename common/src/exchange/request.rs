@@ -0,0 +1,69 @@
+use utils::errors::{Error, ErrorCode};
+
+use super::kind::OrderKind;
+use crate::backtest::Side;
+use crate::currency::CurrencyPair;
+use crate::money::Money;
+
+/// The body of a request to place an order over HTTP, with amounts as decimal strings, e.g.
+/// `{"pair":"BTC/USD","side":"buy","kind":"limit","quantity":"0.5","price":"30000"}`.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct OrderRequest {
+    pair: String,
+    side: Side,
+    kind: OrderKind,
+    quantity: String,
+    #[json(default, skip_serializing_if = "Option::is_none")]
+    price: Option<String>,
+}
+
+impl OrderRequest {
+    pub fn limit(pair: &CurrencyPair, side: Side, quantity: &Money, price: &Money) -> Self {
+        Self {
+            pair: pair.to_string(),
+            side,
+            kind: OrderKind::Limit,
+            quantity: quantity.to_decimal_string(),
+            price: Some(price.to_decimal_string()),
+        }
+    }
+
+    pub fn market(pair: &CurrencyPair, side: Side, quantity: &Money) -> Self {
+        Self {
+            pair: pair.to_string(),
+            side,
+            kind: OrderKind::Market,
+            quantity: quantity.to_decimal_string(),
+            price: None,
+        }
+    }
+
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    pub fn kind(&self) -> OrderKind {
+        self.kind
+    }
+
+    /// Resolves the pair and amounts.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the pair or an amount does not parse, or a limit order
+    /// has no price.
+    pub(crate) fn parse(&self) -> Result<(CurrencyPair, Money, Option<Money>), Error> {
+        let pair = CurrencyPair::parse(&self.pair)?;
+        let quantity = Money::parse(&self.quantity, pair.base().clone())?;
+        let price = match (self.kind, &self.price) {
+            (OrderKind::Limit, Some(price)) => Some(Money::parse(price, pair.quote().clone())?),
+            (OrderKind::Limit, None) => {
+                return Err(Error::new(
+                    "Limit orders require a price",
+                    ErrorCode::Invalid,
+                ))
+            }
+            (OrderKind::Market, _) => None,
+        };
+        Ok((pair, quantity, price))
+    }
+}
@@ -0,0 +1,149 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+use utils::http::server::{self, IncomingRequest};
+use utils::json::JSON;
+
+use super::request::OrderRequest;
+use super::Exchange;
+use crate::currency::CurrencyPair;
+
+const DEFAULT_DEPTH: usize = 10;
+
+/// Serves an `Exchange` as JSON over HTTP/1.1 so it can stand in for a real venue in tests.
+///
+/// Routes:
+///
+/// - `POST /orders` with an `OrderRequest` body places an order and answers `201` with its
+///   `Execution`.
+/// - `GET /orders/{id}` answers with the `ExchangeOrder`.
+/// - `DELETE /orders/{id}` cancels the order and answers with it.
+/// - `GET /orders/{id}/fills` answers with the order's fills.
+/// - `GET /books/{BASE}-{QUOTE}?depth={levels}` answers with the book's `Depth`, ten levels by
+///   default.
+///
+/// Failures answer with the serialized `Error` and a status matching its code, and bodies over
+/// `utils::http::server::MAX_BODY_BYTES` with `413`. The server stops when it is dropped.
+pub struct ExchangeServer {
+    address: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl ExchangeServer {
+    /// Starts serving on `address`; use port `0`, e.g. `"127.0.0.1:0"`, for any free port.
+    ///
+    /// Fails with `ErrorCode::Unavailable` if the address cannot be bound.
+    pub async fn bind(exchange: Arc<Mutex<Exchange>>, address: &str) -> Result<Self, Error> {
+        let unavailable = |err: std::io::Error| {
+            Error::new("Failed to bind exchange server", ErrorCode::Unavailable)
+                .with_meta(ErrorMeta::new().add("address", address).build())
+                .with_cause(err)
+        };
+        let listener = TcpListener::bind(address).await.map_err(unavailable)?;
+        let address = listener.local_addr().map_err(unavailable)?;
+
+        let task = tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                let exchange = exchange.clone();
+                tokio::spawn(server::serve(stream, move |request| async move {
+                    match route(&exchange, &request) {
+                        Ok(response) => response,
+                        Err(error) => (status(error.code()), error.to_json().unwrap_or_default()),
+                    }
+                }));
+            }
+        });
+
+        Ok(Self { address, task })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The base URL of the server, e.g. `http://127.0.0.1:40123`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+}
+
+impl Drop for ExchangeServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn route(exchange: &Mutex<Exchange>, request: &IncomingRequest) -> Result<(u16, String), Error> {
+    let (method, path, query) = (request.method(), request.path(), request.query());
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let mut exchange = exchange
+        .lock()
+        .map_err(|_| Error::new("Failed to lock exchange", ErrorCode::Internal))?;
+
+    match (method, segments.as_slice()) {
+        ("POST", ["orders"]) => {
+            let request = OrderRequest::from_json(request.body())?;
+            Ok((201, exchange.place(&request)?.to_json()?))
+        }
+        ("GET", ["orders", id]) => {
+            let id = order_id(id)?;
+            let order = exchange.order(id).ok_or_else(|| not_found(id))?;
+            Ok((200, order.to_json()?))
+        }
+        ("DELETE", ["orders", id]) => Ok((200, exchange.cancel(order_id(id)?)?.to_json()?)),
+        ("GET", ["orders", id, "fills"]) => {
+            let id = order_id(id)?;
+            exchange.order(id).ok_or_else(|| not_found(id))?;
+            Ok((200, exchange.fills(id).to_json()?))
+        }
+        ("GET", ["books", pair]) => {
+            let pair = CurrencyPair::parse(&pair.replacen('-', "/", 1))?;
+            let levels = query
+                .split('&')
+                .find_map(|param| param.strip_prefix("depth="))
+                .and_then(|levels| levels.parse().ok())
+                .unwrap_or(DEFAULT_DEPTH);
+            Ok((200, exchange.depth(&pair, levels)?.to_json()?))
+        }
+        _ => Err(
+            Error::new("Route not found", ErrorCode::NotFound).with_meta(
+                ErrorMeta::new()
+                    .add("method", method)
+                    .add("path", path)
+                    .build(),
+            ),
+        ),
+    }
+}
+
+fn order_id(id: &str) -> Result<u64, Error> {
+    id.parse().map_err(|_| {
+        Error::new("Invalid order id", ErrorCode::Invalid)
+            .with_meta(ErrorMeta::new().add("id", id).build())
+    })
+}
+
+fn not_found(id: u64) -> Error {
+    Error::new("Order not found", ErrorCode::NotFound)
+        .with_meta(ErrorMeta::new().add("id", id.to_string().as_str()).build())
+}
+
+fn status(code: ErrorCode) -> u16 {
+    match code {
+        ErrorCode::Invalid | ErrorCode::JsonParse => 400,
+        ErrorCode::Unauthorized => 401,
+        ErrorCode::Forbidden => 403,
+        ErrorCode::NotFound => 404,
+        ErrorCode::Conflict => 409,
+        ErrorCode::Unprocessable => 422,
+        ErrorCode::Unavailable => 503,
+        ErrorCode::Timeout => 504,
+        _ => 500,
+    }
+}
@@ -0,0 +1,20 @@
+/// Where an order is in its life cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[macros::json(rename_all = "snake_case")]
+pub enum OrderStatus {
+    /// Resting on the book with nothing filled yet.
+    Open,
+    /// Resting on the book with part of its quantity filled.
+    PartiallyFilled,
+    Filled,
+    /// Cancelled by request, or a market order that ran out of liquidity. Part of it may have
+    /// filled before.
+    Cancelled,
+}
+
+impl OrderStatus {
+    /// Whether the order is still on the book.
+    pub fn is_open(&self) -> bool {
+        matches!(self, Self::Open | Self::PartiallyFilled)
+    }
+}
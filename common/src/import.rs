@@ -0,0 +1,687 @@
+//! CSV import of exchange exports.
+//!
+//! A `CsvImporter` maps the columns of a CSV file onto `Field`s and turns each row into a typed
+//! record: a `PricePoint` for price histories or a ledger `Transaction` for trade histories.
+//! Columns are found by header name or position, timestamps are tried against a list of date
+//! formats, and currencies are inferred per row from a currency, asset or pair column, from a
+//! symbol or code written next to an amount, or from configured defaults. Currencies are read
+//! with `currency::resolver`, so names, aliases such as `XBT` and registered tokens work too.
+//!
+//! A row that cannot be read does not fail the import. It is reported as an `ErrorCode::Invalid`
+//! error whose metadata names the line, the field and the offending value, and the remaining
+//! rows are still imported.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use csv::StringRecord;
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::currency::code::CurrencyCode;
+use super::currency::{resolver, Currency, CurrencyPair};
+use super::ledger::Transaction;
+use super::money::{from_wide, mul_div_round, out_of_range, Money};
+use super::price::PricePoint;
+
+/// Date formats tried when none are configured. Besides `chrono` format strings, `rfc3339`,
+/// `unix` (seconds) and `unix_ms` (milliseconds) are understood.
+const DEFAULT_DATE_FORMATS: [&str; 5] = [
+    "rfc3339",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d",
+    "unix",
+];
+
+/// A value the importer reads from a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Timestamp,
+    /// The price of one unit of the asset.
+    Price,
+    /// The traded amount of the asset for a price point.
+    Volume,
+    /// The currency prices and totals are quoted in.
+    Currency,
+    /// The asset that was priced or traded.
+    Asset,
+    /// A pair such as `BTC/USD`, `BTC-USD` or `BTCUSD`, giving both the asset and the currency.
+    Pair,
+    /// The kind of trade: `buy`, `sell`, `deposit`, `withdrawal` or `fee`.
+    Side,
+    /// The amount of the asset traded.
+    Quantity,
+    /// The total cost or proceeds of a trade. Computed from price and quantity when absent.
+    Total,
+    /// A fee paid in the quote currency.
+    Fee,
+}
+
+impl Field {
+    const ALL: [Field; 10] = [
+        Field::Timestamp,
+        Field::Price,
+        Field::Volume,
+        Field::Currency,
+        Field::Asset,
+        Field::Pair,
+        Field::Side,
+        Field::Quantity,
+        Field::Total,
+        Field::Fee,
+    ];
+
+    /// The name of the field, which is also the header it is found under by default.
+    pub fn name(&self) -> &str {
+        match self {
+            Field::Timestamp => "timestamp",
+            Field::Price => "price",
+            Field::Volume => "volume",
+            Field::Currency => "currency",
+            Field::Asset => "asset",
+            Field::Pair => "pair",
+            Field::Side => "side",
+            Field::Quantity => "quantity",
+            Field::Total => "total",
+            Field::Fee => "fee",
+        }
+    }
+}
+
+/// Where a field is read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Source {
+    Header(Box<str>),
+    Index(usize),
+}
+
+/// The records read from a file and the rows that could not be read.
+#[derive(Debug)]
+pub struct Import<T> {
+    records: Vec<T>,
+    errors: Vec<Error>,
+}
+
+impl<T> Import<T> {
+    pub fn records(&self) -> &[T] {
+        &self.records
+    }
+
+    /// One error per rejected row, with the row's `line` in the metadata.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn into_parts(self) -> (Vec<T>, Vec<Error>) {
+        (self.records, self.errors)
+    }
+}
+
+/// Reads price points and trades from CSV.
+#[derive(Debug, Clone)]
+pub struct CsvImporter {
+    delimiter: u8,
+    has_header: bool,
+    columns: Vec<(Field, Source)>,
+    date_formats: Vec<Box<str>>,
+    decimal_separator: char,
+    thousands_separator: Option<char>,
+    currency: Option<CurrencyCode>,
+    asset: Option<CurrencyCode>,
+}
+
+impl CsvImporter {
+    /// An importer for comma separated files with a header row, whose columns are named after
+    /// the fields.
+    pub fn new() -> Self {
+        Self {
+            delimiter: b',',
+            has_header: true,
+            columns: Vec::new(),
+            date_formats: Vec::new(),
+            decimal_separator: '.',
+            thousands_separator: None,
+            currency: None,
+            asset: None,
+        }
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Whether the first row names the columns. Without a header, every field has to be mapped
+    /// with `column_index`.
+    pub fn has_header(mut self, has_header: bool) -> Self {
+        self.has_header = has_header;
+        self
+    }
+
+    /// Reads the field from the column with this header, compared case-insensitively.
+    pub fn column(mut self, field: Field, header: &str) -> Self {
+        self.columns.retain(|(mapped, _)| *mapped != field);
+        self.columns.push((field, Source::Header(header.into())));
+        self
+    }
+
+    /// Reads the field from the column at this zero-based position.
+    pub fn column_index(mut self, field: Field, index: usize) -> Self {
+        self.columns.retain(|(mapped, _)| *mapped != field);
+        self.columns.push((field, Source::Index(index)));
+        self
+    }
+
+    /// Adds a timestamp format, tried in the order added. Timestamps without a time of day are
+    /// taken as midnight UTC.
+    pub fn date_format(mut self, date_format: &str) -> Self {
+        self.date_formats.push(date_format.into());
+        self
+    }
+
+    pub fn decimal_separator(mut self, decimal_separator: char) -> Self {
+        self.decimal_separator = decimal_separator;
+        self
+    }
+
+    /// A separator to ignore in amounts, e.g. `Some(',')` for `70,000.50`.
+    pub fn thousands_separator(mut self, thousands_separator: Option<char>) -> Self {
+        self.thousands_separator = thousands_separator;
+        self
+    }
+
+    /// The quote currency for rows that do not name one.
+    pub fn currency(mut self, currency: CurrencyCode) -> Self {
+        self.currency = Some(currency);
+        self
+    }
+
+    /// The asset for rows that do not name one.
+    pub fn asset(mut self, asset: CurrencyCode) -> Self {
+        self.asset = Some(asset);
+        self
+    }
+
+    /// Reads one price point per row from the timestamp, price and optional volume.
+    ///
+    /// Fails with `ErrorCode::Invalid` only if the file cannot be read at all, e.g. when a
+    /// mapped header is missing.
+    pub fn prices(&self, csv: &str) -> Result<Import<PricePoint>, Error> {
+        self.import(csv, |row| {
+            let timestamp = row.timestamp(Field::Timestamp)?;
+            let (price, _) = row.amount(Field::Price, |hint| row.currency(hint))?;
+            let mut point = PricePoint::new(timestamp, price);
+
+            if let Some(cell) = row.get(Field::Volume) {
+                let asset = row.asset()?;
+                let (volume, _) = row.parse_amount(Field::Volume, cell, |_| Ok(asset.clone()))?;
+                point = point.with_volume(volume);
+            }
+            Ok(point)
+        })
+    }
+
+    /// Reads one ledger transaction per row from the timestamp, side, quantity, and either the
+    /// total or the price, plus an optional fee. Totals and fees are in the row's currency.
+    ///
+    /// Fails with `ErrorCode::Invalid` only if the file cannot be read at all.
+    pub fn trades(&self, csv: &str) -> Result<Import<Transaction>, Error> {
+        self.import(csv, |row| {
+            let timestamp = row.timestamp(Field::Timestamp)?;
+            let side = row.required(Field::Side)?;
+            let asset = row.asset()?;
+            let (quantity, _) = row.amount(Field::Quantity, |_| Ok(asset.clone()))?;
+
+            let total = match row.get(Field::Total) {
+                Some(cell) => Some(row.parse_amount(Field::Total, cell, |h| row.currency(h))?.0),
+                None => match row.get(Field::Price) {
+                    Some(cell) => {
+                        let (price, _) =
+                            row.parse_amount(Field::Price, cell, |h| row.currency(h))?;
+                        Some(total(&quantity, &price)?)
+                    }
+                    None => None,
+                },
+            };
+            let fee = match row.get(Field::Fee) {
+                Some(cell) => Some(row.parse_amount(Field::Fee, cell, |h| row.currency(h))?.0),
+                None => None,
+            };
+
+            let missing_total = || row.invalid(Field::Total, "", "total or price is required");
+            let transaction = match side.to_lowercase().as_str() {
+                "buy" => Transaction::buy(timestamp, quantity, total.ok_or_else(missing_total)?),
+                "sell" => Transaction::sell(timestamp, quantity, total.ok_or_else(missing_total)?),
+                "deposit" | "transfer_in" => Transaction::transfer_in(timestamp, quantity, total),
+                "withdrawal" | "transfer_out" => Transaction::transfer_out(timestamp, quantity),
+                "fee" => Transaction::fee(timestamp, quantity),
+                _ => return Err(row.invalid(Field::Side, side, "unknown side")),
+            };
+
+            Ok(match fee {
+                Some(fee) if !fee.is_zero() => transaction.with_fee(fee),
+                _ => transaction,
+            })
+        })
+    }
+
+    fn import<T, F>(&self, csv: &str, parse: F) -> Result<Import<T>, Error>
+    where
+        F: Fn(&Row) -> Result<T, Error>,
+    {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.has_header)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(csv.as_bytes());
+
+        let headers = if self.has_header {
+            Some(reader.headers().map_err(|err| read_error(&err))?.clone())
+        } else {
+            None
+        };
+        let columns = self.resolve(headers.as_ref())?;
+
+        let mut import = Import {
+            records: Vec::new(),
+            errors: Vec::new(),
+        };
+        for record in reader.records() {
+            let record = match record {
+                Ok(record) => record,
+                Err(err) => {
+                    import.errors.push(read_error(&err));
+                    continue;
+                }
+            };
+            let row = Row {
+                importer: self,
+                line: record.position().map_or(0, |position| position.line()),
+                record: &record,
+                columns: &columns,
+            };
+            match parse(&row) {
+                Ok(parsed) => import.records.push(parsed),
+                Err(err) => import.errors.push(err),
+            }
+        }
+
+        Ok(import)
+    }
+
+    /// Finds the position of every field that can be read.
+    fn resolve(&self, headers: Option<&StringRecord>) -> Result<Vec<(Field, usize)>, Error> {
+        let find = |name: &str| {
+            headers?
+                .iter()
+                .position(|header| header.eq_ignore_ascii_case(name))
+        };
+
+        let mut columns = Vec::new();
+        for field in Field::ALL {
+            let source = self.columns.iter().find(|(mapped, _)| *mapped == field);
+            let index = match source {
+                Some((_, Source::Index(index))) => Some(*index),
+                Some((_, Source::Header(header))) => Some(find(header).ok_or_else(|| {
+                    Error::new("Mapped column not found", ErrorCode::Invalid).with_meta(
+                        ErrorMeta::new()
+                            .add("field", field.name())
+                            .add("column", header)
+                            .build(),
+                    )
+                })?),
+                None => find(field.name()),
+            };
+            if let Some(index) = index {
+                columns.push((field, index));
+            }
+        }
+
+        Ok(columns)
+    }
+
+    fn parse_timestamp(&self, value: &str) -> Option<DateTime<Utc>> {
+        let formats: Vec<&str> = if self.date_formats.is_empty() {
+            DEFAULT_DATE_FORMATS.to_vec()
+        } else {
+            self.date_formats.iter().map(|format| &**format).collect()
+        };
+
+        formats.into_iter().find_map(|format| match format {
+            "rfc3339" => DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|timestamp| timestamp.with_timezone(&Utc)),
+            "unix" => value
+                .parse()
+                .ok()
+                .and_then(|seconds| DateTime::from_timestamp(seconds, 0)),
+            "unix_ms" => value.parse().ok().and_then(DateTime::from_timestamp_millis),
+            format => NaiveDateTime::parse_from_str(value, format)
+                .ok()
+                .or_else(|| {
+                    NaiveDate::parse_from_str(value, format)
+                        .ok()
+                        .and_then(|date| date.and_hms_opt(0, 0, 0))
+                })
+                .map(|timestamp| timestamp.and_utc()),
+        })
+    }
+}
+
+impl Default for CsvImporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A row being imported, with the helpers to read its fields.
+struct Row<'r> {
+    importer: &'r CsvImporter,
+    line: u64,
+    record: &'r StringRecord,
+    columns: &'r [(Field, usize)],
+}
+
+impl Row<'_> {
+    /// The non-empty value of a field.
+    fn get(&self, field: Field) -> Option<&str> {
+        let (_, index) = self.columns.iter().find(|(mapped, _)| *mapped == field)?;
+        self.record.get(*index).filter(|value| !value.is_empty())
+    }
+
+    fn required(&self, field: Field) -> Result<&str, Error> {
+        self.get(field)
+            .ok_or_else(|| self.invalid(field, "", "value is missing"))
+    }
+
+    fn timestamp(&self, field: Field) -> Result<DateTime<Utc>, Error> {
+        let value = self.required(field)?;
+        self.importer
+            .parse_timestamp(value)
+            .ok_or_else(|| self.invalid(field, value, "unrecognized date format"))
+    }
+
+    /// Reads a required amount; see `parse_amount`.
+    fn amount<F>(&self, field: Field, currency: F) -> Result<(Money, Currency), Error>
+    where
+        F: Fn(Option<CurrencyCode>) -> Result<Currency, Error>,
+    {
+        let cell = self.required(field)?;
+        self.parse_amount(field, cell, currency)
+    }
+
+    /// Parses an amount that may carry a currency symbol or code, e.g. `$70,000.50` or
+    /// `0.5 BTC`. `currency` picks the currency of the amount given what the cell says, if
+    /// anything.
+    fn parse_amount<F>(
+        &self,
+        field: Field,
+        cell: &str,
+        currency: F,
+    ) -> Result<(Money, Currency), Error>
+    where
+        F: Fn(Option<CurrencyCode>) -> Result<Currency, Error>,
+    {
+        let is_number = |c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | ',');
+        let start = cell.find(is_number).unwrap_or(cell.len());
+        let end = cell.rfind(is_number).map_or(start, |end| end + 1);
+        let label = format!("{}{}", &cell[..start], &cell[end..]);
+        let label = label.trim();
+
+        let hint = if label.is_empty() {
+            None
+        } else {
+            Some(
+                resolver::resolve(label)
+                    .map_err(|_| self.invalid(field, cell, "unknown currency"))?,
+            )
+        };
+        let currency = currency(hint)?;
+        if hint.is_some_and(|hint| &hint != currency.code()) {
+            return Err(self.invalid(field, cell, "currency does not match the row"));
+        }
+
+        let mut number: String = cell[start..end]
+            .chars()
+            .filter(|c| Some(*c) != self.importer.thousands_separator)
+            .collect();
+        if self.importer.decimal_separator != '.' {
+            number = number.replace(self.importer.decimal_separator, ".");
+        }
+
+        let amount = Money::parse_rounded(&number, currency.clone())
+            .map_err(|err| self.invalid(field, cell, err.message()))?;
+        Ok((amount, currency))
+    }
+
+    /// The quote currency of the row, from the currency or pair column, the hint taken from an
+    /// amount, or the default.
+    fn currency(&self, hint: Option<CurrencyCode>) -> Result<Currency, Error> {
+        if let Some(value) = self.get(Field::Currency) {
+            let currency = resolver::resolve(value)
+                .map_err(|_| self.invalid(Field::Currency, value, "unknown currency"))?;
+            return Ok(currency.into());
+        }
+        if let Some(pair) = self.pair()? {
+            return Ok(pair.quote().clone());
+        }
+
+        hint.or(self.importer.currency)
+            .map(Currency::from)
+            .ok_or_else(|| self.invalid(Field::Currency, "", "currency cannot be inferred"))
+    }
+
+    /// The asset of the row, from the asset or pair column or the default.
+    fn asset(&self) -> Result<Currency, Error> {
+        if let Some(value) = self.get(Field::Asset) {
+            let asset = resolver::resolve(value)
+                .map_err(|_| self.invalid(Field::Asset, value, "unknown asset"))?;
+            return Ok(asset.into());
+        }
+        if let Some(pair) = self.pair()? {
+            return Ok(pair.base().clone());
+        }
+
+        self.importer
+            .asset
+            .map(Currency::from)
+            .ok_or_else(|| self.invalid(Field::Asset, "", "asset cannot be inferred"))
+    }
+
+    fn pair(&self) -> Result<Option<CurrencyPair>, Error> {
+        let Some(value) = self.get(Field::Pair) else {
+            return Ok(None);
+        };
+
+        let (base, quote) = match value.split_once(['/', '-', '_']) {
+            Some(parts) => parts,
+            None if value.len() == 6 && value.is_ascii() => value.split_at(3),
+            None => return Err(self.invalid(Field::Pair, value, "unknown pair")),
+        };
+        match (resolver::resolve(base), resolver::resolve(quote)) {
+            (Ok(base), Ok(quote)) => Ok(Some(CurrencyPair::new(base.into(), quote.into()))),
+            _ => Err(self.invalid(Field::Pair, value, "unknown pair")),
+        }
+    }
+
+    fn invalid(&self, field: Field, value: &str, reason: &str) -> Error {
+        Error::new("Invalid row", ErrorCode::Invalid).with_meta(
+            ErrorMeta::new()
+                .add("line", self.line.to_string().as_str())
+                .add("field", field.name())
+                .add("value", value)
+                .add("reason", reason)
+                .build(),
+        )
+    }
+}
+
+/// The cost of `quantity` at `price` per whole unit, in the price's currency.
+fn total(quantity: &Money, price: &Money) -> Result<Money, Error> {
+    let scale = 10i128.pow(quantity.currency().decimal_places());
+    let total = mul_div_round(quantity.amount(), price.amount(), scale)
+        .ok_or_else(|| out_of_range("total", price.currency()))?;
+    from_wide(total, price.currency())
+}
+
+fn read_error(err: &csv::Error) -> Error {
+    let line = err.position().map_or(0, |position| position.line());
+    Error::new("Invalid row", ErrorCode::Invalid).with_meta(
+        ErrorMeta::new()
+            .add("line", line.to_string().as_str())
+            .add("reason", err.to_string().as_str())
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::ledger::{Ledger, TransactionKind};
+
+    fn lines(errors: &[Error]) -> Vec<&str> {
+        errors
+            .iter()
+            .map(|error| error.meta_value("line").unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_prices_with_defaults() {
+        let csv = "timestamp,price,volume,pair
+2023-06-01,27000.50,12.5,BTC/USD
+2023-06-02 12:30:00,\"$27,100.00\",,BTC/USD
+yesterday,27200,,BTC/USD
+1685836800,27300,1,BTC-EUR
+2023-06-05,€27400,1,BTC/USD
+";
+        let import = CsvImporter::new()
+            .thousands_separator(Some(','))
+            .prices(csv)
+            .unwrap();
+
+        let prices: Vec<String> = import
+            .records()
+            .iter()
+            .map(|point| point.price().to_string())
+            .collect();
+        assert_eq!(prices, vec!["$27000.50", "$27100.00", "€27300.00"]);
+        assert_eq!(
+            import.records()[0].volume().unwrap().to_decimal_string(),
+            "12.50000000"
+        );
+        assert_eq!(
+            import.records()[2].timestamp(),
+            Utc.with_ymd_and_hms(2023, 6, 4, 0, 0, 0).unwrap()
+        );
+
+        assert_eq!(lines(import.errors()), vec!["4", "6"]);
+        assert_eq!(import.errors()[0].meta_value("field"), Some("timestamp"));
+        assert_eq!(import.errors()[0].meta_value("value"), Some("yesterday"));
+        assert_eq!(
+            import.errors()[1].meta_value("reason"),
+            Some("currency does not match the row")
+        );
+    }
+
+    #[test]
+    fn test_mapped_columns_and_formats() {
+        let csv = "Datum;Kurs;Waehrung
+01.06.2023;27000,50;EUR
+02.06.2023;27.100,25;
+03.06.2023;abc;EUR
+";
+        let import = CsvImporter::new()
+            .delimiter(b';')
+            .column(Field::Timestamp, "datum")
+            .column(Field::Price, "Kurs")
+            .column(Field::Currency, "Waehrung")
+            .date_format("%d.%m.%Y")
+            .decimal_separator(',')
+            .thousands_separator(Some('.'))
+            .currency(CurrencyCode::GBP)
+            .prices(csv)
+            .unwrap();
+
+        let prices: Vec<String> = import
+            .records()
+            .iter()
+            .map(|point| point.price().to_string())
+            .collect();
+        assert_eq!(prices, vec!["€27000.50", "£27100.25"]);
+        assert_eq!(lines(import.errors()), vec!["4"]);
+
+        let error = CsvImporter::new()
+            .column(Field::Price, "close")
+            .prices("timestamp,price\n")
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("column"), Some("close"));
+    }
+
+    #[test]
+    fn test_without_header() {
+        let import = CsvImporter::new()
+            .has_header(false)
+            .column_index(Field::Timestamp, 0)
+            .column_index(Field::Price, 1)
+            .date_format("unix_ms")
+            .currency(CurrencyCode::USD)
+            .prices("1685577600000,27000\n1685664000000\n")
+            .unwrap();
+        assert_eq!(import.records().len(), 1);
+        assert_eq!(lines(import.errors()), vec!["2"]);
+        assert_eq!(
+            import.errors()[0].meta_value("reason"),
+            Some("value is missing")
+        );
+    }
+
+    #[test]
+    fn test_trades() {
+        let csv = "timestamp,side,pair,quantity,price,total,fee
+2023-01-01,buy,XBTUSD,1.5,20000,,10
+2023-02-01,Sell,Bitcoin/US$,0.5,,12000,2.5
+2023-03-01,deposit,BTC/USD,0.1,,,
+2023-03-02,withdrawal,BTC/USD,0.05,,,
+2023-03-03,swap,BTC/USD,1,,,
+2023-03-04,sell,BTC/USD,1,,,
+";
+        let import = CsvImporter::new().trades(csv).unwrap();
+        let kinds: Vec<TransactionKind> = import.records().iter().map(|t| t.kind()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TransactionKind::Buy,
+                TransactionKind::Sell,
+                TransactionKind::TransferIn,
+                TransactionKind::TransferOut
+            ]
+        );
+        assert_eq!(
+            import.records()[0].total().unwrap().to_string(),
+            "$30000.00"
+        );
+        assert_eq!(
+            import.records()[0].fee_paid().unwrap().to_string(),
+            "$10.00"
+        );
+        assert_eq!(import.records()[2].total(), None);
+
+        assert_eq!(lines(import.errors()), vec!["6", "7"]);
+        assert_eq!(import.errors()[0].meta_value("field"), Some("side"));
+        assert_eq!(import.errors()[1].meta_value("field"), Some("total"));
+
+        let mut ledger = Ledger::new(CurrencyCode::USD.into());
+        for transaction in import.into_parts().0 {
+            ledger.record(transaction).unwrap();
+        }
+        let position = ledger.positions().unwrap().remove(0);
+        assert_eq!(position.quantity().to_decimal_string(), "1.05000000");
+    }
+}
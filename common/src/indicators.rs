@@ -0,0 +1,255 @@
+//! Technical indicators over a `PriceSeries`.
+//!
+//! Every indicator is incremental: feed it one `PricePoint` at a time with `next` to use it on a
+//! live stream, or call `batch` to run it over a whole series. Amounts are computed in minor units
+//! with wide integer arithmetic and rounded half to even, so results are exact `Money` values in
+//! the series' `Currency`.
+//!
+//! ```
+//! use chrono::{TimeZone, Utc};
+//! use common::currency::Currency;
+//! use common::indicators::{Indicator, Sma};
+//! use common::money::Money;
+//! use common::price::{PricePoint, PriceSeries};
+//!
+//! let usd = Currency::new().code("USD").name("Dollar").symbol("USD").build().unwrap();
+//! let mut series = PriceSeries::new(usd.clone());
+//! for (hour, price) in [(0, "100.00"), (1, "101.00"), (2, "105.00")] {
+//!     let timestamp = Utc.with_ymd_and_hms(2023, 1, 1, hour, 0, 0).unwrap();
+//!     let price = Money::parse(price, usd.clone()).unwrap();
+//!     series.push(PricePoint::new(timestamp, price)).unwrap();
+//! }
+//!
+//! let averages = Sma::new(2).batch(&series).unwrap();
+//! assert_eq!(averages[0], None);
+//! assert_eq!(averages[1].as_ref().unwrap().to_decimal_string(), "100.50");
+//! assert_eq!(averages[2].as_ref().unwrap().to_decimal_string(), "103.00");
+//! ```
+
+pub mod change;
+pub mod moving_average;
+pub mod twap;
+pub mod volatility;
+pub mod vwap;
+
+use utils::errors::Error;
+
+use super::currency::Currency;
+use super::money::{ensure_currency, Money};
+use super::price::{PricePoint, PriceSeries};
+
+pub use change::PercentChange;
+pub use moving_average::{Ema, Sma};
+pub use twap::Twap;
+pub use volatility::StdDev;
+pub use vwap::Vwap;
+
+/// A streaming computation over price points.
+pub trait Indicator {
+    type Output;
+
+    /// Feeds the next point and returns the indicator value, or `None` while warming up.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the point is quoted in a different currency than the
+    /// points seen before it.
+    fn next(&mut self, point: &PricePoint) -> Result<Option<Self::Output>, Error>;
+
+    /// Clears all state so the indicator can be reused on another stream.
+    fn reset(&mut self);
+
+    /// Runs the indicator over a whole series, returning one value per point.
+    fn batch(mut self, series: &PriceSeries) -> Result<Vec<Option<Self::Output>>, Error>
+    where
+        Self: Sized,
+    {
+        self.reset();
+        series.iter().map(|point| self.next(point)).collect()
+    }
+}
+
+/// Records the currency of the first amount seen and rejects amounts in any other currency.
+fn track_currency(tracked: &mut Option<Currency>, money: &Money) -> Result<(), Error> {
+    match tracked {
+        Some(currency) => ensure_currency(currency, money.currency()),
+        None => {
+            *tracked = Some(money.currency().clone());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use utils::errors::ErrorCode;
+
+    use super::*;
+    use crate::currency::code::CurrencyCode;
+    use crate::test_support::currency;
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    fn series(prices: &[i64]) -> PriceSeries {
+        let points = prices
+            .iter()
+            .enumerate()
+            .map(|(i, price)| {
+                PricePoint::new(
+                    start() + Duration::minutes(i as i64),
+                    Money::from_minor(*price, currency(CurrencyCode::USD)),
+                )
+            })
+            .collect();
+        PriceSeries::from_points(currency(CurrencyCode::USD), points).unwrap()
+    }
+
+    fn amounts(values: Vec<Option<Money>>) -> Vec<Option<i128>> {
+        values.into_iter().map(|v| v.map(|m| m.amount())).collect()
+    }
+
+    #[test]
+    fn test_sma() {
+        let values = Sma::new(3)
+            .batch(&series(&[100, 200, 300, 400, 501]))
+            .unwrap();
+        assert_eq!(
+            amounts(values),
+            vec![None, None, Some(200), Some(300), Some(400)]
+        );
+    }
+
+    #[test]
+    fn test_ema() {
+        let values = Ema::new(3)
+            .batch(&series(&[100, 200, 300, 400, 500]))
+            .unwrap();
+        // Seeded with the SMA of the first three points, then alpha = 2 / (3 + 1).
+        assert_eq!(
+            amounts(values),
+            vec![None, None, Some(200), Some(300), Some(400)]
+        );
+    }
+
+    #[test]
+    fn test_vwap() {
+        let points = vec![
+            PricePoint::new(start(), Money::from_minor(100, currency(CurrencyCode::USD)))
+                .with_volume(Money::from_minor(1, currency(CurrencyCode::BTC))),
+            PricePoint::new(start(), Money::from_minor(200, currency(CurrencyCode::USD)))
+                .with_volume(Money::from_minor(3, currency(CurrencyCode::BTC))),
+            PricePoint::new(start(), Money::from_minor(400, currency(CurrencyCode::USD))),
+        ];
+        let series = PriceSeries::from_points(currency(CurrencyCode::USD), points).unwrap();
+
+        let values = Vwap::new().batch(&series).unwrap();
+        assert_eq!(amounts(values), vec![Some(100), Some(175), Some(175)]);
+
+        let values = Vwap::rolling(1).batch(&series).unwrap();
+        assert_eq!(amounts(values), vec![Some(100), Some(200), None]);
+    }
+
+    #[test]
+    fn test_twap() {
+        let points = vec![
+            PricePoint::new(start(), Money::from_minor(100, currency(CurrencyCode::USD))),
+            PricePoint::new(
+                start() + Duration::minutes(3),
+                Money::from_minor(200, currency(CurrencyCode::USD)),
+            ),
+            PricePoint::new(
+                start() + Duration::minutes(4),
+                Money::from_minor(400, currency(CurrencyCode::USD)),
+            ),
+            PricePoint::new(
+                start() + Duration::minutes(8),
+                Money::from_minor(400, currency(CurrencyCode::USD)),
+            ),
+        ];
+        let series = PriceSeries::from_points(currency(CurrencyCode::USD), points).unwrap();
+
+        let values = Twap::new(Duration::minutes(4)).batch(&series).unwrap();
+        // 100 held for 3 minutes then 200 for 1; then the window slides past the 100.
+        assert_eq!(amounts(values), vec![None, Some(100), Some(125), Some(400)]);
+    }
+
+    #[test]
+    fn test_std_dev() {
+        let values = StdDev::new(4)
+            .batch(&series(&[200, 400, 400, 400, 500, 500, 700, 900]))
+            .unwrap();
+        assert_eq!(
+            amounts(values),
+            vec![
+                None,
+                None,
+                None,
+                Some(87),
+                Some(43),
+                Some(50),
+                Some(109),
+                Some(166)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wide_amounts() {
+        let ether = |wei: i128| Money::from_minor(wei, currency(CurrencyCode::ETH));
+        let wei = 10i128.pow(18);
+        let points = [20, 21]
+            .into_iter()
+            .enumerate()
+            .map(|(i, price)| {
+                PricePoint::new(start() + Duration::minutes(i as i64), ether(price * wei))
+                    .with_volume(ether(10 * wei))
+            })
+            .collect();
+        let series = PriceSeries::from_points(currency(CurrencyCode::ETH), points).unwrap();
+
+        // 20 ETH in wei squares to more than an i128 holds.
+        let values = StdDev::new(2).batch(&series).unwrap();
+        assert_eq!(amounts(values), vec![None, Some(wei / 2)]);
+        let values = Ema::new(1).batch(&series).unwrap();
+        assert_eq!(amounts(values), vec![Some(20 * wei), Some(21 * wei)]);
+
+        let error = Vwap::new().batch(&series).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("operation"), Some("vwap"));
+        assert_eq!(error.meta_value("currency"), Some("ETH"));
+
+        // A price near the limit held for a minute, weighted by the millisecond.
+        let points = (0..2)
+            .map(|i| PricePoint::new(start() + Duration::minutes(i), ether(i128::MAX / 1000)))
+            .collect();
+        let series = PriceSeries::from_points(currency(CurrencyCode::ETH), points).unwrap();
+        let error = Twap::new(Duration::minutes(5)).batch(&series).unwrap_err();
+        assert_eq!(error.meta_value("operation"), Some("twap"));
+    }
+
+    #[test]
+    fn test_percent_change() {
+        let values = PercentChange::new(2)
+            .batch(&series(&[100, 150, 110, 0, 55]))
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![None, None, Some(10.0), Some(-100.0), Some(-50.0)]
+        );
+    }
+
+    #[test]
+    fn test_streaming_rejects_mixed_currencies() {
+        let mut sma = Sma::new(2);
+        let point = PricePoint::new(start(), Money::from_minor(100, currency(CurrencyCode::USD)));
+        assert_eq!(sma.next(&point).unwrap(), None);
+
+        let point = PricePoint::new(start(), Money::from_minor(100, currency(CurrencyCode::BTC)));
+        let error = sma.next(&point).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+
+        sma.reset();
+        assert_eq!(sma.next(&point).unwrap(), None);
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::VecDeque;
+
+use utils::errors::Error;
+
+use super::{track_currency, Indicator};
+use crate::currency::Currency;
+use crate::price::PricePoint;
+
+/// Percent change between the current price and the price `period` points earlier.
+///
+/// A change from a zero price is undefined and yields `None`.
+pub struct PercentChange {
+    period: usize,
+    window: VecDeque<i128>,
+    currency: Option<Currency>,
+}
+
+impl PercentChange {
+    pub fn new(period: usize) -> Self {
+        assert!(
+            period > 0,
+            "percent change period must be greater than zero"
+        );
+        Self {
+            period,
+            window: VecDeque::with_capacity(period + 1),
+            currency: None,
+        }
+    }
+
+    pub fn period(&self) -> usize {
+        self.period
+    }
+}
+
+impl Indicator for PercentChange {
+    type Output = f64;
+
+    fn next(&mut self, point: &PricePoint) -> Result<Option<f64>, Error> {
+        track_currency(&mut self.currency, point.price())?;
+
+        self.window.push_back(point.price().amount());
+        if self.window.len() > self.period + 1 {
+            self.window.pop_front();
+        }
+        if self.window.len() <= self.period {
+            return Ok(None);
+        }
+
+        let previous = self.window[0];
+        if previous == 0 {
+            return Ok(None);
+        }
+
+        let change = point.price().amount() - previous;
+        Ok(Some(change as f64 * 100.0 / previous as f64))
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.currency = None;
+    }
+}
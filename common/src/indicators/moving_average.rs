@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+
+use utils::errors::Error;
+
+use super::{track_currency, Indicator};
+use crate::currency::Currency;
+use crate::money::{div_round, from_wide, mul_div_round, out_of_range, Money};
+use crate::price::PricePoint;
+
+/// Extra precision kept by the EMA between points so rounding does not accumulate.
+const EMA_SCALE: i128 = 1_000_000_000;
+
+/// Simple moving average of the last `period` prices.
+pub struct Sma {
+    period: usize,
+    window: VecDeque<i128>,
+    sum: i128,
+    currency: Option<Currency>,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "SMA period must be greater than zero");
+        Self {
+            period,
+            window: VecDeque::with_capacity(period),
+            sum: 0,
+            currency: None,
+        }
+    }
+
+    pub fn period(&self) -> usize {
+        self.period
+    }
+}
+
+impl Indicator for Sma {
+    type Output = Money;
+
+    fn next(&mut self, point: &PricePoint) -> Result<Option<Money>, Error> {
+        track_currency(&mut self.currency, point.price())?;
+
+        let price = point.price().amount();
+        self.window.push_back(price);
+        self.sum = self
+            .sum
+            .checked_add(price)
+            .ok_or_else(|| out_of_range("sma", point.price().currency()))?;
+        if self.window.len() > self.period {
+            if let Some(oldest) = self.window.pop_front() {
+                self.sum -= oldest;
+            }
+        }
+
+        if self.window.len() < self.period {
+            return Ok(None);
+        }
+
+        from_wide(
+            div_round(self.sum, self.period as i128),
+            point.price().currency(),
+        )
+        .map(Some)
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.sum = 0;
+        self.currency = None;
+    }
+}
+
+/// Exponential moving average with smoothing factor `2 / (period + 1)`, seeded with the SMA of
+/// the first `period` prices.
+pub struct Ema {
+    period: usize,
+    seed: Sma,
+    value: Option<i128>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            seed: Sma::new(period),
+            value: None,
+        }
+    }
+
+    pub fn period(&self) -> usize {
+        self.period
+    }
+}
+
+impl Indicator for Ema {
+    type Output = Money;
+
+    fn next(&mut self, point: &PricePoint) -> Result<Option<Money>, Error> {
+        let currency = point.price().currency();
+        let value = match self.value {
+            Some(previous) => {
+                track_currency(&mut self.seed.currency, point.price())?;
+                point
+                    .price()
+                    .amount()
+                    .checked_mul(EMA_SCALE)
+                    .and_then(|price| price.checked_sub(previous))
+                    .and_then(|change| mul_div_round(change, 2, self.period as i128 + 1))
+                    .and_then(|step| previous.checked_add(step))
+            }
+            None => match self.seed.next(point)? {
+                Some(_) => mul_div_round(self.seed.sum, EMA_SCALE, self.period as i128),
+                None => return Ok(None),
+            },
+        }
+        .ok_or_else(|| out_of_range("ema", currency))?;
+
+        self.value = Some(value);
+        from_wide(div_round(value, EMA_SCALE), currency).map(Some)
+    }
+
+    fn reset(&mut self) {
+        self.seed.reset();
+        self.value = None;
+    }
+}
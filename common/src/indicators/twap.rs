@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Utc};
+use utils::errors::Error;
+
+use super::{track_currency, Indicator};
+use crate::currency::Currency;
+use crate::money::{div_round, from_wide, out_of_range, Money};
+use crate::price::PricePoint;
+
+/// Time weighted average price over a trailing time window.
+///
+/// Each price is treated as holding until the next point arrives, so a price that stood for
+/// ten minutes weighs ten times as much as one that stood for one minute.
+pub struct Twap {
+    window: Duration,
+    segments: VecDeque<Segment>,
+    last: Option<(DateTime<Utc>, i128)>,
+    currency: Option<Currency>,
+}
+
+struct Segment {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    price: i128,
+}
+
+impl Twap {
+    pub fn new(window: Duration) -> Self {
+        assert!(
+            window > Duration::zero(),
+            "TWAP window must be greater than zero"
+        );
+        Self {
+            window,
+            segments: VecDeque::new(),
+            last: None,
+            currency: None,
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+}
+
+impl Indicator for Twap {
+    type Output = Money;
+
+    fn next(&mut self, point: &PricePoint) -> Result<Option<Money>, Error> {
+        track_currency(&mut self.currency, point.price())?;
+
+        let now = point.timestamp();
+        if let Some((start, price)) = self.last {
+            if now > start {
+                self.segments.push_back(Segment {
+                    start,
+                    end: now,
+                    price,
+                });
+            }
+        }
+        self.last = Some((now, point.price().amount()));
+
+        let window_start = now - self.window;
+        while matches!(self.segments.front(), Some(segment) if segment.end <= window_start) {
+            self.segments.pop_front();
+        }
+
+        let currency = point.price().currency();
+        let mut weighted: i128 = 0;
+        let mut elapsed: i128 = 0;
+        for segment in &self.segments {
+            let start = segment.start.max(window_start);
+            let millis = (segment.end - start).num_milliseconds() as i128;
+            weighted = segment
+                .price
+                .checked_mul(millis)
+                .and_then(|product| weighted.checked_add(product))
+                .ok_or_else(|| out_of_range("twap", currency))?;
+            elapsed += millis;
+        }
+
+        if elapsed == 0 {
+            return Ok(None);
+        }
+
+        from_wide(div_round(weighted, elapsed), currency).map(Some)
+    }
+
+    fn reset(&mut self) {
+        self.segments.clear();
+        self.last = None;
+        self.currency = None;
+    }
+}
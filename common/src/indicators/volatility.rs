@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+
+use utils::errors::Error;
+
+use super::{track_currency, Indicator};
+use crate::currency::Currency;
+use crate::money::{div_round, from_wide, out_of_range, Money};
+use crate::price::PricePoint;
+
+/// Rolling population standard deviation of the last `period` prices, as a measure of
+/// volatility in the series' currency.
+pub struct StdDev {
+    period: usize,
+    window: VecDeque<i128>,
+    currency: Option<Currency>,
+}
+
+impl StdDev {
+    pub fn new(period: usize) -> Self {
+        assert!(
+            period > 0,
+            "standard deviation period must be greater than zero"
+        );
+        Self {
+            period,
+            window: VecDeque::with_capacity(period),
+            currency: None,
+        }
+    }
+
+    pub fn period(&self) -> usize {
+        self.period
+    }
+}
+
+impl Indicator for StdDev {
+    type Output = Money;
+
+    fn next(&mut self, point: &PricePoint) -> Result<Option<Money>, Error> {
+        track_currency(&mut self.currency, point.price())?;
+
+        self.window.push_back(point.price().amount());
+        if self.window.len() > self.period {
+            self.window.pop_front();
+        }
+
+        if self.window.len() < self.period {
+            return Ok(None);
+        }
+
+        let currency = point.price().currency();
+        let spread = spread(&self.window).ok_or_else(|| out_of_range("std_dev", currency))?;
+        // sigma = sqrt(spread) / n, with three extra digits so the final division can round
+        // correctly. Spreads too wide for them get fewer; a minor unit is noise at that size.
+        let (root, scale) = [1_000u128, 100, 10]
+            .into_iter()
+            .find_map(|scale| {
+                let scaled = spread.checked_mul(scale * scale)?;
+                Some((isqrt(scaled), scale))
+            })
+            .unwrap_or((isqrt(spread), 1));
+
+        let n = self.period as i128;
+        from_wide(div_round(root as i128, n * scale as i128), currency).map(Some)
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.currency = None;
+    }
+}
+
+/// `n * sum(x^2) - sum(x)^2` over the window, which is `n^2` times the variance.
+///
+/// Computed on the deviations from the first price, which gives the same result while keeping
+/// the squares small: 20 ETH in wei already squares to more than an `i128` holds.
+fn spread(window: &VecDeque<i128>) -> Option<u128> {
+    let origin = *window.front()?;
+    let mut sum = 0i128;
+    let mut sum_of_squares = 0i128;
+    for price in window {
+        let deviation = price.checked_sub(origin)?;
+        sum = sum.checked_add(deviation)?;
+        sum_of_squares = sum_of_squares.checked_add(deviation.checked_mul(deviation)?)?;
+    }
+
+    let n = window.len() as i128;
+    let spread = n
+        .checked_mul(sum_of_squares)?
+        .checked_sub(sum.checked_mul(sum)?)?;
+    Some(spread as u128)
+}
+
+/// Integer square root, rounded down.
+fn isqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+
+    let mut x = 1u128 << (128 - value.leading_zeros()).div_ceil(2);
+    loop {
+        let y = (x + value / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
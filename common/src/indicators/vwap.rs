@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+
+use utils::errors::Error;
+
+use super::{track_currency, Indicator};
+use crate::currency::Currency;
+use crate::money::{div_round, from_wide, out_of_range, Money};
+use crate::price::PricePoint;
+
+/// Volume weighted average price, either cumulative from the first point or over the last
+/// `period` points. Points without a volume carry no weight.
+pub struct Vwap {
+    period: Option<usize>,
+    window: VecDeque<(i128, i128)>,
+    notional: i128,
+    volume: i128,
+    currency: Option<Currency>,
+    volume_currency: Option<Currency>,
+}
+
+impl Vwap {
+    /// A VWAP over every point seen since the last reset.
+    pub fn new() -> Self {
+        Self {
+            period: None,
+            window: VecDeque::new(),
+            notional: 0,
+            volume: 0,
+            currency: None,
+            volume_currency: None,
+        }
+    }
+
+    /// A VWAP over the last `period` points.
+    pub fn rolling(period: usize) -> Self {
+        assert!(period > 0, "VWAP period must be greater than zero");
+        Self {
+            period: Some(period),
+            ..Self::new()
+        }
+    }
+}
+
+impl Default for Vwap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Indicator for Vwap {
+    type Output = Money;
+
+    fn next(&mut self, point: &PricePoint) -> Result<Option<Money>, Error> {
+        track_currency(&mut self.currency, point.price())?;
+
+        let volume = match point.volume() {
+            Some(volume) => {
+                track_currency(&mut self.volume_currency, volume)?;
+                volume.amount()
+            }
+            None => 0,
+        };
+        let currency = point.price().currency();
+        let notional = point
+            .price()
+            .amount()
+            .checked_mul(volume)
+            .ok_or_else(|| out_of_range("vwap", currency))?;
+
+        self.notional = self
+            .notional
+            .checked_add(notional)
+            .ok_or_else(|| out_of_range("vwap", currency))?;
+        self.volume = self
+            .volume
+            .checked_add(volume)
+            .ok_or_else(|| out_of_range("vwap", currency))?;
+
+        if let Some(period) = self.period {
+            self.window.push_back((notional, volume));
+            if self.window.len() > period {
+                if let Some((notional, volume)) = self.window.pop_front() {
+                    self.notional -= notional;
+                    self.volume -= volume;
+                }
+            }
+        }
+
+        if self.volume == 0 {
+            return Ok(None);
+        }
+
+        from_wide(div_round(self.notional, self.volume), currency).map(Some)
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.notional = 0;
+        self.volume = 0;
+        self.currency = None;
+        self.volume_currency = None;
+    }
+}
@@ -0,0 +1,467 @@
+//! Inflation adjustment.
+//!
+//! A `CpiSeries` is a monthly consumer price index for one fiat currency, loaded from CSV or
+//! JSON. An `InflationAdjuster` holds one series per currency and restates historical amounts
+//! in the money of another point in time: the latest month with data, a given date, or the
+//! average of a base year. Months missing from a series are interpolated linearly between their
+//! neighbours; dates before the first or after the last month of a series are an error rather
+//! than being extrapolated.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, NaiveDate};
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+use utils::json::JSON;
+
+use super::currency::code::CurrencyCode;
+use super::money::{from_wide, mul_div_round, out_of_range, Money};
+use super::price::{PricePoint, PriceSeries};
+
+/// Precision of the adjustment ratio applied to minor units.
+const RATIO_SCALE: f64 = 1e12;
+
+/// One month of a CPI series as it appears in CSV and JSON input, e.g.
+/// `{"date":"2023-01","value":299.17}`. Dates may also carry a day, which is ignored.
+#[derive(Debug)]
+#[macros::json]
+struct CpiRow {
+    date: String,
+    value: f64,
+}
+
+/// The money whose purchasing power amounts are restated in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Basis {
+    /// The latest month of the series, i.e. today's money as far as the data goes.
+    Latest,
+    /// The month containing the date.
+    Date(NaiveDate),
+    /// The average over the twelve months of a year, as statistics offices rebase indices.
+    Year(i32),
+}
+
+/// A monthly consumer price index for a fiat currency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpiSeries {
+    currency: CurrencyCode,
+    /// Index values keyed by months since year zero.
+    months: BTreeMap<i32, f64>,
+}
+
+impl CpiSeries {
+    /// Creates an empty series.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the currency is not a fiat currency.
+    pub fn new(currency: CurrencyCode) -> Result<Self, Error> {
+        if !currency.is_fiat() {
+            return Err(
+                Error::new("CPI series require a fiat currency", ErrorCode::Invalid).with_meta(
+                    ErrorMeta::new()
+                        .add("currency", currency.to_string())
+                        .build(),
+                ),
+            );
+        }
+
+        Ok(Self {
+            currency,
+            months: BTreeMap::new(),
+        })
+    }
+
+    /// Parses CSV with a header row and `date,value` columns, e.g. `2023-01,299.17`.
+    ///
+    /// Fails with `ErrorCode::Invalid` naming the line of the first malformed row.
+    pub fn parse_csv(currency: CurrencyCode, csv: &str) -> Result<Self, Error> {
+        let mut series = Self::new(currency)?;
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(csv.as_bytes());
+        let headers = reader.headers().map_err(csv_error)?.clone();
+
+        for record in reader.records() {
+            let record = record.map_err(csv_error)?;
+            let line = record.position().map_or(0, |position| position.line());
+            let row: CpiRow = record
+                .deserialize(Some(&headers))
+                .map_err(|err| invalid_row(line, &err.to_string()))?;
+            series
+                .insert_row(&row)
+                .map_err(|err| invalid_row(line, err.message()))?;
+        }
+
+        Ok(series)
+    }
+
+    /// Parses a JSON array of `{"date":"2023-01","value":299.17}` objects.
+    ///
+    /// Fails with `ErrorCode::JsonParse` if the input is not such an array and with
+    /// `ErrorCode::Invalid` naming the index of the first malformed entry.
+    pub fn parse_json(currency: CurrencyCode, json: &str) -> Result<Self, Error> {
+        let mut series = Self::new(currency)?;
+        let rows = Vec::<CpiRow>::from_json(json)?;
+
+        for (index, row) in rows.iter().enumerate() {
+            series.insert_row(row).map_err(|err| {
+                Error::new("Invalid CPI entry", ErrorCode::Invalid).with_meta(
+                    ErrorMeta::new()
+                        .add("index", index.to_string().as_str())
+                        .add("reason", err.message())
+                        .build(),
+                )
+            })?;
+        }
+
+        Ok(series)
+    }
+
+    /// Sets the index value for the month containing the date.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the value is not a positive number.
+    pub fn insert(&mut self, date: NaiveDate, value: f64) -> Result<(), Error> {
+        if !(value.is_finite() && value > 0.0) {
+            return Err(
+                Error::new("CPI value must be positive", ErrorCode::Invalid).with_meta(
+                    ErrorMeta::new()
+                        .add("date", format_month(month_index(date)).as_str())
+                        .add("value", value.to_string().as_str())
+                        .build(),
+                ),
+            );
+        }
+
+        self.months.insert(month_index(date), value);
+        Ok(())
+    }
+
+    pub fn currency(&self) -> CurrencyCode {
+        self.currency
+    }
+
+    pub fn len(&self) -> usize {
+        self.months.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.months.is_empty()
+    }
+
+    /// The first day of the earliest month with data.
+    pub fn first(&self) -> Option<NaiveDate> {
+        self.months.keys().next().map(|month| month_date(*month))
+    }
+
+    /// The first day of the latest month with data.
+    pub fn last(&self) -> Option<NaiveDate> {
+        self.months
+            .keys()
+            .next_back()
+            .map(|month| month_date(*month))
+    }
+
+    /// The index value for the month containing the date, interpolated if the month is missing.
+    ///
+    /// Fails with `ErrorCode::NotFound` if the date is outside the series.
+    pub fn index(&self, date: NaiveDate) -> Result<f64, Error> {
+        let month = month_index(date);
+        if let Some(value) = self.months.get(&month) {
+            return Ok(*value);
+        }
+
+        let before = self.months.range(..month).next_back();
+        let after = self.months.range(month..).next();
+        match (before, after) {
+            (Some((start, low)), Some((end, high))) => {
+                let progress = (month - start) as f64 / (end - start) as f64;
+                Ok(low + (high - low) * progress)
+            }
+            _ => Err(self.out_of_range(&format_month(month))),
+        }
+    }
+
+    /// The index value that amounts are restated against for the basis.
+    ///
+    /// Fails with `ErrorCode::NotFound` if the series is empty or does not cover the basis.
+    pub fn base(&self, basis: Basis) -> Result<f64, Error> {
+        match basis {
+            Basis::Latest => self
+                .months
+                .values()
+                .next_back()
+                .copied()
+                .ok_or_else(|| self.out_of_range("latest")),
+            Basis::Date(date) => self.index(date),
+            Basis::Year(year) => {
+                let start = NaiveDate::from_ymd_opt(year, 1, 1)
+                    .ok_or_else(|| self.out_of_range(&year.to_string()))?;
+                let first = month_index(start);
+                let mut total = 0.0;
+                for month in first..first + 12 {
+                    total += self
+                        .index(month_date(month))
+                        .map_err(|_| self.out_of_range(&year.to_string()))?;
+                }
+                Ok(total / 12.0)
+            }
+        }
+    }
+
+    fn insert_row(&mut self, row: &CpiRow) -> Result<(), Error> {
+        let date = parse_month(&row.date).ok_or_else(|| {
+            Error::new("Invalid CPI date", ErrorCode::Invalid)
+                .with_meta(ErrorMeta::new().add("date", &row.date).build())
+        })?;
+        if self.months.contains_key(&month_index(date)) {
+            return Err(Error::new("Duplicate CPI month", ErrorCode::Invalid)
+                .with_meta(ErrorMeta::new().add("date", &row.date).build()));
+        }
+
+        self.insert(date, row.value)
+    }
+
+    fn out_of_range(&self, date: &str) -> Error {
+        let bound = |month: Option<&i32>| month.map_or(String::new(), |m| format_month(*m));
+        Error::new("No CPI data for date", ErrorCode::NotFound).with_meta(
+            ErrorMeta::new()
+                .add("currency", self.currency.to_string())
+                .add("date", date)
+                .add("first", bound(self.months.keys().next()).as_str())
+                .add("last", bound(self.months.keys().next_back()).as_str())
+                .build(),
+        )
+    }
+}
+
+/// Restates amounts in the purchasing power of another point in time.
+#[derive(Debug, Clone, Default)]
+pub struct InflationAdjuster {
+    series: HashMap<CurrencyCode, CpiSeries>,
+}
+
+impl InflationAdjuster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the CPI series for a currency.
+    ///
+    /// Fails with `ErrorCode::Conflict` if the currency already has a series.
+    pub fn add_series(&mut self, series: CpiSeries) -> Result<(), Error> {
+        if self.series.contains_key(&series.currency()) {
+            return Err(
+                Error::new("CPI series already loaded", ErrorCode::Conflict).with_meta(
+                    ErrorMeta::new()
+                        .add("currency", series.currency().to_string())
+                        .build(),
+                ),
+            );
+        }
+
+        self.series.insert(series.currency(), series);
+        Ok(())
+    }
+
+    pub fn series(&self, currency: &CurrencyCode) -> Option<&CpiSeries> {
+        self.series.get(currency)
+    }
+
+    /// Restates an amount paid on `date` in the money of the basis, rounding half to even.
+    ///
+    /// Fails with `ErrorCode::NotFound` if there is no series for the amount's currency or the
+    /// series does not cover the date or the basis.
+    pub fn restate(&self, amount: &Money, date: NaiveDate, basis: Basis) -> Result<Money, Error> {
+        let currency = amount.currency().code();
+        let series = self.series.get(currency).ok_or_else(|| {
+            Error::new("No CPI series for currency", ErrorCode::NotFound).with_meta(
+                ErrorMeta::new()
+                    .add("currency", currency.to_string())
+                    .build(),
+            )
+        })?;
+
+        let ratio = series.base(basis)? / series.index(date)?;
+        let ratio = (ratio * RATIO_SCALE).round() as i128;
+        let restated = mul_div_round(amount.amount(), ratio, RATIO_SCALE as i128)
+            .ok_or_else(|| out_of_range("restate", amount.currency()))?;
+        from_wide(restated, amount.currency())
+    }
+
+    /// Restates every price of a series in the money of the basis. Volumes are left as they are.
+    pub fn restate_series(&self, series: &PriceSeries, basis: Basis) -> Result<PriceSeries, Error> {
+        let mut restated = PriceSeries::new(series.currency().clone());
+        for point in series {
+            let price = self.restate(point.price(), point.timestamp().date_naive(), basis)?;
+            let mut adjusted = PricePoint::new(point.timestamp(), price);
+            if let Some(volume) = point.volume() {
+                adjusted = adjusted.with_volume(volume.clone());
+            }
+            restated.push(adjusted)?;
+        }
+        Ok(restated)
+    }
+}
+
+fn month_index(date: NaiveDate) -> i32 {
+    date.year() * 12 + date.month0() as i32
+}
+
+fn month_date(month: i32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(month.div_euclid(12), month.rem_euclid(12) as u32 + 1, 1)
+        .unwrap_or_default()
+}
+
+fn format_month(month: i32) -> String {
+    month_date(month).format("%Y-%m").to_string()
+}
+
+/// Parses `YYYY-MM` or `YYYY-MM-DD`.
+fn parse_month(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(&format!("{}-01", date), "%Y-%m-%d"))
+        .ok()
+}
+
+fn csv_error(err: csv::Error) -> Error {
+    let line = err.position().map_or(0, |position| position.line());
+    invalid_row(line, &err.to_string())
+}
+
+fn invalid_row(line: u64, reason: &str) -> Error {
+    Error::new("Invalid CPI row", ErrorCode::Invalid).with_meta(
+        ErrorMeta::new()
+            .add("line", line.to_string().as_str())
+            .add("reason", reason)
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::currency::Currency;
+    use crate::test_support::usd;
+
+    const USD_CPI: &str = "date,value
+2020-01,100.0
+2020-04,103.0
+2021-01,110.0
+2021-12,121.0
+";
+
+    fn date(year: i32, month: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, 15).unwrap()
+    }
+
+    fn adjuster() -> InflationAdjuster {
+        let mut adjuster = InflationAdjuster::new();
+        adjuster
+            .add_series(CpiSeries::parse_csv(CurrencyCode::USD, USD_CPI).unwrap())
+            .unwrap();
+        adjuster
+    }
+
+    #[test]
+    fn test_interpolates_missing_months() {
+        let series = CpiSeries::parse_csv(CurrencyCode::USD, USD_CPI).unwrap();
+        assert_eq!(series.len(), 4);
+        assert_eq!(series.index(date(2020, 4)).unwrap(), 103.0);
+        assert_eq!(series.index(date(2020, 2)).unwrap(), 101.0);
+        assert!((series.index(date(2020, 7)).unwrap() - 105.333_333).abs() < 1e-6);
+        assert_eq!(series.first(), NaiveDate::from_ymd_opt(2020, 1, 1));
+        assert_eq!(series.last(), NaiveDate::from_ymd_opt(2021, 12, 1));
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let series = CpiSeries::parse_csv(CurrencyCode::USD, USD_CPI).unwrap();
+        let error = series.index(date(2019, 12)).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::NotFound);
+        assert_eq!(error.meta_value("date"), Some("2019-12"));
+        assert_eq!(error.meta_value("first"), Some("2020-01"));
+        assert_eq!(error.meta_value("last"), Some("2021-12"));
+
+        let error = series.base(Basis::Year(2022)).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::NotFound);
+        assert_eq!(error.meta_value("date"), Some("2022"));
+    }
+
+    #[test]
+    fn test_restate() {
+        let adjuster = adjuster();
+
+        let restated = adjuster
+            .restate(&usd("100"), date(2020, 1), Basis::Latest)
+            .unwrap();
+        assert_eq!(restated, usd("121"));
+
+        let restated = adjuster
+            .restate(&usd("121"), date(2021, 12), Basis::Date(date(2020, 1)))
+            .unwrap();
+        assert_eq!(restated, usd("100"));
+
+        // 2021 averages 115.5 when interpolating linearly from 110 in January to 121 in December.
+        let restated = adjuster
+            .restate(&usd("100"), date(2021, 1), Basis::Year(2021))
+            .unwrap();
+        assert_eq!(restated, usd("105"));
+
+        let euros = Money::from_minor(100, Currency::from(CurrencyCode::EUR));
+        let error = adjuster
+            .restate(&euros, date(2021, 1), Basis::Latest)
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn test_restate_series() {
+        let prices = PriceSeries::from_points(
+            Currency::from(CurrencyCode::USD),
+            vec![
+                PricePoint::new(Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(), usd("1")),
+                PricePoint::new(Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(), usd("1")),
+            ],
+        )
+        .unwrap();
+
+        let restated = adjuster().restate_series(&prices, Basis::Latest).unwrap();
+        let amounts: Vec<i128> = restated.iter().map(|p| p.price().amount()).collect();
+        assert_eq!(amounts, vec![121, 110]);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let series = CpiSeries::parse_json(
+            CurrencyCode::GBP,
+            r#"[{"date":"2023-01-01","value":126.4},{"date":"2023-02","value":127.9}]"#,
+        )
+        .unwrap();
+        assert_eq!(series.index(date(2023, 2)).unwrap(), 127.9);
+
+        let error = CpiSeries::parse_json(CurrencyCode::GBP, r#"[{"date":"2023-13","value":1}]"#)
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("index"), Some("0"));
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let error =
+            CpiSeries::parse_csv(CurrencyCode::USD, "date,value\n2020-01,100\n2020-01,101\n")
+                .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("line"), Some("3"));
+
+        let error =
+            CpiSeries::parse_csv(CurrencyCode::USD, "date,value\n2020-01,abc\n").unwrap_err();
+        assert_eq!(error.meta_value("line"), Some("2"));
+
+        let error =
+            CpiSeries::parse_csv(CurrencyCode::USD, "date,value\n2020-01,-1\n").unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+
+        let error = CpiSeries::new(CurrencyCode::BTC).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+    }
+}
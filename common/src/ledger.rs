@@ -0,0 +1,363 @@
+//! Portfolio ledger.
+//!
+//! A `Ledger` records buys, sells, transfers and fees of any number of assets, with costs and
+//! proceeds kept in a single ledger currency. Replaying it in time order yields a `Position` per
+//! asset: the lots still held, the disposals made, and the realized gain, with disposals matched
+//! to lots by the ledger's `CostMethod`. A `Statement` values every position at supplied market
+//! prices to add unrealized gains, and a `GainsReport` exports the realized gains to CSV. The
+//! ledger and its results serialize with the `JSON` trait.
+
+pub mod disposal;
+pub mod kind;
+pub mod lot;
+pub mod method;
+pub mod position;
+pub mod report;
+pub mod statement;
+pub mod transaction;
+pub mod valuation;
+
+use std::collections::HashMap;
+
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::currency::code::CurrencyCode;
+use super::currency::Currency;
+use super::money::Money;
+
+pub use disposal::Disposal;
+pub use kind::TransactionKind;
+pub use lot::Lot;
+pub use method::CostMethod;
+pub use position::Position;
+pub use report::{AmountStyle, Column, CsvOptions, GainsReport, ReportRow, Term};
+pub use statement::Statement;
+pub use transaction::Transaction;
+pub use valuation::Valuation;
+
+/// Transactions in assets whose costs and proceeds are kept in one currency.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Ledger {
+    currency: Currency,
+    method: CostMethod,
+    transactions: Vec<Transaction>,
+}
+
+impl Ledger {
+    /// Creates an empty ledger kept in `currency`, matching disposals first in, first out.
+    pub fn new(currency: Currency) -> Self {
+        Self {
+            currency,
+            method: CostMethod::default(),
+            transactions: Vec::new(),
+        }
+    }
+
+    pub fn with_method(mut self, method: CostMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    pub fn method(&self) -> CostMethod {
+        self.method
+    }
+
+    /// The transactions in the order they were recorded.
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    /// Adds a transaction, which may be dated before ones already recorded.
+    ///
+    /// Fails with `ErrorCode::Invalid` and leaves the ledger unchanged if the transaction is
+    /// malformed or would dispose of more of an asset than is held at that time.
+    pub fn record(&mut self, transaction: Transaction) -> Result<(), Error> {
+        transaction.validate(&self.currency)?;
+        self.transactions.push(transaction);
+
+        if let Err(err) = self.positions() {
+            self.transactions.pop();
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Replays the transactions in time order into a position per asset, in the order the assets
+    /// first appear. Transactions with the same timestamp are replayed in recorded order.
+    pub fn positions(&self) -> Result<Vec<Position>, Error> {
+        let mut ordered: Vec<&Transaction> = self.transactions.iter().collect();
+        ordered.sort_by_key(|transaction| transaction.timestamp());
+
+        let mut positions: Vec<Position> = Vec::new();
+        for transaction in ordered {
+            transaction.validate(&self.currency)?;
+
+            let asset = transaction.asset();
+            let index = match positions
+                .iter()
+                .position(|p| p.asset().code() == asset.code())
+            {
+                Some(index) => index,
+                None => {
+                    positions.push(Position::new(asset.clone(), self.currency.clone()));
+                    positions.len() - 1
+                }
+            };
+            positions[index].apply(transaction, self.method)?;
+        }
+
+        Ok(positions)
+    }
+
+    /// Values every position at the price of one whole unit of its asset in the ledger's
+    /// currency.
+    ///
+    /// Fails with `ErrorCode::NotFound` if an open position has no price.
+    pub fn statement(&self, prices: &HashMap<CurrencyCode, Money>) -> Result<Statement, Error> {
+        let zero = Money::zero(self.currency.clone());
+        let mut valuations = Vec::new();
+        let (mut cost_basis, mut market_value, mut unrealized, mut realized) =
+            (zero.clone(), zero.clone(), zero.clone(), zero.clone());
+
+        for position in self.positions()? {
+            let price = prices.get(position.asset().code());
+            let value = match price {
+                Some(price) => position.market_value(price)?,
+                None if position.is_closed() => zero.clone(),
+                None => {
+                    return Err(Error::new("Missing price", ErrorCode::NotFound).with_meta(
+                        ErrorMeta::new()
+                            .add("asset", position.asset().code().to_string())
+                            .build(),
+                    ))
+                }
+            };
+            let gain = value.checked_sub(position.cost_basis())?;
+
+            cost_basis = cost_basis.checked_add(position.cost_basis())?;
+            market_value = market_value.checked_add(&value)?;
+            unrealized = unrealized.checked_add(&gain)?;
+            realized = realized.checked_add(position.realized())?;
+
+            valuations.push(Valuation::new(
+                position.asset().clone(),
+                position.quantity().clone(),
+                price.cloned(),
+                position.cost_basis().clone(),
+                value,
+                gain,
+                position.realized().clone(),
+            ));
+        }
+
+        Ok(Statement::new(
+            self.currency.clone(),
+            valuations,
+            cost_basis,
+            market_value,
+            unrealized,
+            realized,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use utils::json::JSON;
+
+    use super::*;
+    use crate::currency::token::{register, Token};
+    use crate::test_support::{btc, usd};
+
+    fn day(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 1, day, 0, 0, 0).unwrap()
+    }
+
+    fn ledger(method: CostMethod) -> Ledger {
+        let mut ledger = Ledger::new(Currency::from(CurrencyCode::USD)).with_method(method);
+        ledger
+            .record(Transaction::buy(day(1), btc("1"), usd("20000")).with_fee(usd("10")))
+            .unwrap();
+        ledger
+            .record(Transaction::buy(day(2), btc("1"), usd("40000")))
+            .unwrap();
+        ledger
+            .record(Transaction::buy(day(3), btc("1"), usd("30000")))
+            .unwrap();
+        ledger
+            .record(Transaction::sell(day(4), btc("1.5"), usd("75000")).with_fee(usd("30")))
+            .unwrap();
+        ledger
+    }
+
+    fn position(ledger: &Ledger) -> Position {
+        ledger.positions().unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_cost_methods() {
+        // Selling 1.5 BTC for $74,970 net of fees.
+        let fifo = position(&ledger(CostMethod::Fifo));
+        assert_eq!(fifo.realized(), &usd("34960"));
+        assert_eq!(fifo.cost_basis(), &usd("50000"));
+        assert_eq!(fifo.disposals().len(), 2);
+        assert_eq!(fifo.disposals()[0].acquired(), day(1));
+        assert_eq!(fifo.disposals()[0].proceeds(), &usd("49980"));
+
+        let lifo = position(&ledger(CostMethod::Lifo));
+        assert_eq!(lifo.realized(), &usd("24970"));
+        assert_eq!(lifo.cost_basis(), &usd("40010"));
+
+        let hifo = position(&ledger(CostMethod::Hifo));
+        assert_eq!(hifo.realized(), &usd("19970"));
+        assert_eq!(hifo.lots().len(), 2);
+        assert_eq!(hifo.lots()[1].cost(), &usd("15000"));
+
+        let average = position(&ledger(CostMethod::Average));
+        assert_eq!(average.realized(), &usd("29965"));
+        assert_eq!(average.cost_basis(), &usd("45005"));
+        assert_eq!(average.lots().len(), 1);
+
+        for position in [fifo, lifo, hifo, average] {
+            assert_eq!(position.quantity(), &btc("1.5"));
+        }
+    }
+
+    #[test]
+    fn test_statement() {
+        let ledger = ledger(CostMethod::Fifo);
+        let prices = HashMap::from([(CurrencyCode::BTC, usd("50000"))]);
+        let statement = ledger.statement(&prices).unwrap();
+
+        assert_eq!(statement.market_value(), &usd("75000"));
+        assert_eq!(statement.unrealized(), &usd("25000"));
+        assert_eq!(statement.realized(), &usd("34960"));
+        assert_eq!(statement.positions()[0].price(), Some(&usd("50000")));
+
+        let error = ledger.statement(&HashMap::new()).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::NotFound);
+        assert_eq!(error.meta_value("asset"), Some("BTC"));
+    }
+
+    #[test]
+    fn test_wide_amounts() {
+        // A token with 18 decimals priced in ETH: the products of two amounts in wei need more
+        // than 128 bits before they are scaled back down.
+        let token =
+            Currency::from(register(Token::new("TSTW", "Test W", "ⓦ", 18, "ethereum")).unwrap());
+        let eth = Currency::from(CurrencyCode::ETH);
+        let wei = 10i128.pow(18);
+        let mut ledger = Ledger::new(eth.clone());
+        ledger
+            .record(Transaction::buy(
+                day(1),
+                Money::from_minor(20 * wei, token.clone()),
+                Money::from_minor(40 * wei, eth.clone()),
+            ))
+            .unwrap();
+        ledger
+            .record(Transaction::sell(
+                day(2),
+                Money::from_minor(5 * wei, token.clone()),
+                Money::from_minor(15 * wei, eth.clone()),
+            ))
+            .unwrap();
+
+        let position = position(&ledger);
+        assert_eq!(position.cost_basis().amount(), 30 * wei);
+        assert_eq!(position.realized().amount(), 5 * wei);
+        let price = Money::from_minor(21 * wei, eth.clone());
+        assert_eq!(position.market_value(&price).unwrap().amount(), 315 * wei);
+
+        let price = Money::from_minor(i128::MAX, eth.clone());
+        let error = position.market_value(&price).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("operation"), Some("market_value"));
+
+        // Holdings past the range of an amount.
+        let half = Money::from_minor(i128::MAX / 2 + 1, token);
+        let buy = Transaction::buy(day(3), half, Money::from_minor(wei, eth));
+        ledger.record(buy.clone()).unwrap();
+        let error = ledger.record(buy).unwrap_err();
+        assert_eq!(error.meta_value("operation"), Some("total"));
+    }
+
+    #[test]
+    fn test_transfers_and_fees() {
+        let mut ledger = Ledger::new(Currency::from(CurrencyCode::USD));
+        ledger
+            .record(Transaction::transfer_in(
+                day(1),
+                btc("0.1"),
+                Some(usd("3000")),
+            ))
+            .unwrap();
+        ledger
+            .record(Transaction::fee(day(2), btc("0.01")))
+            .unwrap();
+        ledger
+            .record(Transaction::transfer_out(day(3), btc("0.09")))
+            .unwrap();
+
+        let position = position(&ledger);
+        assert!(position.is_closed());
+        assert_eq!(position.realized(), &usd("-300"));
+        assert_eq!(position.disposals().len(), 1);
+        assert_eq!(position.disposals()[0].kind(), TransactionKind::Fee);
+
+        let statement = ledger.statement(&HashMap::new()).unwrap();
+        assert_eq!(statement.market_value(), &usd("0"));
+    }
+
+    #[test]
+    fn test_rejects_overselling() {
+        let mut ledger = ledger(CostMethod::Fifo);
+        let error = ledger
+            .record(Transaction::sell(day(5), btc("2"), usd("1")))
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("available"), Some("1.50000000"));
+
+        // Backdating a sale before the holdings were bought is caught as well.
+        let error = ledger
+            .record(Transaction::sell(
+                Utc.with_ymd_and_hms(2022, 12, 31, 0, 0, 0).unwrap(),
+                btc("0.00000001"),
+                usd("1"),
+            ))
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(ledger.transactions().len(), 4);
+
+        let error = ledger
+            .record(Transaction::buy(
+                day(5),
+                btc("0.00000001"),
+                btc("0.00000001"),
+            ))
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+    }
+
+    #[test]
+    fn test_json() {
+        let ledger = ledger(CostMethod::Hifo);
+        let json = ledger.to_json().unwrap();
+        assert!(json.contains(r#""method":"hifo""#));
+        assert!(json.contains(r#""kind":"buy""#));
+        assert_eq!(Ledger::from_json(&json).unwrap(), ledger);
+
+        let prices = HashMap::from([(CurrencyCode::BTC, usd("50000"))]);
+        let statement = ledger.statement(&prices).unwrap();
+        assert_eq!(
+            Statement::from_json(&statement.to_json().unwrap()).unwrap(),
+            statement
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use utils::errors::Error;
+
+use super::kind::TransactionKind;
+use crate::money::Money;
+
+/// A quantity from one lot leaving the portfolio by a sale or a fee, with the gain it realized.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Disposal {
+    kind: TransactionKind,
+    acquired: DateTime<Utc>,
+    disposed: DateTime<Utc>,
+    quantity: Money,
+    cost: Money,
+    proceeds: Money,
+}
+
+impl Disposal {
+    pub fn new(
+        kind: TransactionKind,
+        acquired: DateTime<Utc>,
+        disposed: DateTime<Utc>,
+        quantity: Money,
+        cost: Money,
+        proceeds: Money,
+    ) -> Self {
+        Self {
+            kind,
+            acquired,
+            disposed,
+            quantity,
+            cost,
+            proceeds,
+        }
+    }
+
+    /// Whether the disposal was a sale or a fee.
+    pub fn kind(&self) -> TransactionKind {
+        self.kind
+    }
+
+    /// When the lot the quantity came from was acquired.
+    pub fn acquired(&self) -> DateTime<Utc> {
+        self.acquired
+    }
+
+    pub fn disposed(&self) -> DateTime<Utc> {
+        self.disposed
+    }
+
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    /// The share of the lot's cost basis that was disposed of.
+    pub fn cost(&self) -> &Money {
+        &self.cost
+    }
+
+    /// The share of the sale's proceeds, net of fees, attributed to this lot.
+    pub fn proceeds(&self) -> &Money {
+        &self.proceeds
+    }
+
+    /// The realized gain, negative for a loss.
+    pub fn gain(&self) -> Result<Money, Error> {
+        self.proceeds.checked_sub(&self.cost)
+    }
+}
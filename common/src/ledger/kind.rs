@@ -0,0 +1,16 @@
+/// What a ledger transaction does to a holding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[macros::json(rename_all = "snake_case")]
+pub enum TransactionKind {
+    /// Acquires the asset for a total cost in the ledger's currency.
+    Buy,
+    /// Disposes of the asset for total proceeds in the ledger's currency.
+    Sell,
+    /// Receives the asset from elsewhere, optionally with a known cost basis.
+    TransferIn,
+    /// Sends the asset elsewhere; its cost basis leaves with it and nothing is realized.
+    TransferOut,
+    /// Spends the asset on a fee, such as a network fee. It is disposed of for no proceeds, so
+    /// its cost basis is realized as a loss.
+    Fee,
+}
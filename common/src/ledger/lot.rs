@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+
+use crate::money::Money;
+
+/// A quantity of an asset acquired at one time for a known cost.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Lot {
+    acquired: DateTime<Utc>,
+    quantity: Money,
+    cost: Money,
+}
+
+impl Lot {
+    pub fn new(acquired: DateTime<Utc>, quantity: Money, cost: Money) -> Self {
+        Self {
+            acquired,
+            quantity,
+            cost,
+        }
+    }
+
+    pub fn acquired(&self) -> DateTime<Utc> {
+        self.acquired
+    }
+
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    /// The total cost of the lot, including fees.
+    pub fn cost(&self) -> &Money {
+        &self.cost
+    }
+
+    pub(crate) fn quantity_mut(&mut self) -> &mut Money {
+        &mut self.quantity
+    }
+
+    pub(crate) fn cost_mut(&mut self) -> &mut Money {
+        &mut self.cost
+    }
+}
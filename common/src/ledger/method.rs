@@ -0,0 +1,14 @@
+/// Which lots a disposal is matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[macros::json(rename_all = "lowercase")]
+pub enum CostMethod {
+    /// First in, first out: the oldest lots are disposed of first.
+    #[default]
+    Fifo,
+    /// Last in, first out: the newest lots are disposed of first.
+    Lifo,
+    /// Highest in, first out: the lots with the highest unit cost are disposed of first.
+    Hifo,
+    /// Every acquisition is pooled into a single lot at the average unit cost.
+    Average,
+}
@@ -0,0 +1,258 @@
+use chrono::{DateTime, Utc};
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::disposal::Disposal;
+use super::kind::TransactionKind;
+use super::lot::Lot;
+use super::method::CostMethod;
+use super::transaction::Transaction;
+use crate::currency::Currency;
+use crate::money::{cmp_products, ensure_currency, from_wide, mul_div_round, out_of_range, Money};
+
+/// The holding of one asset built up by replaying a ledger.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Position {
+    asset: Currency,
+    quantity: Money,
+    cost_basis: Money,
+    realized: Money,
+    lots: Vec<Lot>,
+    disposals: Vec<Disposal>,
+}
+
+impl Position {
+    pub(crate) fn new(asset: Currency, currency: Currency) -> Self {
+        Self {
+            quantity: Money::zero(asset.clone()),
+            cost_basis: Money::zero(currency.clone()),
+            realized: Money::zero(currency),
+            asset,
+            lots: Vec::new(),
+            disposals: Vec::new(),
+        }
+    }
+
+    pub fn asset(&self) -> &Currency {
+        &self.asset
+    }
+
+    /// The amount of the asset held.
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    /// The total cost of the lots still held.
+    pub fn cost_basis(&self) -> &Money {
+        &self.cost_basis
+    }
+
+    /// The gain realized by every disposal so far, negative for a loss.
+    pub fn realized(&self) -> &Money {
+        &self.realized
+    }
+
+    /// The lots still held, oldest first.
+    pub fn lots(&self) -> &[Lot] {
+        &self.lots
+    }
+
+    pub fn disposals(&self) -> &[Disposal] {
+        &self.disposals
+    }
+
+    /// Whether everything that was acquired has left the portfolio.
+    pub fn is_closed(&self) -> bool {
+        self.quantity.is_zero()
+    }
+
+    /// The value of the holding at `price`, the price of one whole unit of the asset in the
+    /// ledger's currency.
+    pub fn market_value(&self, price: &Money) -> Result<Money, Error> {
+        ensure_currency(self.cost_basis.currency(), price.currency())?;
+        let scale = 10i128.pow(self.asset.decimal_places());
+        let value = mul_div_round(self.quantity.amount(), price.amount(), scale)
+            .ok_or_else(|| out_of_range("market_value", price.currency()))?;
+        from_wide(value, price.currency())
+    }
+
+    /// The gain that selling the holding at `price` would realize, before fees.
+    pub fn unrealized(&self, price: &Money) -> Result<Money, Error> {
+        self.market_value(price)?.checked_sub(&self.cost_basis)
+    }
+
+    pub(crate) fn apply(
+        &mut self,
+        transaction: &Transaction,
+        method: CostMethod,
+    ) -> Result<(), Error> {
+        let timestamp = transaction.timestamp();
+        let quantity = transaction.quantity();
+        let zero = Money::zero(self.cost_basis.currency().clone());
+        let total = transaction.total().unwrap_or(&zero);
+        let fee = transaction.fee_paid().unwrap_or(&zero);
+
+        match transaction.kind() {
+            TransactionKind::Buy => {
+                self.acquire(timestamp, quantity, total.checked_add(fee)?, method)?
+            }
+            TransactionKind::TransferIn => {
+                self.acquire(timestamp, quantity, total.clone(), method)?
+            }
+            TransactionKind::Sell => {
+                let proceeds = total.checked_sub(fee)?;
+                let slices = self.consume(timestamp, quantity, method)?;
+                self.dispose(TransactionKind::Sell, timestamp, slices, &proceeds)?;
+            }
+            TransactionKind::Fee => {
+                let slices = self.consume(timestamp, quantity, method)?;
+                self.dispose(TransactionKind::Fee, timestamp, slices, &zero)?;
+            }
+            TransactionKind::TransferOut => {
+                self.consume(timestamp, quantity, method)?;
+            }
+        }
+
+        self.quantity = self.sum(|lot| lot.quantity(), &self.asset)?;
+        self.cost_basis = self.sum(|lot| lot.cost(), self.cost_basis.currency())?;
+        Ok(())
+    }
+
+    fn acquire(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        quantity: &Money,
+        cost: Money,
+        method: CostMethod,
+    ) -> Result<(), Error> {
+        match (method, self.lots.first_mut()) {
+            (CostMethod::Average, Some(pool)) => {
+                *pool.quantity_mut() = pool.quantity().checked_add(quantity)?;
+                *pool.cost_mut() = pool.cost().checked_add(&cost)?;
+            }
+            _ => self.lots.push(Lot::new(timestamp, quantity.clone(), cost)),
+        }
+        Ok(())
+    }
+
+    /// Removes `quantity` from the lots in the order of the cost method and returns what was
+    /// removed from each lot.
+    fn consume(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        quantity: &Money,
+        method: CostMethod,
+    ) -> Result<Vec<Lot>, Error> {
+        ensure_currency(&self.asset, quantity.currency())?;
+        if quantity.amount() > self.quantity.amount() {
+            return Err(
+                Error::new("Insufficient holdings", ErrorCode::Invalid).with_meta(
+                    ErrorMeta::new()
+                        .add("asset", self.asset.code().to_string())
+                        .add("timestamp", timestamp.to_rfc3339().as_str())
+                        .add("available", self.quantity.to_decimal_string().as_str())
+                        .add("requested", quantity.to_decimal_string().as_str())
+                        .build(),
+                ),
+            );
+        }
+
+        let mut remaining = quantity.amount();
+        let mut slices = Vec::new();
+        while remaining > 0 {
+            let index = match method {
+                CostMethod::Fifo | CostMethod::Average => 0,
+                CostMethod::Lifo => self.lots.len() - 1,
+                CostMethod::Hifo => self.highest_unit_cost(),
+            };
+
+            let lot = &mut self.lots[index];
+            let available = lot.quantity().amount();
+            if available <= remaining {
+                remaining -= available;
+                slices.push(self.lots.remove(index));
+                continue;
+            }
+
+            let cost = mul_div_round(lot.cost().amount(), remaining, available)
+                .ok_or_else(|| out_of_range("cost_basis", lot.cost().currency()))?;
+            let cost = from_wide(cost, lot.cost().currency())?;
+            let taken = Money::from_minor(remaining, self.asset.clone());
+            *lot.quantity_mut() = lot.quantity().checked_sub(&taken)?;
+            *lot.cost_mut() = lot.cost().checked_sub(&cost)?;
+            slices.push(Lot::new(lot.acquired(), taken, cost));
+            remaining = 0;
+        }
+
+        Ok(slices)
+    }
+
+    /// Records a disposal per slice, sharing the proceeds by quantity.
+    fn dispose(
+        &mut self,
+        kind: TransactionKind,
+        timestamp: DateTime<Utc>,
+        slices: Vec<Lot>,
+        proceeds: &Money,
+    ) -> Result<(), Error> {
+        let total = slices.iter().try_fold(0i128, |total, slice| {
+            total
+                .checked_add(slice.quantity().amount())
+                .ok_or_else(|| out_of_range("quantity", slice.quantity().currency()))
+        })?;
+        let mut allocated = 0;
+
+        for (index, slice) in slices.iter().enumerate() {
+            let share = if index + 1 == slices.len() {
+                proceeds.amount() - allocated
+            } else {
+                mul_div_round(proceeds.amount(), slice.quantity().amount(), total)
+                    .ok_or_else(|| out_of_range("proceeds", proceeds.currency()))?
+            };
+            allocated += share;
+
+            let disposal = Disposal::new(
+                kind,
+                slice.acquired(),
+                timestamp,
+                slice.quantity().clone(),
+                slice.cost().clone(),
+                from_wide(share, proceeds.currency())?,
+            );
+            self.realized = self.realized.checked_add(&disposal.gain()?)?;
+            self.disposals.push(disposal);
+        }
+
+        Ok(())
+    }
+
+    /// The index of the lot with the highest cost per unit, the oldest one on ties.
+    fn highest_unit_cost(&self) -> usize {
+        let mut highest = 0;
+        for (index, lot) in self.lots.iter().enumerate().skip(1) {
+            let best = &self.lots[highest];
+            let higher = cmp_products(
+                lot.cost().amount(),
+                best.quantity().amount(),
+                best.cost().amount(),
+                lot.quantity().amount(),
+            );
+            if higher.is_gt() {
+                highest = index;
+            }
+        }
+        highest
+    }
+
+    fn sum<F>(&self, amount: F, currency: &Currency) -> Result<Money, Error>
+    where
+        F: Fn(&Lot) -> &Money,
+    {
+        let total = self.lots.iter().try_fold(0i128, |total, lot| {
+            total
+                .checked_add(amount(lot).amount())
+                .ok_or_else(|| out_of_range("total", currency))
+        })?;
+        from_wide(total, currency)
+    }
+}
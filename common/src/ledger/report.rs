@@ -0,0 +1,547 @@
+//! Capital gains reports.
+//!
+//! A `GainsReport` lists every disposal of a ledger as one row with the dates, proceeds, cost
+//! basis and gain needed for a tax return, classified as short or long term. It exports to CSV
+//! with the columns, dates and amount formatting set by `CsvOptions`, so it can be imported into
+//! accounting software that expects a particular layout.
+
+use std::io::Write;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Months, Utc};
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::kind::TransactionKind;
+use super::method::CostMethod;
+use super::transaction::Transaction;
+use super::Ledger;
+use crate::currency::Currency;
+use crate::money::{from_wide, out_of_range, Money};
+
+/// How long a disposed lot was held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Term {
+    /// Held for one year or less.
+    Short,
+    /// Held for more than one year.
+    Long,
+}
+
+impl Term {
+    pub fn of(acquired: DateTime<Utc>, disposed: DateTime<Utc>) -> Self {
+        match acquired.checked_add_months(Months::new(12)) {
+            Some(anniversary) if disposed > anniversary => Term::Long,
+            _ => Term::Short,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Term::Short => "short",
+            Term::Long => "long",
+        }
+    }
+}
+
+/// A column of the CSV export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Asset,
+    Quantity,
+    Acquired,
+    Disposed,
+    Proceeds,
+    CostBasis,
+    Gain,
+    Term,
+    /// Whether the disposal was a sale or a fee.
+    Kind,
+}
+
+impl Column {
+    /// The columns exported by default, in order.
+    pub const DEFAULT: [Column; 8] = [
+        Column::Asset,
+        Column::Quantity,
+        Column::Acquired,
+        Column::Disposed,
+        Column::Proceeds,
+        Column::CostBasis,
+        Column::Gain,
+        Column::Term,
+    ];
+
+    /// Parses a column from its header name, e.g. `"cost_basis"`.
+    ///
+    /// Fails with `ErrorCode::Invalid` if there is no such column.
+    pub fn parse(name: &str) -> Result<Self, Error> {
+        match name.trim() {
+            "asset" => Ok(Column::Asset),
+            "quantity" => Ok(Column::Quantity),
+            "acquired" => Ok(Column::Acquired),
+            "disposed" => Ok(Column::Disposed),
+            "proceeds" => Ok(Column::Proceeds),
+            "cost_basis" => Ok(Column::CostBasis),
+            "gain" => Ok(Column::Gain),
+            "term" => Ok(Column::Term),
+            "kind" => Ok(Column::Kind),
+            _ => Err(Error::new("Unknown report column", ErrorCode::Invalid)
+                .with_meta(ErrorMeta::new().add("column", name).build())),
+        }
+    }
+
+    /// The header name of the column.
+    pub fn name(&self) -> &str {
+        match self {
+            Column::Asset => "asset",
+            Column::Quantity => "quantity",
+            Column::Acquired => "acquired",
+            Column::Disposed => "disposed",
+            Column::Proceeds => "proceeds",
+            Column::CostBasis => "cost_basis",
+            Column::Gain => "gain",
+            Column::Term => "term",
+            Column::Kind => "kind",
+        }
+    }
+}
+
+/// How amounts are labelled with their currency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AmountStyle {
+    /// A bare number, e.g. `70000.50`.
+    #[default]
+    Plain,
+    /// Prefixed with the currency symbol, e.g. `$70000.50`.
+    Symbol,
+    /// Followed by the currency code, e.g. `70000.50 USD`.
+    Code,
+}
+
+/// Layout of a CSV export.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    columns: Vec<Column>,
+    delimiter: u8,
+    header: bool,
+    date_format: Box<str>,
+    amount_style: AmountStyle,
+    decimal_separator: char,
+    thousands_separator: Option<char>,
+}
+
+impl CsvOptions {
+    /// The default columns separated by commas, dates as `YYYY-MM-DD` and plain amounts with
+    /// a `.` decimal separator.
+    pub fn new() -> Self {
+        Self {
+            columns: Column::DEFAULT.to_vec(),
+            delimiter: b',',
+            header: true,
+            date_format: "%Y-%m-%d".into(),
+            amount_style: AmountStyle::Plain,
+            decimal_separator: '.',
+            thousands_separator: None,
+        }
+    }
+
+    pub fn columns<I>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = Column>,
+    {
+        self.columns = columns.into_iter().collect();
+        self
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Whether to write a header row. Defaults to true.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// A `chrono` format string for the acquired and disposed dates. It is checked when the
+    /// report is written.
+    pub fn date_format(mut self, date_format: &str) -> Self {
+        self.date_format = date_format.into();
+        self
+    }
+
+    pub fn amount_style(mut self, amount_style: AmountStyle) -> Self {
+        self.amount_style = amount_style;
+        self
+    }
+
+    pub fn decimal_separator(mut self, decimal_separator: char) -> Self {
+        self.decimal_separator = decimal_separator;
+        self
+    }
+
+    /// Groups the whole part of amounts in thousands, e.g. `Some(',')` for `70,000.50`.
+    pub fn thousands_separator(mut self, thousands_separator: Option<char>) -> Self {
+        self.thousands_separator = thousands_separator;
+        self
+    }
+
+    /// Fails with `ErrorCode::Invalid` if the date format has a specifier `chrono` does not know,
+    /// which would otherwise panic while formatting.
+    fn validate(&self) -> Result<(), Error> {
+        if StrftimeItems::new(&self.date_format).any(|item| item == Item::Error) {
+            return Err(
+                Error::new("Invalid date format", ErrorCode::Invalid).with_meta(
+                    ErrorMeta::new()
+                        .add("date_format", &self.date_format)
+                        .build(),
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    /// Formats an amount with the currency's decimal places and these options.
+    pub fn format_amount(&self, amount: &Money) -> String {
+        let decimal = amount.to_decimal_string();
+        let (sign, magnitude) = match decimal.strip_prefix('-') {
+            Some(magnitude) => ("-", magnitude),
+            None => ("", decimal.as_str()),
+        };
+        let (whole, fraction) = match magnitude.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (magnitude, None),
+        };
+
+        let mut number = String::new();
+        for (index, digit) in whole.chars().enumerate() {
+            let remaining = whole.len() - index;
+            if index > 0 && remaining % 3 == 0 {
+                if let Some(separator) = self.thousands_separator {
+                    number.push(separator);
+                }
+            }
+            number.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            number.push(self.decimal_separator);
+            number.push_str(fraction);
+        }
+
+        let currency = amount.currency();
+        match self.amount_style {
+            AmountStyle::Plain => format!("{}{}", sign, number),
+            AmountStyle::Symbol => format!("{}{}{}", sign, currency.symbol(), number),
+            AmountStyle::Code => format!("{}{} {}", sign, number, currency.code().to_string()),
+        }
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One disposed lot in a `GainsReport`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportRow {
+    kind: TransactionKind,
+    quantity: Money,
+    acquired: DateTime<Utc>,
+    disposed: DateTime<Utc>,
+    proceeds: Money,
+    cost_basis: Money,
+    gain: Money,
+    term: Term,
+}
+
+impl ReportRow {
+    pub fn kind(&self) -> TransactionKind {
+        self.kind
+    }
+
+    pub fn asset(&self) -> &Currency {
+        self.quantity.currency()
+    }
+
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    pub fn acquired(&self) -> DateTime<Utc> {
+        self.acquired
+    }
+
+    pub fn disposed(&self) -> DateTime<Utc> {
+        self.disposed
+    }
+
+    pub fn proceeds(&self) -> &Money {
+        &self.proceeds
+    }
+
+    pub fn cost_basis(&self) -> &Money {
+        &self.cost_basis
+    }
+
+    pub fn gain(&self) -> &Money {
+        &self.gain
+    }
+
+    pub fn term(&self) -> Term {
+        self.term
+    }
+
+    fn cell(&self, column: Column, options: &CsvOptions) -> String {
+        match column {
+            Column::Asset => self.asset().code().to_string().to_owned(),
+            Column::Quantity => options.format_amount(&self.quantity),
+            Column::Acquired => self.acquired.format(&options.date_format).to_string(),
+            Column::Disposed => self.disposed.format(&options.date_format).to_string(),
+            Column::Proceeds => options.format_amount(&self.proceeds),
+            Column::CostBasis => options.format_amount(&self.cost_basis),
+            Column::Gain => options.format_amount(&self.gain),
+            Column::Term => self.term.name().to_owned(),
+            Column::Kind => match self.kind {
+                TransactionKind::Fee => "fee".to_owned(),
+                _ => "sell".to_owned(),
+            },
+        }
+    }
+}
+
+/// Realized capital gains of a ledger, one row per disposed lot, ordered by disposal date.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GainsReport {
+    currency: Currency,
+    rows: Vec<ReportRow>,
+}
+
+impl GainsReport {
+    pub fn from_ledger(ledger: &Ledger) -> Result<Self, Error> {
+        let mut rows = Vec::new();
+        for position in ledger.positions()? {
+            for disposal in position.disposals() {
+                rows.push(ReportRow {
+                    kind: disposal.kind(),
+                    quantity: disposal.quantity().clone(),
+                    acquired: disposal.acquired(),
+                    disposed: disposal.disposed(),
+                    proceeds: disposal.proceeds().clone(),
+                    cost_basis: disposal.cost().clone(),
+                    gain: disposal.gain()?,
+                    term: Term::of(disposal.acquired(), disposal.disposed()),
+                });
+            }
+        }
+        rows.sort_by_key(|row| row.disposed);
+
+        Ok(Self {
+            currency: ledger.currency().clone(),
+            rows,
+        })
+    }
+
+    /// Builds the report from transactions kept in `currency`, matched with `method`.
+    ///
+    /// Fails with `ErrorCode::Invalid` if a transaction is malformed or oversells a holding.
+    pub fn from_transactions<I>(
+        currency: Currency,
+        method: CostMethod,
+        transactions: I,
+    ) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = Transaction>,
+    {
+        let mut ledger = Ledger::new(currency).with_method(method);
+        for transaction in transactions {
+            ledger.record(transaction)?;
+        }
+        Self::from_ledger(&ledger)
+    }
+
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    pub fn rows(&self) -> &[ReportRow] {
+        &self.rows
+    }
+
+    /// The total gain of the rows held for the given term, or of every row.
+    pub fn total_gain(&self, term: Option<Term>) -> Result<Money, Error> {
+        let total = self
+            .rows
+            .iter()
+            .filter(|row| term.is_none_or(|term| row.term == term))
+            .try_fold(0i128, |total, row| {
+                total
+                    .checked_add(row.gain.amount())
+                    .ok_or_else(|| out_of_range("total_gain", &self.currency))
+            })?;
+        from_wide(total, &self.currency)
+    }
+
+    /// Writes the report as CSV.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the options' date format is invalid, and with
+    /// `ErrorCode::Internal` if the writer fails.
+    pub fn write_csv<W: Write>(&self, writer: W, options: &CsvOptions) -> Result<(), Error> {
+        options.validate()?;
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(options.delimiter)
+            .from_writer(writer);
+
+        if options.header {
+            writer
+                .write_record(options.columns.iter().map(|column| column.name()))
+                .map_err(csv_error)?;
+        }
+        for row in &self.rows {
+            writer
+                .write_record(options.columns.iter().map(|c| row.cell(*c, options)))
+                .map_err(csv_error)?;
+        }
+
+        writer.flush().map_err(|err| {
+            Error::new("Failed to write report", ErrorCode::Internal).with_cause(err)
+        })
+    }
+
+    pub fn to_csv(&self, options: &CsvOptions) -> Result<String, Error> {
+        let mut buffer = Vec::new();
+        self.write_csv(&mut buffer, options)?;
+        String::from_utf8(buffer).map_err(|err| {
+            Error::new("Failed to write report", ErrorCode::Internal).with_cause(err)
+        })
+    }
+}
+
+fn csv_error(err: csv::Error) -> Error {
+    Error::new("Failed to write report", ErrorCode::Internal).with_cause(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::currency::code::CurrencyCode;
+    use crate::test_support::{btc, usd};
+
+    fn date(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 12, 0, 0).unwrap()
+    }
+
+    fn report() -> GainsReport {
+        GainsReport::from_transactions(
+            Currency::from(CurrencyCode::USD),
+            CostMethod::Fifo,
+            [
+                Transaction::buy(date(2022, 1, 10), btc("1"), usd("40000")),
+                Transaction::buy(date(2023, 3, 1), btc("1"), usd("25000")),
+                Transaction::sell(date(2023, 6, 1), btc("1.5"), usd("40500.75")),
+                Transaction::fee(date(2023, 6, 2), btc("0.0001")),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_terms() {
+        assert_eq!(Term::of(date(2022, 1, 10), date(2023, 1, 10)), Term::Short);
+        assert_eq!(Term::of(date(2022, 1, 10), date(2023, 1, 11)), Term::Long);
+        assert_eq!(Term::of(date(2024, 2, 29), date(2025, 3, 1)), Term::Long);
+    }
+
+    #[test]
+    fn test_rows_and_totals() {
+        let report = report();
+        let terms: Vec<Term> = report.rows().iter().map(|row| row.term()).collect();
+        assert_eq!(terms, vec![Term::Long, Term::Short, Term::Short]);
+
+        assert_eq!(report.rows()[0].gain(), &usd("-12999.50"));
+        assert_eq!(report.rows()[1].gain(), &usd("1000.25"));
+        assert_eq!(report.rows()[2].kind(), TransactionKind::Fee);
+        assert_eq!(
+            report.total_gain(Some(Term::Long)).unwrap(),
+            usd("-12999.50")
+        );
+        assert_eq!(report.total_gain(None).unwrap(), usd("-12001.75"));
+
+        // Gains that fit in each position but not in the total.
+        let proceeds = Money::from_minor(i128::MAX / 2 + 2, Currency::from(CurrencyCode::USD));
+        let eth = Money::from_minor(1, Currency::from(CurrencyCode::ETH));
+        let report = GainsReport::from_transactions(
+            Currency::from(CurrencyCode::USD),
+            CostMethod::Fifo,
+            [
+                Transaction::buy(date(2022, 1, 10), btc("0.00000001"), usd("0.01")),
+                Transaction::buy(date(2022, 1, 10), eth.clone(), usd("0.01")),
+                Transaction::sell(date(2023, 6, 1), btc("0.00000001"), proceeds.clone()),
+                Transaction::sell(date(2023, 6, 1), eth, proceeds),
+            ],
+        )
+        .unwrap();
+        let error = report.total_gain(None).unwrap_err();
+        assert_eq!(error.meta_value("operation"), Some("total_gain"));
+    }
+
+    #[test]
+    fn test_default_csv() {
+        let csv = report().to_csv(&CsvOptions::new()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "asset,quantity,acquired,disposed,proceeds,cost_basis,gain,term"
+        );
+        assert_eq!(
+            lines[1],
+            "BTC,1.00000000,2022-01-10,2023-06-01,27000.50,40000.00,-12999.50,long"
+        );
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[test]
+    fn test_configured_csv() {
+        let options = CsvOptions::new()
+            .columns([Column::Disposed, Column::Gain, Column::Kind])
+            .delimiter(b';')
+            .date_format("%d.%m.%Y")
+            .amount_style(AmountStyle::Symbol)
+            .decimal_separator(',')
+            .thousands_separator(Some('.'));
+        let csv = report().to_csv(&options).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "disposed;gain;kind");
+        assert_eq!(lines[1], "01.06.2023;-$12.999,50;sell");
+        assert_eq!(lines[3], "02.06.2023;-$2,50;fee");
+
+        let options = CsvOptions::new()
+            .columns([Column::Proceeds])
+            .header(false)
+            .amount_style(AmountStyle::Code)
+            .thousands_separator(Some(','));
+        let csv = report().to_csv(&options).unwrap();
+        assert_eq!(csv.lines().next(), Some("\"27,000.50 USD\""));
+
+        let options = CsvOptions::new().date_format("%Y-%Q");
+        let error = report().to_csv(&options).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("date_format"), Some("%Y-%Q"));
+    }
+
+    #[test]
+    fn test_parse_column() {
+        assert_eq!(Column::parse("cost_basis").unwrap(), Column::CostBasis);
+        for column in Column::DEFAULT {
+            assert_eq!(Column::parse(column.name()).unwrap(), column);
+        }
+        assert_eq!(
+            Column::parse("basis").unwrap_err().code(),
+            ErrorCode::Invalid
+        );
+    }
+}
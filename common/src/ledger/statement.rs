@@ -0,0 +1,59 @@
+use super::valuation::Valuation;
+use crate::currency::Currency;
+use crate::money::Money;
+
+/// Every position of a ledger valued at market prices, with totals in the ledger's currency.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Statement {
+    currency: Currency,
+    positions: Vec<Valuation>,
+    cost_basis: Money,
+    market_value: Money,
+    unrealized: Money,
+    realized: Money,
+}
+
+impl Statement {
+    pub(crate) fn new(
+        currency: Currency,
+        positions: Vec<Valuation>,
+        cost_basis: Money,
+        market_value: Money,
+        unrealized: Money,
+        realized: Money,
+    ) -> Self {
+        Self {
+            currency,
+            positions,
+            cost_basis,
+            market_value,
+            unrealized,
+            realized,
+        }
+    }
+
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    pub fn positions(&self) -> &[Valuation] {
+        &self.positions
+    }
+
+    pub fn cost_basis(&self) -> &Money {
+        &self.cost_basis
+    }
+
+    pub fn market_value(&self) -> &Money {
+        &self.market_value
+    }
+
+    pub fn unrealized(&self) -> &Money {
+        &self.unrealized
+    }
+
+    pub fn realized(&self) -> &Money {
+        &self.realized
+    }
+}
@@ -0,0 +1,145 @@
+use chrono::{DateTime, Utc};
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::kind::TransactionKind;
+use crate::currency::Currency;
+use crate::money::{ensure_currency, Money};
+
+/// A single entry in a `Ledger`.
+///
+/// The quantity is an amount of the asset. The total is the cost of a buy or the proceeds of a
+/// sale, before fees, and is optional for transfers in, where it is the cost basis carried over.
+/// Fees are in the ledger's currency: they add to the cost of a buy and are deducted from the
+/// proceeds of a sale.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Transaction {
+    timestamp: DateTime<Utc>,
+    kind: TransactionKind,
+    quantity: Money,
+    total: Option<Money>,
+    fee: Option<Money>,
+}
+
+impl Transaction {
+    pub fn buy(timestamp: DateTime<Utc>, quantity: Money, cost: Money) -> Self {
+        Self::new(timestamp, TransactionKind::Buy, quantity, Some(cost))
+    }
+
+    pub fn sell(timestamp: DateTime<Utc>, quantity: Money, proceeds: Money) -> Self {
+        Self::new(timestamp, TransactionKind::Sell, quantity, Some(proceeds))
+    }
+
+    /// A transfer in, with the cost basis carried over from where the asset came from. Without a
+    /// cost basis the asset is treated as acquired for nothing.
+    pub fn transfer_in(timestamp: DateTime<Utc>, quantity: Money, cost: Option<Money>) -> Self {
+        Self::new(timestamp, TransactionKind::TransferIn, quantity, cost)
+    }
+
+    pub fn transfer_out(timestamp: DateTime<Utc>, quantity: Money) -> Self {
+        Self::new(timestamp, TransactionKind::TransferOut, quantity, None)
+    }
+
+    /// A quantity of the asset spent on a fee.
+    pub fn fee(timestamp: DateTime<Utc>, quantity: Money) -> Self {
+        Self::new(timestamp, TransactionKind::Fee, quantity, None)
+    }
+
+    fn new(
+        timestamp: DateTime<Utc>,
+        kind: TransactionKind,
+        quantity: Money,
+        total: Option<Money>,
+    ) -> Self {
+        Self {
+            timestamp,
+            kind,
+            quantity,
+            total,
+            fee: None,
+        }
+    }
+
+    /// Adds a fee paid in the ledger's currency to a buy or a sale.
+    pub fn with_fee(mut self, fee: Money) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    pub fn kind(&self) -> TransactionKind {
+        self.kind
+    }
+
+    /// The amount of the asset the transaction moves.
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    /// The asset the transaction moves.
+    pub fn asset(&self) -> &Currency {
+        self.quantity.currency()
+    }
+
+    /// The cost of a buy, the proceeds of a sale or the cost basis of a transfer in.
+    pub fn total(&self) -> Option<&Money> {
+        self.total.as_ref()
+    }
+
+    pub fn fee_paid(&self) -> Option<&Money> {
+        self.fee.as_ref()
+    }
+
+    /// Checks that the transaction makes sense in a ledger kept in `currency`.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the quantity is not positive or is in the ledger's
+    /// currency, a required total is missing, a total is given where none applies, or a total or
+    /// fee is negative or in another currency.
+    pub(crate) fn validate(&self, currency: &Currency) -> Result<(), Error> {
+        let invalid = |reason: &str| {
+            Error::new(
+                format!("Invalid transaction: {}", reason).as_str(),
+                ErrorCode::Invalid,
+            )
+            .with_meta(
+                ErrorMeta::new()
+                    .add("timestamp", self.timestamp.to_rfc3339().as_str())
+                    .add("asset", self.asset().code().to_string())
+                    .build(),
+            )
+        };
+
+        if self.quantity.amount() <= 0 {
+            return Err(invalid("quantity must be positive"));
+        }
+        if self.asset().code() == currency.code() {
+            return Err(invalid("asset must differ from the ledger currency"));
+        }
+
+        match (self.kind, &self.total) {
+            (TransactionKind::Buy | TransactionKind::Sell, None) => {
+                return Err(invalid("total is required"))
+            }
+            (TransactionKind::TransferOut | TransactionKind::Fee, Some(_)) => {
+                return Err(invalid("total is not allowed"))
+            }
+            _ => {}
+        }
+        if self.fee.is_some() && !matches!(self.kind, TransactionKind::Buy | TransactionKind::Sell)
+        {
+            return Err(invalid("fee is only allowed on buys and sells"));
+        }
+
+        for amount in self.total.iter().chain(self.fee.iter()) {
+            ensure_currency(currency, amount.currency())?;
+            if amount.is_negative() {
+                return Err(invalid("amounts must not be negative"));
+            }
+        }
+
+        Ok(())
+    }
+}
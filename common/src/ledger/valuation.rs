@@ -0,0 +1,66 @@
+use crate::currency::Currency;
+use crate::money::Money;
+
+/// A position valued at a market price.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Valuation {
+    asset: Currency,
+    quantity: Money,
+    price: Option<Money>,
+    cost_basis: Money,
+    market_value: Money,
+    unrealized: Money,
+    realized: Money,
+}
+
+impl Valuation {
+    pub(crate) fn new(
+        asset: Currency,
+        quantity: Money,
+        price: Option<Money>,
+        cost_basis: Money,
+        market_value: Money,
+        unrealized: Money,
+        realized: Money,
+    ) -> Self {
+        Self {
+            asset,
+            quantity,
+            price,
+            cost_basis,
+            market_value,
+            unrealized,
+            realized,
+        }
+    }
+
+    pub fn asset(&self) -> &Currency {
+        &self.asset
+    }
+
+    pub fn quantity(&self) -> &Money {
+        &self.quantity
+    }
+
+    /// The price the position was valued at, absent for closed positions.
+    pub fn price(&self) -> Option<&Money> {
+        self.price.as_ref()
+    }
+
+    pub fn cost_basis(&self) -> &Money {
+        &self.cost_basis
+    }
+
+    pub fn market_value(&self) -> &Money {
+        &self.market_value
+    }
+
+    pub fn unrealized(&self) -> &Money {
+        &self.unrealized
+    }
+
+    pub fn realized(&self) -> &Money {
+        &self.realized
+    }
+}
@@ -1,2 +1,38 @@
+#![allow(clippy::new_ret_no_self)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod alerts;
+#[cfg(feature = "std")]
+pub mod arbitrage;
+#[cfg(feature = "std")]
+pub mod backtest;
 pub mod bitcoin;
+#[cfg(feature = "std")]
+pub mod conversion;
 pub mod currency;
+#[cfg(feature = "std")]
+pub mod dca;
+#[cfg(feature = "std")]
+pub mod exchange;
+#[cfg(feature = "std")]
+pub mod import;
+#[cfg(feature = "std")]
+pub mod indicators;
+#[cfg(feature = "std")]
+pub mod inflation;
+#[cfg(feature = "std")]
+pub mod ledger;
+pub mod money;
+#[cfg(feature = "std")]
+pub mod oracle;
+#[cfg(feature = "std")]
+pub mod price;
+#[cfg(feature = "std")]
+pub mod providers;
+#[cfg(feature = "std")]
+pub mod rates;
+#[cfg(all(test, feature = "std"))]
+pub(crate) mod test_support;
@@ -0,0 +1,575 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::cmp::Ordering;
+use core::fmt::{Display, Formatter};
+
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::currency::{Currency, Locale};
+
+/// An exact amount of a `Currency`, stored as a 128-bit integer number of minor units (cents
+/// for USD, satoshis for BTC), which leaves room for assets with 18 or more decimals.
+///
+/// In JSON the amount is a decimal string such as `"70000.12"`, so no precision is lost to
+/// floating point numbers. Amounts written as an integer number of minor units are still read.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json(into = "MoneyRepr", try_from = "MoneyRepr")]
+pub struct Money {
+    amount: i128,
+    currency: Currency,
+}
+
+/// How `Money` appears in JSON.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MoneyRepr {
+    amount: AmountRepr,
+    currency: Currency,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum AmountRepr {
+    Decimal(String),
+    Minor(i64),
+}
+
+impl From<Money> for MoneyRepr {
+    fn from(money: Money) -> Self {
+        Self {
+            amount: AmountRepr::Decimal(money.to_decimal_string()),
+            currency: money.currency,
+        }
+    }
+}
+
+impl TryFrom<MoneyRepr> for Money {
+    type Error = Error;
+
+    fn try_from(repr: MoneyRepr) -> Result<Self, Error> {
+        match repr.amount {
+            AmountRepr::Decimal(decimal) => Money::parse(&decimal, repr.currency),
+            AmountRepr::Minor(amount) => Ok(Money::from_minor(amount, repr.currency)),
+        }
+    }
+}
+
+impl Money {
+    /// Creates an amount from a number of minor units of the currency.
+    pub fn from_minor(amount: impl Into<i128>, currency: Currency) -> Self {
+        Self {
+            amount: amount.into(),
+            currency,
+        }
+    }
+
+    /// Creates a zero amount of the currency.
+    pub fn zero(currency: Currency) -> Self {
+        Self::from_minor(0, currency)
+    }
+
+    /// Parses a decimal string such as `"70000.12"` or `"-0.5"` into an amount of the currency.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the string is not a decimal number, has more
+    /// fractional digits than the currency supports, or does not fit in the amount range.
+    pub fn parse(value: &str, currency: Currency) -> Result<Self, Error> {
+        Self::parse_decimal(value, currency, false)
+    }
+
+    /// Parses a decimal string like `parse`, rounding half to even any fractional digits beyond
+    /// what the currency supports instead of rejecting them.
+    pub fn parse_rounded(value: &str, currency: Currency) -> Result<Self, Error> {
+        Self::parse_decimal(value, currency, true)
+    }
+
+    fn parse_decimal(value: &str, currency: Currency, round: bool) -> Result<Self, Error> {
+        match parse_minor(value, currency.decimal_places(), round) {
+            Ok(amount) => Ok(Self::from_minor(amount, currency)),
+            Err(reason) => Err(Error::new(
+                format!("Invalid amount: {}", reason).as_str(),
+                ErrorCode::Invalid,
+            )
+            .with_meta(
+                ErrorMeta::new()
+                    .add("value", value)
+                    .add("currency", currency.code().to_string())
+                    .build(),
+            )),
+        }
+    }
+
+    /// The amount in minor units of the currency.
+    pub fn amount(&self) -> i128 {
+        self.amount
+    }
+
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.amount < 0
+    }
+
+    /// Adds two amounts of the same currency.
+    pub fn checked_add(&self, other: &Money) -> Result<Money, Error> {
+        self.ensure_same_currency(other)?;
+        self.amount
+            .checked_add(other.amount)
+            .map(|amount| Money::from_minor(amount, self.currency.clone()))
+            .ok_or_else(|| overflow(self))
+    }
+
+    /// Subtracts an amount of the same currency.
+    pub fn checked_sub(&self, other: &Money) -> Result<Money, Error> {
+        self.ensure_same_currency(other)?;
+        self.amount
+            .checked_sub(other.amount)
+            .map(|amount| Money::from_minor(amount, self.currency.clone()))
+            .ok_or_else(|| overflow(self))
+    }
+
+    /// Negates the amount. Fails with `ErrorCode::Invalid` for the one amount whose negation is
+    /// out of range.
+    pub fn negate(&self) -> Result<Money, Error> {
+        self.amount
+            .checked_neg()
+            .map(|amount| Money::from_minor(amount, self.currency.clone()))
+            .ok_or_else(|| overflow(self))
+    }
+
+    /// Returns an error with `ErrorCode::Invalid` if `other` is in a different currency.
+    pub fn ensure_same_currency(&self, other: &Money) -> Result<(), Error> {
+        ensure_currency(&self.currency, &other.currency)
+    }
+
+    /// Formats the amount as a plain decimal string without a symbol, e.g. `"70000.12"`.
+    pub fn to_decimal_string(&self) -> String {
+        format_minor(self.amount, self.currency.decimal_places())
+    }
+
+    /// The amount followed by the currency's name in a locale, without trailing zeros and in
+    /// the plural form the number calls for, e.g. "3 euros" or "1,5 Euro".
+    pub fn to_localized_string(&self, locale: Locale) -> String {
+        let decimal = self.to_decimal_string();
+        let number = match decimal.contains('.') {
+            true => decimal.trim_end_matches('0').trim_end_matches('.'),
+            false => decimal.as_str(),
+        };
+        let name = self
+            .currency
+            .name()
+            .localized(locale, locale.plural_category(number));
+        format!(
+            "{} {}",
+            number.replace('.', &locale.decimal_separator().to_string()),
+            name
+        )
+    }
+}
+
+/// Parses a decimal string into minor units with `decimals` fractional digits, rounding half to
+/// even any further digits if `round` is set. Fails with the reason the string was rejected.
+fn parse_minor(value: &str, decimals: u32, round: bool) -> Result<i128, &'static str> {
+    let decimals = decimals as usize;
+    let trimmed = value.trim();
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (digits, ""),
+    };
+
+    if whole.is_empty() && fraction.is_empty() {
+        return Err("no digits");
+    }
+    if !whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err("not a decimal number");
+    }
+    if fraction.len() > decimals && !round {
+        return Err("too many decimal places");
+    }
+
+    let (kept, dropped) = fraction.split_at(fraction.len().min(decimals));
+    let padded = format!("{}{:0<width$}", whole, kept, width = decimals);
+    let mut amount = padded.parse::<i128>().map_err(|_| "out of range")?;
+
+    let round_up = match dropped.as_bytes().split_first() {
+        Some((b'5', rest)) if rest.iter().all(|digit| *digit == b'0') => amount % 2 == 1,
+        Some((first, _)) => *first >= b'5',
+        None => false,
+    };
+    if round_up {
+        amount = amount.checked_add(1).ok_or("out of range")?;
+    }
+
+    Ok(if negative { -amount } else { amount })
+}
+
+/// Formats minor units with `decimals` fractional digits.
+pub(crate) fn format_minor(amount: i128, decimals: u32) -> String {
+    let sign = if amount < 0 { "-" } else { "" };
+    let magnitude = amount.unsigned_abs();
+
+    if decimals == 0 {
+        return format!("{}{}", sign, magnitude);
+    }
+
+    let scale = 10u128.pow(decimals);
+    format!(
+        "{}{}.{:0width$}",
+        sign,
+        magnitude / scale,
+        magnitude % scale,
+        width = decimals as usize
+    )
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        let decimal = self.to_decimal_string();
+        match decimal.strip_prefix('-') {
+            Some(magnitude) => write!(f, "-{}{}", self.currency.symbol(), magnitude),
+            None => write!(f, "{}{}", self.currency.symbol(), decimal),
+        }
+    }
+}
+
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.currency.code() != other.currency.code() {
+            return None;
+        }
+        self.amount.partial_cmp(&other.amount)
+    }
+}
+
+/// Returns an error with `ErrorCode::Invalid` if the two currencies differ.
+pub(crate) fn ensure_currency(expected: &Currency, actual: &Currency) -> Result<(), Error> {
+    if expected.code() == actual.code() {
+        return Ok(());
+    }
+
+    Err(
+        Error::new("Currency mismatch", ErrorCode::Invalid).with_meta(
+            ErrorMeta::new()
+                .add("expected", expected.code().to_string())
+                .add("actual", actual.code().to_string())
+                .build(),
+        ),
+    )
+}
+
+/// Converts a minor unit amount computed with wide arithmetic back into `Money`.
+///
+/// Fails with `ErrorCode::Invalid` if the amount is outside the range `Money` can hold.
+#[cfg(feature = "std")]
+pub(crate) fn from_wide(amount: i128, currency: &Currency) -> Result<Money, Error> {
+    if amount == i128::MIN {
+        return Err(
+            Error::new("Amount out of range", ErrorCode::Invalid).with_meta(
+                ErrorMeta::new()
+                    .add("currency", currency.code().to_string())
+                    .build(),
+            ),
+        );
+    }
+    Ok(Money::from_minor(amount, currency.clone()))
+}
+
+/// Divides and rounds half to even, so repeated rounding does not drift in one direction.
+#[cfg(feature = "std")]
+pub(crate) fn div_round(numerator: i128, denominator: i128) -> i128 {
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+    if remainder == 0 {
+        return quotient;
+    }
+
+    let away = if (numerator < 0) != (denominator < 0) {
+        -1
+    } else {
+        1
+    };
+    match (remainder.abs() * 2).cmp(&denominator.abs()) {
+        Ordering::Less => quotient,
+        Ordering::Greater => quotient + away,
+        Ordering::Equal if quotient % 2 == 0 => quotient,
+        Ordering::Equal => quotient + away,
+    }
+}
+
+/// Computes `a * b / denominator`, rounded half to even like `div_round`.
+///
+/// The product is kept in 256 bits, so two amounts with 18 decimals can be multiplied before
+/// scaling back down. Returns `None` if the result does not fit in an `i128`.
+#[cfg(feature = "std")]
+pub(crate) fn mul_div_round(a: i128, b: i128, denominator: i128) -> Option<i128> {
+    let negative = ((a < 0) != (b < 0)) != (denominator < 0);
+    let denominator = denominator.unsigned_abs();
+    let (high, low) = widening_mul(a.unsigned_abs(), b.unsigned_abs());
+    if high >= denominator {
+        return None;
+    }
+
+    // Long division of the 256-bit product, one bit at a time. The remainder stays below the
+    // denominator, so only the bit shifted out of it needs carrying.
+    let mut quotient = 0u128;
+    let mut remainder = high;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1 << bit;
+        }
+    }
+
+    let quotient = match remainder.cmp(&(denominator - remainder)) {
+        Ordering::Less => quotient,
+        Ordering::Equal if quotient.is_multiple_of(2) => quotient,
+        _ => quotient.checked_add(1)?,
+    };
+    let quotient = i128::try_from(quotient).ok()?;
+    Some(if negative { -quotient } else { quotient })
+}
+
+/// The fraction of an amount a rate stands for, in minor units rounded half to even.
+///
+/// The rate is fixed to twelve decimal places first, so fees come out the same on every
+/// platform. Returns `None` if the result does not fit in an `i128`.
+#[cfg(feature = "std")]
+pub(crate) fn apply_rate(amount: i128, rate: f64) -> Option<i128> {
+    const RATE_SCALE: f64 = 1e12;
+    mul_div_round(
+        amount,
+        (rate * RATE_SCALE).round() as i128,
+        RATE_SCALE as i128,
+    )
+}
+
+/// Compares `a * b` with `c * d` without overflowing.
+#[cfg(feature = "std")]
+pub(crate) fn cmp_products(a: i128, b: i128, c: i128, d: i128) -> Ordering {
+    let sign = a.signum() * b.signum();
+    let other_sign = c.signum() * d.signum();
+    if sign != other_sign {
+        return sign.cmp(&other_sign);
+    }
+
+    let left = widening_mul(a.unsigned_abs(), b.unsigned_abs());
+    let right = widening_mul(c.unsigned_abs(), d.unsigned_abs());
+    if sign < 0 {
+        right.cmp(&left)
+    } else {
+        left.cmp(&right)
+    }
+}
+
+/// The full product of two `u128`s as its high and low halves.
+#[cfg(feature = "std")]
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & MASK);
+    let (b_high, b_low) = (b >> 64, b & MASK);
+
+    let low = a_low * b_low;
+    let middle = a_high * b_low + (low >> 64);
+    let middle_low = (middle & MASK) + a_low * b_high;
+    let high = a_high * b_high + (middle >> 64) + (middle_low >> 64);
+    (high, (middle_low << 64) | (low & MASK))
+}
+
+/// The error for arithmetic on amounts whose result does not fit in 128 bits.
+///
+/// Fails with `ErrorCode::Invalid`, naming the operation and the currency of the result.
+#[cfg(feature = "std")]
+pub(crate) fn out_of_range(operation: &str, currency: &Currency) -> Error {
+    Error::new("Amount out of range", ErrorCode::Invalid).with_meta(
+        ErrorMeta::new()
+            .add("operation", operation)
+            .add("currency", currency.code().to_string())
+            .build(),
+    )
+}
+
+fn overflow(money: &Money) -> Error {
+    Error::new("Amount out of range", ErrorCode::Invalid).with_meta(
+        ErrorMeta::new()
+            .add("currency", money.currency.code().to_string())
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use utils::json::JSON;
+
+    use super::*;
+    use crate::currency::code::CurrencyCode;
+    use crate::test_support::currency;
+
+    #[test]
+    fn test_parse_and_format() {
+        let money = Money::parse("70000.5", currency(CurrencyCode::USD)).unwrap();
+        assert_eq!(money.amount(), 7_000_050);
+        assert_eq!(money.to_decimal_string(), "70000.50");
+        assert_eq!(money.to_string(), "$70000.50");
+
+        let money = Money::parse("-0.00000001", currency(CurrencyCode::BTC)).unwrap();
+        assert_eq!(money.amount(), -1);
+        assert_eq!(money.to_string(), "-₿0.00000001");
+
+        assert!(Money::parse("1.005", currency(CurrencyCode::USD)).is_err());
+        assert_eq!(
+            Money::parse_rounded("1.005", currency(CurrencyCode::USD))
+                .unwrap()
+                .amount(),
+            100
+        );
+        assert_eq!(
+            Money::parse_rounded("1.015", currency(CurrencyCode::USD))
+                .unwrap()
+                .amount(),
+            102
+        );
+        assert_eq!(
+            Money::parse_rounded("1.0051", currency(CurrencyCode::USD))
+                .unwrap()
+                .amount(),
+            101
+        );
+        assert_eq!(
+            Money::parse_rounded("-70000.00000", currency(CurrencyCode::USD))
+                .unwrap()
+                .amount(),
+            -7_000_000
+        );
+        assert!(Money::parse("1e5", currency(CurrencyCode::USD)).is_err());
+        assert!(Money::parse("", currency(CurrencyCode::USD)).is_err());
+    }
+
+    #[test]
+    fn test_wide_amounts() {
+        let wei = "1.000000000000000001";
+        assert_eq!(parse_minor(wei, 18, false), Ok(1_000_000_000_000_000_001));
+        assert_eq!(format_minor(1_000_000_000_000_000_001, 18), wei);
+        assert_eq!(parse_minor("0.0000000000000000015", 18, true), Ok(2));
+        assert_eq!(
+            parse_minor("0.0000000000000000015", 18, false),
+            Err("too many decimal places")
+        );
+
+        // Beyond what 64 bits can hold in satoshis.
+        let money = Money::parse("500000000000000.00000001", currency(CurrencyCode::BTC)).unwrap();
+        assert_eq!(money.amount(), 50_000_000_000_000_000_000_001);
+        assert_eq!(money.to_decimal_string(), "500000000000000.00000001");
+        assert!(money
+            .checked_add(&Money::from_minor(i128::MAX, currency(CurrencyCode::BTC)))
+            .is_err());
+    }
+
+    #[test]
+    fn test_json_amounts() {
+        let money = Money::parse("70000.5", currency(CurrencyCode::USD)).unwrap();
+        let json = money.to_json().unwrap();
+        assert!(json.contains(r#""amount":"70000.50""#));
+        assert_eq!(Money::from_json(&json).unwrap(), money);
+
+        let legacy = json.replace(r#""70000.50""#, "7000050");
+        assert_eq!(Money::from_json(&legacy).unwrap(), money);
+
+        let invalid = json.replace("70000.50", "70000.505");
+        assert!(Money::from_json(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Money::from_minor(150, currency(CurrencyCode::USD));
+        let b = Money::from_minor(50, currency(CurrencyCode::USD));
+        assert_eq!(a.checked_add(&b).unwrap().amount(), 200);
+        assert_eq!(a.checked_sub(&b).unwrap().amount(), 100);
+        assert_eq!(a.negate().unwrap().amount(), -150);
+        assert!(a > b);
+        let min = Money::from_minor(i128::MIN, currency(CurrencyCode::USD));
+        assert_eq!(min.negate().unwrap_err().code(), ErrorCode::Invalid);
+
+        let error = a
+            .checked_add(&Money::from_minor(1, currency(CurrencyCode::BTC)))
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+        assert_eq!(error.meta_value("actual"), Some("BTC"));
+        assert_eq!(
+            a.partial_cmp(&Money::from_minor(1, currency(CurrencyCode::BTC))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_div_round() {
+        assert_eq!(div_round(5, 2), 2);
+        assert_eq!(div_round(7, 2), 4);
+        assert_eq!(div_round(-5, 2), -2);
+        assert_eq!(div_round(-7, 2), -4);
+        assert_eq!(div_round(10, 3), 3);
+        assert_eq!(div_round(11, 3), 4);
+    }
+
+    #[test]
+    fn test_mul_div_round() {
+        for (a, b, d) in [
+            (5, 1, 2),
+            (7, 1, 2),
+            (-5, 1, 2),
+            (7, -3, 2),
+            (10, 1, -3),
+            (11, 7, 3),
+        ] {
+            assert_eq!(mul_div_round(a, b, d), Some(div_round(a * b, d)));
+        }
+
+        // 20 ETH at 21 ETH each, in wei: the product needs more than 128 bits.
+        let wei = 10i128.pow(18);
+        assert_eq!(mul_div_round(20 * wei, 21 * wei, wei), Some(420 * wei));
+        assert_eq!(mul_div_round(-20 * wei, 21 * wei, wei), Some(-420 * wei));
+        assert_eq!(mul_div_round(i128::MAX, 2, 1), None);
+        assert_eq!(
+            mul_div_round(i128::MAX, i128::MAX, i128::MAX),
+            Some(i128::MAX)
+        );
+    }
+
+    #[test]
+    fn test_apply_rate() {
+        assert_eq!(apply_rate(10_000, 0.001), Some(10));
+        assert_eq!(apply_rate(2_500, 0.001), Some(2));
+        assert_eq!(apply_rate(3_500, 0.001), Some(4));
+        assert_eq!(apply_rate(i128::MAX, 0.5), Some(i128::MAX / 2 + 1));
+        assert_eq!(apply_rate(i128::MAX, 2.0), None);
+    }
+
+    #[test]
+    fn test_cmp_products() {
+        let wei = 10i128.pow(18);
+        assert_eq!(
+            cmp_products(20 * wei, 21 * wei, 21 * wei, 20 * wei),
+            Ordering::Equal
+        );
+        assert_eq!(
+            cmp_products(20 * wei, 21 * wei + 1, 21 * wei, 20 * wei),
+            Ordering::Greater
+        );
+        assert_eq!(cmp_products(-2, 3, 1, 1), Ordering::Less);
+        assert_eq!(cmp_products(-2, 3, -1, 7), Ordering::Greater);
+    }
+}
@@ -0,0 +1,557 @@
+//! Multi-source price oracle.
+//!
+//! A `PriceOracle` asks every configured `PriceProvider` for a quote at the same time through a shared
+//! `HttpClientPool`, throws away quotes that are stale or disagree with the rest, and reports the
+//! median of what is left together with the quotes that produced it and a confidence score.
+//! A source that fails is recorded in the result's failure metadata instead of failing the query.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::task::JoinSet;
+use utils::adapters::http_client::HttpClientPool;
+use utils::errors::{Error, ErrorCode};
+use utils::send_request;
+
+use super::currency::CurrencyPair;
+use super::money::{from_wide, Money};
+use super::price::Quote;
+use super::providers::PriceProvider;
+
+const DEFAULT_MAX_AGE_SECONDS: i64 = 60;
+const DEFAULT_TIMEOUT_SECONDS: u64 = 10;
+const DEFAULT_MAD_THRESHOLD: f64 = 3.5;
+const DEFAULT_DISPERSION_TOLERANCE: f64 = 0.05;
+
+/// Scales a median absolute deviation to be comparable with a standard deviation.
+const MAD_SCALE: f64 = 0.6745;
+
+/// How quotes that disagree with the others are detected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlierFilter {
+    /// Keep every fresh quote.
+    None,
+    /// Drop quotes whose modified z-score, based on the median absolute deviation, exceeds the
+    /// threshold. When most quotes agree exactly the deviation is zero and any quote that differs
+    /// from the median is dropped.
+    Mad(f64),
+    /// Drop quotes deviating from the median by more than the given fraction, e.g. `0.02` for 2%.
+    PercentDeviation(f64),
+}
+
+/// Why a quote that was received did not contribute to the price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// The quote is for a different currency pair.
+    WrongPair,
+    /// The quote is older than the oracle's maximum age.
+    Stale,
+    /// The quote disagrees with the other quotes.
+    Outlier,
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Rejection::WrongPair => write!(f, "wrong_pair"),
+            Rejection::Stale => write!(f, "stale"),
+            Rejection::Outlier => write!(f, "outlier"),
+        }
+    }
+}
+
+/// The aggregated answer of a `PriceOracle` query.
+#[derive(Debug, Clone)]
+pub struct OraclePrice {
+    pair: CurrencyPair,
+    price: Money,
+    sources: Vec<Quote>,
+    rejected: Vec<(Box<str>, Rejection)>,
+    failures: HashMap<Box<str>, Box<str>>,
+    confidence: f64,
+    timestamp: DateTime<Utc>,
+}
+
+impl OraclePrice {
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    /// The median of the contributing quotes.
+    pub fn price(&self) -> &Money {
+        &self.price
+    }
+
+    /// The quotes the price was computed from.
+    pub fn sources(&self) -> &[Quote] {
+        &self.sources
+    }
+
+    /// Sources that answered but whose quote was discarded.
+    pub fn rejected(&self) -> &[(Box<str>, Rejection)] {
+        &self.rejected
+    }
+
+    /// Error messages of the sources that could not be queried, keyed by source name.
+    pub fn failures(&self) -> &HashMap<Box<str>, Box<str>> {
+        &self.failures
+    }
+
+    /// A score between 0 and 1: the share of configured sources that contributed, reduced as
+    /// the contributing quotes spread apart.
+    pub fn confidence(&self) -> f64 {
+        self.confidence
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+}
+
+pub struct PriceOracle {
+    pair: CurrencyPair,
+    pool: Arc<Mutex<HttpClientPool>>,
+    providers: Vec<Arc<dyn PriceProvider>>,
+    max_age: chrono::Duration,
+    timeout: Duration,
+    outlier_filter: OutlierFilter,
+    min_sources: usize,
+    dispersion_tolerance: f64,
+}
+
+impl PriceOracle {
+    pub fn new(pair: CurrencyPair, pool: Arc<Mutex<HttpClientPool>>) -> PriceOracleBuilder {
+        PriceOracleBuilder::new(pair, pool)
+    }
+
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    pub fn providers(&self) -> &[Arc<dyn PriceProvider>] {
+        &self.providers
+    }
+
+    /// Queries every provider concurrently and aggregates their quotes.
+    ///
+    /// Fails with `ErrorCode::Unavailable` if fewer than the minimum number of sources produce a
+    /// usable quote; the error metadata then names every failed or rejected source.
+    pub async fn query(&self) -> Result<OraclePrice, Error> {
+        let mut tasks = JoinSet::new();
+        for provider in &self.providers {
+            let pool = self.pool.clone();
+            let provider = provider.clone();
+            let timeout = self.timeout;
+            tasks.spawn(async move {
+                let quote =
+                    match tokio::time::timeout(timeout, fetch(pool, provider.as_ref())).await {
+                        Ok(quote) => quote,
+                        Err(_) => Err(Error::new("Provider timed out", ErrorCode::Timeout)),
+                    };
+                (Box::from(provider.name()), quote)
+            });
+        }
+
+        let mut outcomes = Vec::with_capacity(self.providers.len());
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(outcome) => outcomes.push(outcome),
+                Err(err) => {
+                    return Err(
+                        Error::new("Oracle task failed", ErrorCode::Internal).with_cause(err)
+                    )
+                }
+            }
+        }
+
+        self.aggregate(outcomes, Utc::now())
+    }
+
+    fn aggregate(
+        &self,
+        outcomes: Vec<(Box<str>, Result<Quote, Error>)>,
+        now: DateTime<Utc>,
+    ) -> Result<OraclePrice, Error> {
+        let mut failures = HashMap::new();
+        let mut rejected = Vec::new();
+        let mut fresh = Vec::new();
+
+        for (name, outcome) in outcomes {
+            match outcome {
+                Err(err) => {
+                    failures.insert(name, err.message().into());
+                }
+                Ok(quote)
+                    if quote.pair() != &self.pair
+                        || quote.price().currency() != self.pair.quote() =>
+                {
+                    rejected.push((name, Rejection::WrongPair));
+                }
+                Ok(quote) if now - quote.timestamp() > self.max_age => {
+                    rejected.push((name, Rejection::Stale));
+                }
+                Ok(quote) => fresh.push((name, quote)),
+            }
+        }
+
+        let (sources, outliers) = self.filter_outliers(fresh);
+        rejected.extend(outliers.into_iter().map(|name| (name, Rejection::Outlier)));
+
+        if sources.is_empty() || sources.len() < self.min_sources {
+            let mut meta = failures;
+            for (name, rejection) in &rejected {
+                meta.insert(name.clone(), format!("rejected: {}", rejection).into());
+            }
+            return Err(
+                Error::new("Not enough usable quotes", ErrorCode::Unavailable).with_meta(meta),
+            );
+        }
+
+        let amounts: Vec<i128> = sources.iter().map(|q| q.price().amount()).collect();
+        let median = median(&amounts);
+        let price = from_wide(median, self.pair.quote())?;
+
+        let coverage = sources.len() as f64 / self.providers.len().max(sources.len()) as f64;
+        let spread = match (amounts.iter().min(), amounts.iter().max()) {
+            (Some(min), Some(max)) if median != 0 => (max - min) as f64 / median.abs() as f64,
+            _ => 0.0,
+        };
+        let agreement = (1.0 - spread / self.dispersion_tolerance).clamp(0.0, 1.0);
+
+        Ok(OraclePrice {
+            pair: self.pair.clone(),
+            price,
+            sources,
+            rejected,
+            failures,
+            confidence: coverage * agreement,
+            timestamp: now,
+        })
+    }
+
+    fn filter_outliers(&self, fresh: Vec<(Box<str>, Quote)>) -> (Vec<Quote>, Vec<Box<str>>) {
+        let amounts: Vec<i128> = fresh.iter().map(|(_, q)| q.price().amount()).collect();
+        if amounts.is_empty() {
+            return (Vec::new(), Vec::new());
+        }
+
+        let center = median(&amounts);
+        let deviations: Vec<i128> = amounts
+            .iter()
+            .map(|amount| (amount - center).abs())
+            .collect();
+        let mad = median(&deviations);
+
+        let mut accepted = Vec::new();
+        let mut outliers = Vec::new();
+        for ((name, quote), deviation) in fresh.into_iter().zip(deviations) {
+            let keep = match self.outlier_filter {
+                OutlierFilter::None => true,
+                OutlierFilter::Mad(threshold) if mad == 0 => {
+                    deviation == 0 || threshold.is_infinite()
+                }
+                OutlierFilter::Mad(threshold) => {
+                    MAD_SCALE * deviation as f64 / mad as f64 <= threshold
+                }
+                OutlierFilter::PercentDeviation(max) if center == 0 => {
+                    deviation == 0 || max.is_infinite()
+                }
+                OutlierFilter::PercentDeviation(max) => {
+                    deviation as f64 / center.abs() as f64 <= max
+                }
+            };
+
+            if keep {
+                accepted.push(quote);
+            } else {
+                outliers.push(name);
+            }
+        }
+
+        (accepted, outliers)
+    }
+}
+
+pub struct PriceOracleBuilder {
+    pair: CurrencyPair,
+    pool: Arc<Mutex<HttpClientPool>>,
+    providers: Vec<Arc<dyn PriceProvider>>,
+    max_age: chrono::Duration,
+    timeout: Duration,
+    outlier_filter: OutlierFilter,
+    min_sources: usize,
+    dispersion_tolerance: f64,
+}
+
+impl PriceOracleBuilder {
+    pub fn new(pair: CurrencyPair, pool: Arc<Mutex<HttpClientPool>>) -> Self {
+        Self {
+            pair,
+            pool,
+            providers: Vec::new(),
+            max_age: chrono::Duration::seconds(DEFAULT_MAX_AGE_SECONDS),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECONDS),
+            outlier_filter: OutlierFilter::Mad(DEFAULT_MAD_THRESHOLD),
+            min_sources: 1,
+            dispersion_tolerance: DEFAULT_DISPERSION_TOLERANCE,
+        }
+    }
+
+    pub fn provider(mut self, provider: Arc<dyn PriceProvider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    pub fn providers<I>(mut self, providers: I) -> Self
+    where
+        I: IntoIterator<Item = Arc<dyn PriceProvider>>,
+    {
+        self.providers.extend(providers);
+        self
+    }
+
+    /// Quotes older than this are discarded as stale. Defaults to one minute.
+    pub fn max_age(mut self, max_age: chrono::Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// How long to wait for each source. Defaults to ten seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Defaults to `OutlierFilter::Mad(3.5)`.
+    pub fn outlier_filter(mut self, outlier_filter: OutlierFilter) -> Self {
+        self.outlier_filter = outlier_filter;
+        self
+    }
+
+    /// The fewest contributing quotes for a query to succeed. Defaults to one.
+    pub fn min_sources(mut self, min_sources: usize) -> Self {
+        self.min_sources = min_sources;
+        self
+    }
+
+    /// The relative spread between the highest and lowest contributing quote at which the
+    /// confidence score drops to zero. Defaults to `0.05` (5%).
+    pub fn dispersion_tolerance(mut self, dispersion_tolerance: f64) -> Self {
+        self.dispersion_tolerance = dispersion_tolerance;
+        self
+    }
+
+    pub fn build(self) -> PriceOracle {
+        PriceOracle {
+            pair: self.pair,
+            pool: self.pool,
+            providers: self.providers,
+            max_age: self.max_age,
+            timeout: self.timeout,
+            outlier_filter: self.outlier_filter,
+            min_sources: self.min_sources,
+            dispersion_tolerance: self.dispersion_tolerance,
+        }
+    }
+}
+
+pub(crate) async fn fetch(
+    pool: Arc<Mutex<HttpClientPool>>,
+    provider: &dyn PriceProvider,
+) -> Result<Quote, Error> {
+    let request = Arc::new(provider.request());
+    let response = send_request!(pool, request).await?;
+    if !response.is_successful() {
+        return Err(Error::new(
+            format!("Provider responded with status {}", response.status_code()).as_str(),
+            ErrorCode::Unavailable,
+        ));
+    }
+
+    provider.parse(&response)
+}
+
+fn median(values: &[i128]) -> i128 {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+
+    let middle = sorted.len() / 2;
+    if sorted.len() % 2 == 1 {
+        return sorted[middle];
+    }
+
+    // The mean of the middle two, rounded half to even, from their halves so the sum cannot
+    // overflow.
+    let (low, high) = (sorted[middle - 1], sorted[middle]);
+    let half = low / 2 + high / 2;
+    match low % 2 + high % 2 {
+        remainder if remainder % 2 == 0 => half + remainder / 2,
+        _ if half % 2 == 0 => half,
+        remainder => half + remainder,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use utils::http::{HttpMethod, HttpRequest};
+    use utils::testing::MockServer;
+
+    use super::*;
+    use crate::providers::CustomProvider;
+    use crate::test_support::pair;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    fn quote(source: &str, price: i64, age_seconds: i64) -> Result<Quote, Error> {
+        Ok(Quote::new(
+            source,
+            pair(),
+            Money::from_minor(price, pair().quote().clone()),
+            now() - chrono::Duration::seconds(age_seconds),
+        ))
+    }
+
+    fn unreachable_provider(name: &str) -> Arc<dyn PriceProvider> {
+        let request = HttpRequest::new("http://127.0.0.1:9/ticker", HttpMethod::GET).build();
+        Arc::new(CustomProvider::new(name, pair(), request, |_| {
+            Err(Error::new("parser should not run", ErrorCode::Internal))
+        }))
+    }
+
+    fn oracle(sources: usize) -> PriceOracleBuilder {
+        let pool = Arc::new(Mutex::new(HttpClientPool::with_capacity(1)));
+        let mut builder = PriceOracle::new(pair(), pool);
+        for i in 0..sources {
+            builder = builder.provider(unreachable_provider(&format!("source-{}", i)));
+        }
+        builder
+    }
+
+    #[test]
+    fn test_median_and_confidence() {
+        let oracle = oracle(4).build();
+        let outcomes = vec![
+            ("a".into(), quote("a", 3_000_000, 0)),
+            ("b".into(), quote("b", 3_001_000, 0)),
+            ("c".into(), quote("c", 3_002_000, 0)),
+            ("d".into(), quote("d", 2_999_000, 0)),
+        ];
+
+        let price = oracle.aggregate(outcomes, now()).unwrap();
+        assert_eq!(price.price().amount(), 3_000_500);
+        assert_eq!(price.sources().len(), 4);
+        assert!(price.rejected().is_empty());
+        // A 0.1% spread against a 5% tolerance.
+        assert!((price.confidence() - (1.0 - 3_000.0 / 3_000_500.0 / 0.05)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&[3, 1, 2]), 2);
+        assert_eq!(median(&[1, 2]), 2);
+        assert_eq!(median(&[1, 4]), 2);
+        assert_eq!(median(&[-5, -2]), -4);
+        assert_eq!(median(&[i128::MAX, i128::MAX - 1]), i128::MAX - 1);
+        assert_eq!(median(&[i128::MIN, i128::MAX]), 0);
+    }
+
+    #[test]
+    fn test_rejects_stale_and_outlier_quotes() {
+        let oracle = oracle(5).max_age(chrono::Duration::seconds(30)).build();
+        let outcomes = vec![
+            ("a".into(), quote("a", 3_000_000, 0)),
+            ("b".into(), quote("b", 3_001_000, 5)),
+            ("c".into(), quote("c", 3_002_000, 10)),
+            ("d".into(), quote("d", 4_500_000, 0)),
+            ("e".into(), quote("e", 3_000_000, 120)),
+        ];
+
+        let price = oracle.aggregate(outcomes, now()).unwrap();
+        assert_eq!(price.price().amount(), 3_001_000);
+        assert_eq!(
+            price.rejected(),
+            &[
+                ("e".into(), Rejection::Stale),
+                ("d".into(), Rejection::Outlier)
+            ]
+        );
+        assert!((price.confidence() - 0.6 * (1.0 - 2_000.0 / 3_001_000.0 / 0.05)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_percent_deviation_filter() {
+        let oracle = oracle(3)
+            .outlier_filter(OutlierFilter::PercentDeviation(0.01))
+            .build();
+        let outcomes = vec![
+            ("a".into(), quote("a", 10_000, 0)),
+            ("b".into(), quote("b", 10_050, 0)),
+            ("c".into(), quote("c", 10_200, 0)),
+        ];
+
+        let price = oracle.aggregate(outcomes, now()).unwrap();
+        assert_eq!(price.price().amount(), 10_025);
+        assert_eq!(price.rejected(), &[("c".into(), Rejection::Outlier)]);
+    }
+
+    #[test]
+    fn test_failures_are_reported_as_metadata() {
+        let oracle = oracle(3).min_sources(2).build();
+        let outcomes = vec![
+            ("a".into(), quote("a", 3_000_000, 0)),
+            (
+                "b".into(),
+                Err(Error::new("connection refused", ErrorCode::Internal)),
+            ),
+            ("c".into(), quote("c", 3_000_000, 600)),
+        ];
+
+        let error = oracle.aggregate(outcomes, now()).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Unavailable);
+        assert_eq!(error.meta_value("b"), Some("connection refused"));
+        assert_eq!(error.meta_value("c"), Some("rejected: stale"));
+    }
+
+    #[macros::async_test]
+    async fn test_query_survives_failing_providers() {
+        let oracle = oracle(2).timeout(Duration::from_secs(5)).build();
+
+        let error = oracle.query().await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Unavailable);
+        assert!(error.meta_value("source-0").is_some());
+        assert!(error.meta_value("source-1").is_some());
+    }
+
+    #[macros::async_test(timeout = 10)]
+    async fn test_query_mock_providers(server: MockServer, pool: Arc<Mutex<HttpClientPool>>) {
+        server.mock(HttpMethod::GET, "/a", 200, "30000.00");
+        server.mock(HttpMethod::GET, "/b", 200, "30010.00");
+        server.mock(HttpMethod::GET, "/c", 503, "");
+
+        let mut builder = PriceOracle::new(pair(), pool);
+        for source in ["a", "b", "c"] {
+            let url = format!("{}/{}", server.url(), source);
+            let request = HttpRequest::new(&url, HttpMethod::GET).build();
+            let provider = CustomProvider::new(source, pair(), request, move |response| {
+                let body = response.body().as_deref().unwrap_or_default();
+                let price = Money::parse(body, pair().quote().clone())?;
+                Ok(Quote::new(source, pair(), price, Utc::now()))
+            });
+            builder = builder.provider(Arc::new(provider));
+        }
+
+        let price = builder.build().query().await.unwrap();
+        assert_eq!(
+            price.price(),
+            &Money::parse("30005.00", pair().quote().clone()).unwrap()
+        );
+        assert_eq!(price.sources().len(), 2);
+        assert_eq!(server.hits(HttpMethod::GET, "/c"), 1);
+    }
+}
@@ -0,0 +1,129 @@
+pub mod candle;
+pub mod point;
+pub mod quote;
+
+use chrono::{DateTime, Duration, Utc};
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::currency::Currency;
+use super::money::ensure_currency;
+
+pub use candle::Candle;
+pub use point::PricePoint;
+pub use quote::Quote;
+
+/// A time ordered series of prices quoted in a single `Currency`.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[macros::json]
+pub struct PriceSeries {
+    currency: Currency,
+    points: Vec<PricePoint>,
+}
+
+impl PriceSeries {
+    pub fn new(currency: Currency) -> Self {
+        Self {
+            currency,
+            points: Vec::new(),
+        }
+    }
+
+    /// Creates a series from existing points, validating currency and ordering.
+    pub fn from_points(currency: Currency, points: Vec<PricePoint>) -> Result<Self, Error> {
+        let mut series = Self::new(currency);
+        for point in points {
+            series.push(point)?;
+        }
+        Ok(series)
+    }
+
+    /// Appends a point to the end of the series.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the point is quoted in another currency or is
+    /// older than the last point in the series.
+    pub fn push(&mut self, point: PricePoint) -> Result<(), Error> {
+        ensure_currency(&self.currency, point.price().currency())?;
+
+        if let Some(last) = self.points.last() {
+            if point.timestamp() < last.timestamp() {
+                return Err(
+                    Error::new("Price points out of order", ErrorCode::Invalid).with_meta(
+                        ErrorMeta::new()
+                            .add("last", last.timestamp().to_rfc3339().as_str())
+                            .add("timestamp", point.timestamp().to_rfc3339().as_str())
+                            .build(),
+                    ),
+                );
+            }
+        }
+
+        self.points.push(point);
+        Ok(())
+    }
+
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    pub fn points(&self) -> &[PricePoint] {
+        &self.points
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, PricePoint> {
+        self.points.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&PricePoint> {
+        self.points.first()
+    }
+
+    pub fn last(&self) -> Option<&PricePoint> {
+        self.points.last()
+    }
+
+    /// Groups the points into candles of a fixed interval, aligned to the Unix epoch. Intervals
+    /// without points are skipped.
+    ///
+    /// Fails with `ErrorCode::Invalid` if the interval is not positive.
+    pub fn candles(&self, interval: Duration) -> Result<Vec<Candle>, Error> {
+        let seconds = interval.num_seconds();
+        if seconds <= 0 {
+            return Err(
+                Error::new("Candle interval must be positive", ErrorCode::Invalid).with_meta(
+                    ErrorMeta::new()
+                        .add("interval", interval.to_string().as_str())
+                        .build(),
+                ),
+            );
+        }
+
+        let mut candles: Vec<Candle> = Vec::new();
+        for point in &self.points {
+            let start = point.timestamp().timestamp().div_euclid(seconds) * seconds;
+            let start = DateTime::<Utc>::from_timestamp(start, 0).unwrap_or(point.timestamp());
+            match candles.last_mut() {
+                Some(candle) if candle.timestamp() == start => candle.merge(point)?,
+                _ => candles.push(Candle::from_point(point).starting_at(start)),
+            }
+        }
+
+        Ok(candles)
+    }
+}
+
+impl<'a> IntoIterator for &'a PriceSeries {
+    type Item = &'a PricePoint;
+    type IntoIter = std::slice::Iter<'a, PricePoint>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.points.iter()
+    }
+}
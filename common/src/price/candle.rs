@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::point::PricePoint;
+use crate::money::{ensure_currency, Money};
+
+/// Open, high, low and close prices over an interval starting at `timestamp`, optionally with
+/// the traded volume in the base asset.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Candle {
+    timestamp: DateTime<Utc>,
+    open: Money,
+    high: Money,
+    low: Money,
+    close: Money,
+    volume: Option<Money>,
+}
+
+impl Candle {
+    /// Fails with `ErrorCode::Invalid` if the prices are in different currencies or the high and
+    /// low do not bound the open and close.
+    pub fn new(
+        timestamp: DateTime<Utc>,
+        open: Money,
+        high: Money,
+        low: Money,
+        close: Money,
+    ) -> Result<Self, Error> {
+        for price in [&high, &low, &close] {
+            ensure_currency(open.currency(), price.currency())?;
+        }
+
+        let amounts = [open.amount(), close.amount()];
+        if amounts
+            .iter()
+            .any(|a| *a > high.amount() || *a < low.amount())
+        {
+            return Err(
+                Error::new("Candle prices out of range", ErrorCode::Invalid).with_meta(
+                    ErrorMeta::new()
+                        .add("timestamp", timestamp.to_rfc3339().as_str())
+                        .add("high", high.to_decimal_string().as_str())
+                        .add("low", low.to_decimal_string().as_str())
+                        .build(),
+                ),
+            );
+        }
+
+        Ok(Self {
+            timestamp,
+            open,
+            high,
+            low,
+            close,
+            volume: None,
+        })
+    }
+
+    /// A candle for an interval with a single price.
+    pub fn from_point(point: &PricePoint) -> Self {
+        let price = point.price().clone();
+        Self {
+            timestamp: point.timestamp(),
+            open: price.clone(),
+            high: price.clone(),
+            low: price.clone(),
+            close: price,
+            volume: point.volume().cloned(),
+        }
+    }
+
+    pub fn with_volume(mut self, volume: Money) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+
+    /// The start of the interval.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    pub fn open(&self) -> &Money {
+        &self.open
+    }
+
+    pub fn high(&self) -> &Money {
+        &self.high
+    }
+
+    pub fn low(&self) -> &Money {
+        &self.low
+    }
+
+    pub fn close(&self) -> &Money {
+        &self.close
+    }
+
+    pub fn volume(&self) -> Option<&Money> {
+        self.volume.as_ref()
+    }
+
+    /// The closing price as a point, so candles can be fed to indicators.
+    pub fn close_point(&self) -> PricePoint {
+        let point = PricePoint::new(self.timestamp, self.close.clone());
+        match &self.volume {
+            Some(volume) => point.with_volume(volume.clone()),
+            None => point,
+        }
+    }
+
+    /// Moves the start of the candle to the start of its interval.
+    pub(crate) fn starting_at(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Extends the candle with a later point of the same interval.
+    pub(crate) fn merge(&mut self, point: &PricePoint) -> Result<(), Error> {
+        let price = point.price();
+        ensure_currency(self.close.currency(), price.currency())?;
+
+        if price.amount() > self.high.amount() {
+            self.high = price.clone();
+        }
+        if price.amount() < self.low.amount() {
+            self.low = price.clone();
+        }
+        self.close = price.clone();
+
+        self.volume = match (self.volume.take(), point.volume()) {
+            (Some(total), Some(volume)) => Some(total.checked_add(volume)?),
+            (total, volume) => total.or_else(|| volume.cloned()),
+        };
+        Ok(())
+    }
+}
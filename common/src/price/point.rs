@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+
+use crate::money::Money;
+
+/// A single observation of a price, optionally with the traded volume in the base asset.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct PricePoint {
+    timestamp: DateTime<Utc>,
+    price: Money,
+    volume: Option<Money>,
+}
+
+impl PricePoint {
+    pub fn new(timestamp: DateTime<Utc>, price: Money) -> Self {
+        Self {
+            timestamp,
+            price,
+            volume: None,
+        }
+    }
+
+    pub fn with_volume(mut self, volume: Money) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    pub fn price(&self) -> &Money {
+        &self.price
+    }
+
+    pub fn volume(&self) -> Option<&Money> {
+        self.volume.as_ref()
+    }
+}
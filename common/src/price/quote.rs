@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+
+use crate::currency::CurrencyPair;
+use crate::money::Money;
+
+/// A price for a `CurrencyPair` reported by a single source at a point in time.
+#[derive(Debug, Clone, PartialEq)]
+#[macros::json]
+pub struct Quote {
+    source: Box<str>,
+    pair: CurrencyPair,
+    price: Money,
+    bid: Option<Money>,
+    ask: Option<Money>,
+    volume: Option<Money>,
+    timestamp: DateTime<Utc>,
+}
+
+impl Quote {
+    pub fn new(source: &str, pair: CurrencyPair, price: Money, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            source: source.into(),
+            pair,
+            price,
+            bid: None,
+            ask: None,
+            volume: None,
+            timestamp,
+        }
+    }
+
+    pub fn with_bid_ask(mut self, bid: Money, ask: Money) -> Self {
+        self.bid = Some(bid);
+        self.ask = Some(ask);
+        self
+    }
+
+    pub fn with_volume(mut self, volume: Money) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    pub fn price(&self) -> &Money {
+        &self.price
+    }
+
+    pub fn bid(&self) -> Option<&Money> {
+        self.bid.as_ref()
+    }
+
+    pub fn ask(&self) -> Option<&Money> {
+        self.ask.as_ref()
+    }
+
+    pub fn volume(&self) -> Option<&Money> {
+        self.volume.as_ref()
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+}
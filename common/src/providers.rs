@@ -0,0 +1,393 @@
+//! Price providers.
+//!
+//! A `PriceProvider` knows how to ask one venue for a ticker and how to map the venue's JSON
+//! response into a `Quote`. Built-in providers cover the common public ticker shapes
+//! (`CoinGecko`, `Coinbase` and `Kraken`); anything else can be plugged in by implementing the
+//! trait or wrapping a request and a parser in a `CustomProvider`, and registered alongside the
+//! built-ins in a `ProviderRegistry`.
+
+pub mod coinbase;
+pub mod coingecko;
+pub mod kraken;
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+use utils::http::{HttpRequest, HttpResponse};
+use utils::json::JSON;
+
+use super::currency::{Currency, CurrencyPair};
+use super::money::Money;
+use super::price::Quote;
+
+pub use coinbase::Coinbase;
+pub use coingecko::CoinGecko;
+pub use kraken::Kraken;
+
+/// A venue that can be asked for the current price of a `CurrencyPair`.
+pub trait PriceProvider: Send + Sync {
+    /// A unique name for the provider, used as the quote source.
+    fn name(&self) -> &str;
+
+    /// The pair the provider quotes.
+    fn pair(&self) -> &CurrencyPair;
+
+    /// The request that fetches the current ticker.
+    fn request(&self) -> HttpRequest;
+
+    /// Maps a successful response into a quote.
+    fn parse(&self, response: &HttpResponse) -> Result<Quote, Error>;
+}
+
+/// Turns a provider response into a quote.
+pub type QuoteParser = dyn Fn(&HttpResponse) -> Result<Quote, Error> + Send + Sync;
+
+/// A provider built from a request and a parser closure.
+#[derive(Clone)]
+pub struct CustomProvider {
+    name: Box<str>,
+    pair: CurrencyPair,
+    request: HttpRequest,
+    parser: Arc<QuoteParser>,
+}
+
+impl CustomProvider {
+    pub fn new<F>(name: &str, pair: CurrencyPair, request: HttpRequest, parser: F) -> Self
+    where
+        F: Fn(&HttpResponse) -> Result<Quote, Error> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.into(),
+            pair,
+            request,
+            parser: Arc::new(parser),
+        }
+    }
+}
+
+impl PriceProvider for CustomProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    fn request(&self) -> HttpRequest {
+        self.request.clone()
+    }
+
+    fn parse(&self, response: &HttpResponse) -> Result<Quote, Error> {
+        (self.parser)(response)
+    }
+}
+
+/// A set of providers with unique names.
+#[derive(Clone, Default)]
+pub struct ProviderRegistry {
+    providers: Vec<Arc<dyn PriceProvider>>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry holding the built-in providers for the pair.
+    pub fn with_defaults(pair: &CurrencyPair) -> Self {
+        let mut registry = Self::new();
+        let defaults: [Arc<dyn PriceProvider>; 3] = [
+            Arc::new(CoinGecko::new(pair.clone())),
+            Arc::new(Coinbase::new(pair.clone())),
+            Arc::new(Kraken::new(pair.clone())),
+        ];
+        for provider in defaults {
+            registry.providers.push(provider);
+        }
+        registry
+    }
+
+    /// Adds a provider.
+    ///
+    /// Fails with `ErrorCode::Conflict` if a provider with the same name is already registered.
+    pub fn register<P>(&mut self, provider: P) -> Result<(), Error>
+    where
+        P: PriceProvider + 'static,
+    {
+        self.register_shared(Arc::new(provider))
+    }
+
+    /// Adds a provider that is shared with other owners.
+    pub fn register_shared(&mut self, provider: Arc<dyn PriceProvider>) -> Result<(), Error> {
+        if self.get(provider.name()).is_some() {
+            return Err(
+                Error::new("Provider already registered", ErrorCode::Conflict)
+                    .with_meta(ErrorMeta::new().add("provider", provider.name()).build()),
+            );
+        }
+
+        self.providers.push(provider);
+        Ok(())
+    }
+
+    /// Removes a provider by name, returning it if it was registered.
+    pub fn unregister(&mut self, name: &str) -> Option<Arc<dyn PriceProvider>> {
+        let index = self.providers.iter().position(|p| p.name() == name)?;
+        Some(self.providers.remove(index))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn PriceProvider>> {
+        self.providers.iter().find(|p| p.name() == name).cloned()
+    }
+
+    /// Every registered provider, in registration order.
+    pub fn providers(&self) -> Vec<Arc<dyn PriceProvider>> {
+        self.providers.clone()
+    }
+
+    /// The registered providers quoting the given pair.
+    pub fn for_pair(&self, pair: &CurrencyPair) -> Vec<Arc<dyn PriceProvider>> {
+        self.providers
+            .iter()
+            .filter(|p| p.pair() == pair)
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.providers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+}
+
+/// Parses a response body as JSON.
+fn parse_body(provider: &str, response: &HttpResponse) -> Result<Value, Error> {
+    let body = response.body().as_deref().ok_or_else(|| {
+        Error::new("Empty response body", ErrorCode::Unprocessable)
+            .with_meta(ErrorMeta::new().add("provider", provider).build())
+    })?;
+
+    Value::from_json(body)
+}
+
+/// Follows a path of object keys, failing with `ErrorCode::Unprocessable` naming the path if any
+/// key is missing.
+fn field<'v>(provider: &str, value: &'v Value, path: &[&str]) -> Result<&'v Value, Error> {
+    path.iter()
+        .try_fold(value, |value, key| value.get(*key))
+        .ok_or_else(|| missing(provider, &path.join(".")))
+}
+
+/// Reads a price given either as a JSON string or number.
+fn amount(provider: &str, value: &Value, currency: &Currency) -> Result<Money, Error> {
+    let decimal = match value {
+        Value::String(decimal) => decimal.clone(),
+        Value::Number(number) => match number.as_f64() {
+            // Small and large floats print in exponent notation, which amounts are not read in.
+            Some(float) if number.to_string().contains(['e', 'E']) => {
+                format!("{:.*}", currency.decimal_places() as usize, float)
+            }
+            _ => number.to_string(),
+        },
+        _ => return Err(missing(provider, "amount")),
+    };
+
+    Money::parse_rounded(&decimal, currency.clone()).map_err(|err| {
+        Error::new(
+            "Provider returned an invalid amount",
+            ErrorCode::Unprocessable,
+        )
+        .with_meta(
+            ErrorMeta::new()
+                .add("provider", provider)
+                .add("value", &decimal)
+                .add("reason", err.message())
+                .build(),
+        )
+    })
+}
+
+/// Reads a timestamp given as Unix seconds or an RFC 3339 string.
+fn timestamp(provider: &str, value: &Value) -> Result<DateTime<Utc>, Error> {
+    let parsed = match value {
+        Value::Number(seconds) => seconds
+            .as_i64()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0)),
+        Value::String(text) => DateTime::parse_from_rfc3339(text)
+            .ok()
+            .map(|timestamp| timestamp.with_timezone(&Utc)),
+        _ => None,
+    };
+
+    parsed.ok_or_else(|| missing(provider, "timestamp"))
+}
+
+fn missing(provider: &str, field: &str) -> Error {
+    Error::new("Unexpected provider response", ErrorCode::Unprocessable).with_meta(
+        ErrorMeta::new()
+            .add("provider", provider)
+            .add("field", field)
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use utils::http::HttpMethod;
+
+    use super::*;
+    use crate::test_support::pair;
+
+    fn response(body: &str) -> HttpResponse {
+        HttpResponse::new(200, Some(body), None)
+    }
+
+    #[test]
+    fn test_coingecko_fixture() {
+        let provider = CoinGecko::new(pair());
+        assert!(provider
+            .request()
+            .url
+            .contains("ids=bitcoin&vs_currencies=usd"));
+
+        let quote = provider
+            .parse(&response(include_str!(
+                "../fixtures/providers/coingecko.json"
+            )))
+            .unwrap();
+        assert_eq!(quote.source(), "coingecko");
+        assert_eq!(quote.pair(), &pair());
+        assert_eq!(quote.price().to_decimal_string(), "70123.46");
+        assert_eq!(
+            quote.timestamp(),
+            Utc.with_ymd_and_hms(2023, 11, 14, 22, 13, 20).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_coinbase_fixture() {
+        let provider = Coinbase::new(pair());
+        assert!(provider.request().url.ends_with("/products/BTC-USD/ticker"));
+
+        let quote = provider
+            .parse(&response(include_str!(
+                "../fixtures/providers/coinbase.json"
+            )))
+            .unwrap();
+        assert_eq!(quote.source(), "coinbase");
+        assert_eq!(quote.price().to_decimal_string(), "70120.01");
+        assert_eq!(quote.bid().unwrap().to_decimal_string(), "70120.00");
+        assert_eq!(quote.ask().unwrap().to_decimal_string(), "70120.02");
+        assert_eq!(
+            quote.volume().unwrap().to_decimal_string(),
+            "12345.67891234"
+        );
+        assert_eq!(quote.timestamp().timestamp(), 1_699_999_999);
+    }
+
+    #[test]
+    fn test_kraken_fixture() {
+        let provider = Kraken::new(pair());
+        assert!(provider.request().url.ends_with("/Ticker?pair=XBTUSD"));
+
+        let quote = provider
+            .parse(&response(include_str!("../fixtures/providers/kraken.json")))
+            .unwrap();
+        assert_eq!(quote.source(), "kraken");
+        assert_eq!(quote.price().to_decimal_string(), "70118.50");
+        assert_eq!(quote.bid().unwrap().to_decimal_string(), "70118.40");
+        assert_eq!(quote.ask().unwrap().to_decimal_string(), "70118.60");
+        assert_eq!(quote.volume().unwrap().to_decimal_string(), "2345.12345679");
+    }
+
+    #[test]
+    fn test_kraken_reports_api_errors() {
+        let error = Kraken::new(pair())
+            .parse(&response(r#"{"error":["EQuery:Unknown asset pair"]}"#))
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Unavailable);
+        assert_eq!(
+            error.meta_value("reason"),
+            Some("EQuery:Unknown asset pair")
+        );
+    }
+
+    #[test]
+    fn test_unexpected_shapes() {
+        let error = CoinGecko::new(pair())
+            .parse(&response(r#"{"ethereum":{"usd":2000}}"#))
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Unprocessable);
+        assert_eq!(error.meta_value("field"), Some("bitcoin.usd"));
+
+        let error = Coinbase::new(pair())
+            .parse(&response("not json"))
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::JsonParse);
+
+        let error = Coinbase::new(pair())
+            .parse(&HttpResponse::new(200, None, None))
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Unprocessable);
+    }
+
+    #[test]
+    fn test_amounts() {
+        let btc = pair().base().clone();
+        for (json, minor) in [
+            ("2.5e-5", 2_500),
+            ("1.5E3", 150_000_000_000),
+            ("0.1", 10_000_000),
+        ] {
+            let value = Value::from_json(json).unwrap();
+            assert_eq!(
+                amount("test", &value, &btc).unwrap().amount(),
+                minor,
+                "{}",
+                json
+            );
+        }
+        let value = Value::from_json("true").unwrap();
+        assert!(amount("test", &value, &btc).is_err());
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = ProviderRegistry::with_defaults(&pair());
+        assert_eq!(registry.len(), 3);
+        assert!(registry.get("kraken").is_some());
+
+        let request = HttpRequest::new("https://example.com/btc", HttpMethod::GET).build();
+        let custom = CustomProvider::new("example", pair(), request, |response| {
+            let body = parse_body("example", response)?;
+            let price = amount(
+                "example",
+                field("example", &body, &["price"])?,
+                &Currency::default(),
+            )?;
+            Ok(Quote::new("example", pair(), price, Utc::now()))
+        });
+        registry.register(custom.clone()).unwrap();
+
+        let error = registry.register(custom).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Conflict);
+
+        let provider = registry.get("example").unwrap();
+        let quote = provider.parse(&response(r#"{"price":"1.5"}"#)).unwrap();
+        assert_eq!(quote.price().amount(), 150);
+
+        let other = CurrencyPair::parse("BTC/EUR").unwrap();
+        assert_eq!(registry.for_pair(&pair()).len(), 4);
+        assert!(registry.for_pair(&other).is_empty());
+
+        assert!(registry.unregister("example").is_some());
+        assert!(registry.get("example").is_none());
+    }
+}
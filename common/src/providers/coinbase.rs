@@ -0,0 +1,68 @@
+use utils::errors::Error;
+use utils::http::{HttpMethod, HttpRequest, HttpResponse};
+
+use super::{amount, field, parse_body, timestamp, PriceProvider};
+use crate::currency::CurrencyPair;
+use crate::price::Quote;
+
+const NAME: &str = "coinbase";
+const DEFAULT_BASE_URL: &str = "https://api.exchange.coinbase.com";
+
+/// Coinbase Exchange's product ticker, shaped like
+/// `{"ask":"70120.02","bid":"70120.00","volume":"12345.6","price":"70120.01","time":"..."}`.
+pub struct Coinbase {
+    pair: CurrencyPair,
+    base_url: Box<str>,
+}
+
+impl Coinbase {
+    pub fn new(pair: CurrencyPair) -> Self {
+        Self {
+            pair,
+            base_url: DEFAULT_BASE_URL.into(),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').into();
+        self
+    }
+
+    fn product_id(&self) -> String {
+        format!(
+            "{}-{}",
+            self.pair.base().code().to_string(),
+            self.pair.quote().code().to_string()
+        )
+    }
+}
+
+impl PriceProvider for Coinbase {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    fn request(&self) -> HttpRequest {
+        let url = format!("{}/products/{}/ticker", self.base_url, self.product_id());
+        HttpRequest::new(&url, HttpMethod::GET).build()
+    }
+
+    fn parse(&self, response: &HttpResponse) -> Result<Quote, Error> {
+        let body = parse_body(NAME, response)?;
+        let quote_currency = self.pair.quote();
+
+        let price = amount(NAME, field(NAME, &body, &["price"])?, quote_currency)?;
+        let bid = amount(NAME, field(NAME, &body, &["bid"])?, quote_currency)?;
+        let ask = amount(NAME, field(NAME, &body, &["ask"])?, quote_currency)?;
+        let volume = amount(NAME, field(NAME, &body, &["volume"])?, self.pair.base())?;
+        let time = timestamp(NAME, field(NAME, &body, &["time"])?)?;
+
+        Ok(Quote::new(NAME, self.pair.clone(), price, time)
+            .with_bid_ask(bid, ask)
+            .with_volume(volume))
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::Utc;
+use utils::errors::Error;
+use utils::http::{HttpMethod, HttpRequest, HttpResponse};
+
+use super::{amount, field, parse_body, timestamp, PriceProvider};
+use crate::currency::CurrencyPair;
+use crate::price::Quote;
+
+const NAME: &str = "coingecko";
+const DEFAULT_BASE_URL: &str = "https://api.coingecko.com/api/v3";
+
+/// CoinGecko's `simple/price` endpoint, shaped like
+/// `{"bitcoin":{"usd":70123.45,"last_updated_at":1700000000}}`.
+pub struct CoinGecko {
+    pair: CurrencyPair,
+    base_url: Box<str>,
+    coin_id: Box<str>,
+}
+
+impl CoinGecko {
+    /// The coin id defaults to the lowercase name of the base currency, e.g. `bitcoin`.
+    pub fn new(pair: CurrencyPair) -> Self {
+        let coin_id = pair.base().name().to_string().to_lowercase();
+        Self {
+            pair,
+            base_url: DEFAULT_BASE_URL.into(),
+            coin_id: coin_id.into(),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').into();
+        self
+    }
+
+    pub fn coin_id(mut self, coin_id: &str) -> Self {
+        self.coin_id = coin_id.into();
+        self
+    }
+
+    fn vs_currency(&self) -> String {
+        self.pair.quote().code().to_string().to_lowercase()
+    }
+}
+
+impl PriceProvider for CoinGecko {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    fn request(&self) -> HttpRequest {
+        let url = format!(
+            "{}/simple/price?ids={}&vs_currencies={}&include_last_updated_at=true",
+            self.base_url,
+            self.coin_id,
+            self.vs_currency()
+        );
+        HttpRequest::new(&url, HttpMethod::GET).build()
+    }
+
+    fn parse(&self, response: &HttpResponse) -> Result<Quote, Error> {
+        let body = parse_body(NAME, response)?;
+        let vs_currency = self.vs_currency();
+
+        let price = field(NAME, &body, &[&self.coin_id, &vs_currency])?;
+        let price = amount(NAME, price, self.pair.quote())?;
+
+        let updated = match field(NAME, &body, &[&self.coin_id, "last_updated_at"]) {
+            Ok(updated) => timestamp(NAME, updated)?,
+            Err(_) => Utc::now(),
+        };
+
+        Ok(Quote::new(NAME, self.pair.clone(), price, updated))
+    }
+}
@@ -0,0 +1,108 @@
+use chrono::Utc;
+use serde_json::Value;
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+use utils::http::{HttpMethod, HttpRequest, HttpResponse};
+
+use super::{amount, field, missing, parse_body, PriceProvider};
+use crate::currency::code::CurrencyCode;
+use crate::currency::CurrencyPair;
+use crate::price::Quote;
+
+const NAME: &str = "kraken";
+const DEFAULT_BASE_URL: &str = "https://api.kraken.com/0/public";
+
+/// Kraken's public `Ticker` endpoint, shaped like
+/// `{"error":[],"result":{"XXBTZUSD":{"a":["70118.6",..],"b":["70118.4",..],"c":["70118.5",..],"v":[..,"2345.1"]}}}`.
+///
+/// Kraken has no timestamp in the ticker, so quotes are stamped with the time they were parsed.
+pub struct Kraken {
+    pair: CurrencyPair,
+    base_url: Box<str>,
+}
+
+impl Kraken {
+    pub fn new(pair: CurrencyPair) -> Self {
+        Self {
+            pair,
+            base_url: DEFAULT_BASE_URL.into(),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').into();
+        self
+    }
+
+    fn asset(code: &CurrencyCode) -> &str {
+        match code {
+            CurrencyCode::BTC => "XBT",
+            code => code.to_string(),
+        }
+    }
+}
+
+impl PriceProvider for Kraken {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn pair(&self) -> &CurrencyPair {
+        &self.pair
+    }
+
+    fn request(&self) -> HttpRequest {
+        let url = format!(
+            "{}/Ticker?pair={}{}",
+            self.base_url,
+            Self::asset(self.pair.base().code()),
+            Self::asset(self.pair.quote().code())
+        );
+        HttpRequest::new(&url, HttpMethod::GET).build()
+    }
+
+    fn parse(&self, response: &HttpResponse) -> Result<Quote, Error> {
+        let body = parse_body(NAME, response)?;
+
+        if let Some(Value::Array(errors)) = body.get("error") {
+            if !errors.is_empty() {
+                let reason = errors
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(
+                    Error::new("Provider returned an error", ErrorCode::Unavailable).with_meta(
+                        ErrorMeta::new()
+                            .add("provider", NAME)
+                            .add("reason", &reason)
+                            .build(),
+                    ),
+                );
+            }
+        }
+
+        // The result is keyed by Kraken's internal pair name (e.g. `XXBTZUSD`), which differs
+        // from the name used in the request, so take the single entry.
+        let ticker = field(NAME, &body, &["result"])?
+            .as_object()
+            .and_then(|result| result.values().next())
+            .ok_or_else(|| missing(NAME, "result"))?;
+
+        let first = |key: &str, index: usize| {
+            ticker
+                .get(key)
+                .and_then(|values| values.get(index))
+                .ok_or_else(|| missing(NAME, key))
+        };
+
+        let quote_currency = self.pair.quote();
+        let price = amount(NAME, first("c", 0)?, quote_currency)?;
+        let bid = amount(NAME, first("b", 0)?, quote_currency)?;
+        let ask = amount(NAME, first("a", 0)?, quote_currency)?;
+        let volume = amount(NAME, first("v", 1)?, self.pair.base())?;
+
+        Ok(Quote::new(NAME, self.pair.clone(), price, Utc::now())
+            .with_bid_ask(bid, ask)
+            .with_volume(volume))
+    }
+}
@@ -0,0 +1,452 @@
+//! Exchange rate caching.
+//!
+//! A `RateCache` sits in front of a `RateSource`, such as a provider or an oracle, and keeps the
+//! last quote fetched for every currency pair. Within a pair's time to live the cached quote is
+//! served as is. After that it is still served while it is younger than the pair's maximum
+//! staleness, and a single background refresh replaces it; past the maximum staleness callers wait
+//! for a fresh quote and get `ErrorCode::Unavailable` if it cannot be fetched.
+//!
+//! Refreshes are single-flight: however many callers need a pair refreshed at the same time,
+//! only one request goes upstream and all of them get its result, a failure included.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::time::Instant;
+use utils::errors::{Error, ErrorCode, ErrorMeta};
+
+use super::currency::CurrencyPair;
+use super::price::Quote;
+
+const DEFAULT_TTL_SECONDS: u64 = 30;
+const DEFAULT_MAX_STALENESS_SECONDS: u64 = 300;
+
+/// The future a `RateSource` resolves a quote with.
+pub type RateFuture = Pin<Box<dyn Future<Output = Result<Quote, Error>> + Send>>;
+
+/// Where a `RateCache` fetches quotes from.
+///
+/// Implemented for closures taking the pair and returning a future, so any async lookup can be
+/// cached.
+pub trait RateSource: Send + Sync {
+    fn fetch(&self, pair: &CurrencyPair) -> RateFuture;
+}
+
+impl<F, Fut> RateSource for F
+where
+    F: Fn(CurrencyPair) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Quote, Error>> + Send + 'static,
+{
+    fn fetch(&self, pair: &CurrencyPair) -> RateFuture {
+        Box::pin(self(pair.clone()))
+    }
+}
+
+/// How long cached quotes of a pair are served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    ttl: Duration,
+    max_staleness: Duration,
+}
+
+impl CachePolicy {
+    /// Quotes younger than `ttl` are served without refreshing; older ones are served while a
+    /// refresh runs in the background until they are older than `max_staleness`.
+    ///
+    /// Fails with `ErrorCode::Invalid` if `max_staleness` is shorter than `ttl`.
+    pub fn new(ttl: Duration, max_staleness: Duration) -> Result<Self, Error> {
+        if max_staleness < ttl {
+            return Err(Error::new(
+                "Maximum staleness is shorter than the TTL",
+                ErrorCode::Invalid,
+            )
+            .with_meta(
+                ErrorMeta::new()
+                    .add("ttl", format!("{:?}", ttl).as_str())
+                    .add("max_staleness", format!("{:?}", max_staleness).as_str())
+                    .build(),
+            ));
+        }
+        Ok(Self { ttl, max_staleness })
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn max_staleness(&self) -> Duration {
+        self.max_staleness
+    }
+}
+
+impl Default for CachePolicy {
+    /// Thirty seconds fresh, then stale for up to five minutes.
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(DEFAULT_TTL_SECONDS),
+            max_staleness: Duration::from_secs(DEFAULT_MAX_STALENESS_SECONDS),
+        }
+    }
+}
+
+/// The outcome of a refresh, published to everyone waiting for it once the fetch completes.
+type Refresh = watch::Receiver<Option<Result<Quote, Arc<Error>>>>;
+
+/// The cached quote of one pair.
+#[derive(Default)]
+struct Slot {
+    entry: Mutex<Option<(Quote, Instant)>>,
+    /// The latest refresh, which is still running while its outcome is `None`.
+    refresh: Mutex<Option<Refresh>>,
+}
+
+impl Slot {
+    fn get(&self) -> Option<(Quote, Duration)> {
+        let entry = self.entry.lock().unwrap_or_else(|e| e.into_inner());
+        entry
+            .as_ref()
+            .map(|(quote, fetched)| (quote.clone(), fetched.elapsed()))
+    }
+
+    fn set(&self, quote: Quote) {
+        let mut entry = self.entry.lock().unwrap_or_else(|e| e.into_inner());
+        *entry = Some((quote, Instant::now()));
+    }
+}
+
+/// A cache of quotes in front of a `RateSource`. Clones share the same cache.
+#[derive(Clone)]
+pub struct RateCache {
+    source: Arc<dyn RateSource>,
+    policies: Arc<HashMap<CurrencyPair, CachePolicy>>,
+    default_policy: CachePolicy,
+    slots: Arc<Mutex<HashMap<CurrencyPair, Arc<Slot>>>>,
+}
+
+impl RateCache {
+    pub fn new<S>(source: S) -> RateCacheBuilder
+    where
+        S: RateSource + 'static,
+    {
+        RateCacheBuilder::new(Arc::new(source))
+    }
+
+    /// The policy applied to the pair.
+    pub fn policy(&self, pair: &CurrencyPair) -> CachePolicy {
+        self.policies
+            .get(pair)
+            .copied()
+            .unwrap_or(self.default_policy)
+    }
+
+    /// The quote for the pair, from the cache when it is young enough.
+    ///
+    /// Fails with `ErrorCode::Unavailable` if there is no cached quote within the maximum
+    /// staleness and a fresh one cannot be fetched.
+    pub async fn get(&self, pair: &CurrencyPair) -> Result<Quote, Error> {
+        let policy = self.policy(pair);
+        let slot = self.slot(pair);
+
+        if let Some((quote, age)) = slot.get() {
+            if age <= policy.ttl {
+                return Ok(quote);
+            }
+            if age <= policy.max_staleness {
+                self.refresh(pair, &slot);
+                return Ok(quote);
+            }
+        }
+
+        let mut refresh = self.refresh(pair, &slot);
+        let outcome = match refresh.wait_for(Option::is_some).await {
+            Ok(outcome) => outcome.clone(),
+            // The refresh task was dropped, which only happens when the runtime shuts down.
+            Err(_) => None,
+        };
+        match outcome {
+            Some(Ok(quote)) => Ok(quote),
+            Some(Err(err)) => Err(unavailable(pair, &slot, err.message()).with_cause(err)),
+            None => Err(unavailable(pair, &slot, "refresh cancelled")),
+        }
+    }
+
+    /// Caches a quote as if it had just been fetched.
+    pub fn insert(&self, quote: Quote) {
+        self.slot(quote.pair()).set(quote);
+    }
+
+    /// Drops the cached quote of a pair so the next lookup fetches a fresh one.
+    pub fn invalidate(&self, pair: &CurrencyPair) {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        slots.remove(pair);
+    }
+
+    fn slot(&self, pair: &CurrencyPair) -> Arc<Slot> {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        slots.entry(pair.clone()).or_default().clone()
+    }
+
+    /// Refreshes the pair in the background unless a refresh is already running, and returns
+    /// the refresh to wait for.
+    fn refresh(&self, pair: &CurrencyPair, slot: &Arc<Slot>) -> Refresh {
+        let mut refresh = slot.refresh.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(running) = refresh.as_ref().filter(|r| r.borrow().is_none()) {
+            return running.clone();
+        }
+
+        let (sender, receiver) = watch::channel(None);
+        *refresh = Some(receiver.clone());
+
+        let source = self.source.clone();
+        let slot = slot.clone();
+        let pair = pair.clone();
+        tokio::spawn(async move {
+            let outcome = fetch(source.as_ref(), &pair).await;
+            // A failed refresh leaves the stale quote in place until it passes the maximum
+            // staleness, when callers get the error instead.
+            if let Ok(quote) = &outcome {
+                slot.set(quote.clone());
+            }
+            sender.send_replace(Some(outcome.map_err(Arc::new)));
+        });
+        receiver
+    }
+}
+
+/// The error for a pair whose quote is too old to serve and could not be refreshed.
+fn unavailable(pair: &CurrencyPair, slot: &Slot, reason: &str) -> Error {
+    let age = slot.get().map(|(_, age)| format!("{:?}", age));
+    Error::new("Rate unavailable", ErrorCode::Unavailable).with_meta(
+        ErrorMeta::new()
+            .add("pair", pair.to_string().as_str())
+            .add("age", age.as_deref().unwrap_or("none"))
+            .add("reason", reason)
+            .build(),
+    )
+}
+
+pub struct RateCacheBuilder {
+    source: Arc<dyn RateSource>,
+    policies: HashMap<CurrencyPair, CachePolicy>,
+    default_policy: CachePolicy,
+}
+
+impl RateCacheBuilder {
+    pub fn new(source: Arc<dyn RateSource>) -> Self {
+        Self {
+            source,
+            policies: HashMap::new(),
+            default_policy: CachePolicy::default(),
+        }
+    }
+
+    /// The policy of pairs without their own. Defaults to `CachePolicy::default()`.
+    pub fn default_policy(mut self, policy: CachePolicy) -> Self {
+        self.default_policy = policy;
+        self
+    }
+
+    /// The policy of one pair.
+    pub fn policy(mut self, pair: CurrencyPair, policy: CachePolicy) -> Self {
+        self.policies.insert(pair, policy);
+        self
+    }
+
+    pub fn build(self) -> RateCache {
+        RateCache {
+            source: self.source,
+            policies: Arc::new(self.policies),
+            default_policy: self.default_policy,
+            slots: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+/// Fetches a quote and checks it is for the requested pair.
+async fn fetch(source: &dyn RateSource, pair: &CurrencyPair) -> Result<Quote, Error> {
+    let quote = source.fetch(pair).await?;
+    if quote.pair() != pair {
+        return Err(Error::new(
+            "Rate source returned another pair",
+            ErrorCode::Unprocessable,
+        )
+        .with_meta(
+            ErrorMeta::new()
+                .add("expected", pair.to_string().as_str())
+                .add("actual", quote.pair().to_string().as_str())
+                .build(),
+        ));
+    }
+    Ok(quote)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::money::Money;
+    use crate::test_support::pair;
+
+    /// A source whose quotes are priced by the number of requests made so far, and that fails
+    /// while `failing` is set.
+    struct Counting {
+        requests: Arc<AtomicUsize>,
+        failing: Arc<AtomicBool>,
+        delay: Duration,
+    }
+
+    impl Counting {
+        fn new(delay: Duration) -> Self {
+            Self {
+                requests: Arc::new(AtomicUsize::new(0)),
+                failing: Arc::new(AtomicBool::new(false)),
+                delay,
+            }
+        }
+
+        fn source(&self) -> impl RateSource + 'static {
+            let requests = self.requests.clone();
+            let failing = self.failing.clone();
+            let delay = self.delay;
+            move |pair: CurrencyPair| {
+                let requests = requests.clone();
+                let failing = failing.clone();
+                async move {
+                    let count = requests.fetch_add(1, Ordering::SeqCst) as i64 + 1;
+                    tokio::time::sleep(delay).await;
+                    if failing.load(Ordering::SeqCst) {
+                        return Err(Error::new("Upstream down", ErrorCode::Internal));
+                    }
+                    let price = Money::from_minor(count, pair.quote().clone());
+                    Ok(Quote::new("test", pair, price, Utc::now()))
+                }
+            }
+        }
+
+        fn requests(&self) -> usize {
+            self.requests.load(Ordering::SeqCst)
+        }
+    }
+
+    fn policy(ttl_ms: u64, max_staleness_ms: u64) -> CachePolicy {
+        CachePolicy::new(
+            Duration::from_millis(ttl_ms),
+            Duration::from_millis(max_staleness_ms),
+        )
+        .unwrap()
+    }
+
+    #[macros::async_test]
+    async fn test_serves_fresh_quotes_from_cache() {
+        let upstream = Counting::new(Duration::ZERO);
+        let cache = RateCache::new(upstream.source()).build();
+
+        let first = cache.get(&pair()).await.unwrap();
+        let second = cache.get(&pair()).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(upstream.requests(), 1);
+
+        cache.invalidate(&pair());
+        assert_eq!(cache.get(&pair()).await.unwrap().price().amount(), 2);
+    }
+
+    #[macros::async_test(start_paused)]
+    async fn test_serves_stale_while_revalidating() {
+        let upstream = Counting::new(Duration::ZERO);
+        let cache = RateCache::new(upstream.source())
+            .default_policy(policy(20, 10_000))
+            .build();
+
+        assert_eq!(cache.get(&pair()).await.unwrap().price().amount(), 1);
+        tokio::time::sleep(Duration::from_millis(40)).await;
+
+        // The stale quote comes back at once while the refresh runs.
+        assert_eq!(cache.get(&pair()).await.unwrap().price().amount(), 1);
+        tokio::time::sleep(Duration::from_millis(15)).await;
+        assert_eq!(cache.get(&pair()).await.unwrap().price().amount(), 2);
+        assert_eq!(upstream.requests(), 2);
+    }
+
+    #[macros::async_test(start_paused)]
+    async fn test_rejects_quotes_past_max_staleness() {
+        let upstream = Counting::new(Duration::ZERO);
+        let cache = RateCache::new(upstream.source())
+            .policy(pair(), policy(10, 200))
+            .build();
+
+        cache.get(&pair()).await.unwrap();
+        upstream.failing.store(true, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        // Still within the maximum staleness; the failed refresh is not the caller's problem.
+        assert_eq!(cache.get(&pair()).await.unwrap().price().amount(), 1);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let error = cache.get(&pair()).await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Unavailable);
+        assert_eq!(error.meta_value("pair"), Some("BTC/USD"));
+        assert_eq!(error.meta_value("reason"), Some("Upstream down"));
+    }
+
+    #[macros::async_test(start_paused)]
+    async fn test_single_flight_refresh() {
+        let upstream = Counting::new(Duration::from_millis(30));
+        let cache = RateCache::new(upstream.source()).build();
+
+        let lookups: Vec<_> = (0..10)
+            .map(|_| {
+                let cache = cache.clone();
+                tokio::spawn(async move { cache.get(&pair()).await })
+            })
+            .collect();
+        for lookup in lookups {
+            assert_eq!(lookup.await.unwrap().unwrap().price().amount(), 1);
+        }
+        assert_eq!(upstream.requests(), 1);
+    }
+
+    #[macros::async_test(start_paused)]
+    async fn test_single_flight_failure() {
+        let upstream = Counting::new(Duration::from_millis(30));
+        upstream.failing.store(true, Ordering::SeqCst);
+        let cache = RateCache::new(upstream.source()).build();
+
+        let lookups: Vec<_> = (0..10)
+            .map(|_| {
+                let cache = cache.clone();
+                tokio::spawn(async move { cache.get(&pair()).await })
+            })
+            .collect();
+        for lookup in lookups {
+            let error = lookup.await.unwrap().unwrap_err();
+            assert_eq!(error.code(), ErrorCode::Unavailable);
+            assert_eq!(error.meta_value("reason"), Some("Upstream down"));
+        }
+        assert_eq!(upstream.requests(), 1);
+
+        // The next lookup tries again.
+        upstream.failing.store(false, Ordering::SeqCst);
+        assert_eq!(cache.get(&pair()).await.unwrap().price().amount(), 2);
+    }
+
+    #[test]
+    fn test_policies() {
+        let upstream = Counting::new(Duration::ZERO);
+        let eur = CurrencyPair::parse("BTC/EUR").unwrap();
+        let cache = RateCache::new(upstream.source())
+            .policy(eur.clone(), policy(5, 10))
+            .build();
+
+        assert_eq!(cache.policy(&eur), policy(5, 10));
+        assert_eq!(cache.policy(&pair()), CachePolicy::default());
+
+        let error = CachePolicy::new(Duration::from_secs(10), Duration::from_secs(5)).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Invalid);
+    }
+}
@@ -0,0 +1,25 @@
+//! Fixtures shared by the unit tests.
+
+use crate::currency::code::CurrencyCode;
+use crate::currency::{Currency, CurrencyPair};
+use crate::money::Money;
+
+/// BTC/USD, the pair most tests trade.
+pub(crate) fn pair() -> CurrencyPair {
+    CurrencyPair::parse("BTC/USD").unwrap()
+}
+
+/// The built-in currency with the given code.
+pub(crate) fn currency(code: CurrencyCode) -> Currency {
+    Currency::from(code)
+}
+
+/// Dollars written as a decimal, e.g. `usd("70000.50")`.
+pub(crate) fn usd(amount: &str) -> Money {
+    Money::parse(amount, currency(CurrencyCode::USD)).unwrap()
+}
+
+/// Bitcoin written as a decimal, e.g. `btc("0.5")`.
+pub(crate) fn btc(amount: &str) -> Money {
+    Money::parse(amount, currency(CurrencyCode::BTC)).unwrap()
+}
@@ -0,0 +1,59 @@
+use utils::json::JSON;
+
+// Several annotated types share this module, which the derives must not collide in.
+
+#[derive(Debug, PartialEq)]
+#[macros::json(rename_all = "camelCase", deny_unknown_fields)]
+struct Fill {
+    order_id: u64,
+    #[json(default)]
+    fee_paid: u64,
+    #[json(skip)]
+    cached: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+#[macros::json(tag = "type", rename_all = "snake_case")]
+enum Event {
+    Filled {
+        order_id: u64,
+    },
+    #[json(rename = "cancel")]
+    Cancelled {
+        order_id: u64,
+    },
+}
+
+#[test]
+fn test_container_options() {
+    let fill = Fill::from_json(r#"{"orderId": 7}"#).unwrap();
+    assert_eq!(
+        fill,
+        Fill {
+            order_id: 7,
+            fee_paid: 0,
+            cached: None
+        }
+    );
+    assert!(Fill::from_json(r#"{"orderId": 7, "extra": 1}"#).is_err());
+
+    let event = Event::Filled { order_id: 7 };
+    assert_eq!(
+        event.to_json().unwrap(),
+        r#"{"type":"filled","order_id":7}"#
+    );
+    assert_eq!(
+        Event::from_json(r#"{"type":"cancel","order_id":3}"#).unwrap(),
+        Event::Cancelled { order_id: 3 }
+    );
+}
+
+#[test]
+fn test_field_options() {
+    let fill = Fill {
+        order_id: 1,
+        fee_paid: 2,
+        cached: Some("ignored".into()),
+    };
+    assert_eq!(fill.to_json().unwrap(), r#"{"orderId":1,"feePaid":2}"#);
+}
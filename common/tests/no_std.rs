@@ -0,0 +1,25 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Builds `common` without the `std` feature, which leaves `currency`, `money` and `bitcoin` on
+/// `core` and `alloc` only.
+#[test]
+fn test_builds_without_std() {
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let target = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no_std");
+
+    let output = Command::new(env!("CARGO"))
+        .current_dir(&workspace)
+        .args(["build", "--quiet", "--offline", "-p", "common"])
+        .arg("--no-default-features")
+        .arg("--target-dir")
+        .arg(&target)
+        .output()
+        .expect("failed to run cargo");
+
+    assert!(
+        output.status.success(),
+        "no_std build failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,119 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::{ItemFn, LitInt, LitStr};
+
+#[derive(Default)]
+struct Options {
+    flavor: Option<TokenStream>,
+    worker_threads: Option<LitInt>,
+    log: Option<TokenStream>,
+    shutdown_timeout: Option<LitInt>,
+}
+
+impl Options {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("flavor") {
+            let flavor = meta.value()?.parse::<LitStr>()?;
+            let variant = match flavor.value().as_str() {
+                "current_thread" => format_ident!("CurrentThread"),
+                "multi_thread" => format_ident!("MultiThread"),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        flavor,
+                        "expected `current_thread` or `multi_thread`",
+                    ))
+                }
+            };
+            self.flavor = Some(quote!(::utils::runtime::Flavor::#variant));
+        } else if meta.path.is_ident("worker_threads") {
+            self.worker_threads = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("log") {
+            let level = meta.value()?.parse::<LitStr>()?;
+            let variant = match level.value().as_str() {
+                "off" => format_ident!("Off"),
+                "error" => format_ident!("Error"),
+                "warn" => format_ident!("Warn"),
+                "info" => format_ident!("Info"),
+                "debug" => format_ident!("Debug"),
+                "trace" => format_ident!("Trace"),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        level,
+                        "expected one of `off`, `error`, `warn`, `info`, `debug` or `trace`",
+                    ))
+                }
+            };
+            self.log = Some(quote!(::utils::runtime::LevelFilter::#variant));
+        } else if meta.path.is_ident("shutdown_timeout") {
+            self.shutdown_timeout = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error(
+                "unsupported async_main option, expected `flavor`, `worker_threads`, `log` or \
+                 `shutdown_timeout`",
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn expand(options: TokenStream, input: ItemFn) -> syn::Result<TokenStream> {
+    let mut parsed = Options::default();
+    let parser = syn::meta::parser(|meta| parsed.parse(meta));
+    syn::parse::Parser::parse2(parser, options)?;
+
+    if input.sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            input.sig.fn_token,
+            "async_main requires an `async fn`",
+        ));
+    }
+    if !input.sig.inputs.is_empty() || !input.sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.sig,
+            "async_main functions take no arguments or generics",
+        ));
+    }
+    if let (Some(worker_threads), Some(flavor)) = (&parsed.worker_threads, &parsed.flavor) {
+        if flavor.to_string().ends_with("CurrentThread") {
+            return Err(syn::Error::new_spanned(
+                worker_threads,
+                "`worker_threads` needs the `multi_thread` flavor",
+            ));
+        }
+    }
+
+    let mut settings = Vec::new();
+    if let Some(flavor) = parsed.flavor {
+        settings.push(quote!(.flavor(#flavor)));
+    }
+    if let Some(worker_threads) = parsed.worker_threads {
+        settings.push(quote!(.worker_threads(#worker_threads)));
+    }
+    if let Some(log) = parsed.log {
+        settings.push(quote!(.log_level(#log)));
+    }
+    if let Some(seconds) = parsed.shutdown_timeout {
+        settings.push(quote!(.shutdown_timeout(::std::time::Duration::from_secs(#seconds))));
+    }
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = input;
+    let name = &sig.ident;
+    let output = &sig.output;
+    Ok(quote! {
+        #(#attrs)*
+        #vis fn #name() -> ::std::process::ExitCode {
+            async fn entry() #output #block
+
+            ::utils::runtime::Runtime::new()
+                #(#settings)*
+                .build()
+                .run(entry())
+        }
+    })
+}
@@ -0,0 +1,125 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::{FnArg, Ident, ItemFn, LitInt};
+
+#[derive(Default)]
+struct Options {
+    timeout: Option<TokenStream>,
+    start_paused: Option<Ident>,
+}
+
+impl Options {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("timeout") {
+            let seconds = meta.value()?.parse::<LitInt>()?;
+            self.timeout = Some(quote!(::std::time::Duration::from_secs(#seconds)));
+        } else if meta.path.is_ident("timeout_ms") {
+            let millis = meta.value()?.parse::<LitInt>()?;
+            self.timeout = Some(quote!(::std::time::Duration::from_millis(#millis)));
+        } else if meta.path.is_ident("start_paused") {
+            self.start_paused = meta.path.get_ident().cloned();
+        } else {
+            return Err(meta.error(
+                "unsupported async_test option, expected `timeout`, `timeout_ms` or \
+                 `start_paused`",
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn expand(options: TokenStream, input: ItemFn) -> syn::Result<TokenStream> {
+    let mut parsed = Options::default();
+    let parser = syn::meta::parser(|meta| parsed.parse(meta));
+    syn::parse::Parser::parse2(parser, options)?;
+
+    if input.sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            input.sig.fn_token,
+            "async_test requires an `async fn`",
+        ));
+    }
+
+    // Paused time jumps ahead whenever the runtime is idle, which includes waiting on real
+    // sockets: a timeout would fire before a fixture's server could answer.
+    if let Some(start_paused) = &parsed.start_paused {
+        if parsed.timeout.is_some() || !input.sig.inputs.is_empty() {
+            return Err(syn::Error::new_spanned(
+                start_paused,
+                "`start_paused` cannot be combined with a timeout or fixtures, paused time \
+                 advances while the test waits on I/O",
+            ));
+        }
+    }
+
+    let mut fixtures = Vec::new();
+    let mut setup = Vec::new();
+    for (i, input) in input.sig.inputs.iter().enumerate() {
+        let FnArg::Typed(argument) = input else {
+            return Err(syn::Error::new_spanned(input, "tests cannot take `self`"));
+        };
+        let fixture = format_ident!("fixture{}", i);
+        let ty = &argument.ty;
+        setup.push(quote! {
+            let #fixture = <#ty as ::utils::testing::Fixture>::setup().await?;
+        });
+        fixtures.push(fixture);
+    }
+
+    let runtime = if parsed.start_paused.is_some() {
+        quote!(#[::tokio::test(start_paused = true)])
+    } else {
+        quote!(#[::tokio::test])
+    };
+    let timeout = match parsed.timeout {
+        Some(timeout) => quote!(::core::option::Option::Some(#timeout)),
+        None => quote!(::core::option::Option::None),
+    };
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = input;
+    let name = &sig.ident;
+    let inputs = &sig.inputs;
+    let output = &sig.output;
+    Ok(quote! {
+        #(#attrs)*
+        #runtime
+        #vis async fn #name() -> ::core::result::Result<(), ::utils::errors::Error> {
+            async fn body(#inputs) #output #block
+
+            #(#setup)*
+            ::utils::testing::run(::core::stringify!(#name), #timeout, body(#(#fixtures),*)).await
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_test(options: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
+        expand(options, syn::parse2(input).unwrap())
+    }
+
+    #[test]
+    fn test_rejects_paused_time_with_io() {
+        let sleeper = quote!(
+            async fn test_sleep() {}
+        );
+        assert!(expand_test(quote!(start_paused), sleeper.clone()).is_ok());
+        assert!(expand_test(quote!(timeout = 5), sleeper.clone()).is_ok());
+
+        let error = expand_test(quote!(start_paused, timeout = 5), sleeper).unwrap_err();
+        assert!(error.to_string().contains("start_paused"));
+
+        let fixture = quote!(
+            async fn test_server(server: MockServer) {}
+        );
+        assert!(expand_test(quote!(start_paused), fixture).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    Data, DeriveInput, Expr, Field, Fields, GenericArgument, LitStr, Path, PathArguments, Type,
+};
+
+/// How a field is filled in when its setter was not called.
+enum Kind {
+    /// `build()` fails and names the field.
+    Required,
+    /// An `Option<T>` field, left `None`. The setter takes the `T`.
+    Optional,
+    /// `#[builder(default)]` or `#[builder(default = expr)]`.
+    Default(Option<Expr>),
+}
+
+struct BuilderField<'a> {
+    field: &'a Field,
+    kind: Kind,
+    /// The type the setter stores, which is `T` for an `Option<T>` field.
+    ty: &'a Type,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Builder can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Builder can only be derived for structs",
+            ))
+        }
+    };
+    let fields = fields
+        .iter()
+        .map(parse_field)
+        .collect::<syn::Result<Vec<_>>>()?;
+    let error = error_path(&input)?;
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let builder = format_ident!("{}Builder", name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let idents: Vec<_> = fields.iter().map(|f| &f.field.ident).collect();
+    let types: Vec<_> = fields.iter().map(|f| f.ty).collect();
+
+    let setters = fields.iter().map(|f| {
+        let ident = &f.field.ident;
+        let ty = f.ty;
+        let docs = f
+            .field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"));
+        quote! {
+            #(#docs)*
+            pub fn #ident(mut self, #ident: impl ::core::convert::Into<#ty>) -> Self {
+                self.#ident = ::core::option::Option::Some(#ident.into());
+                self
+            }
+        }
+    });
+
+    let required: Vec<_> = fields
+        .iter()
+        .filter(|f| matches!(f.kind, Kind::Required))
+        .map(|f| &f.field.ident)
+        .collect();
+    let values = fields.iter().map(|f| {
+        let ident = &f.field.ident;
+        match &f.kind {
+            Kind::Required => quote!(#ident),
+            Kind::Optional => quote!(self.#ident),
+            Kind::Default(None) => quote!(self.#ident.unwrap_or_default()),
+            Kind::Default(Some(expr)) => quote!(self.#ident.unwrap_or_else(|| #expr)),
+        }
+    });
+    let constructed = quote! {
+        ::core::result::Result::Ok(#name { #(#idents: #values,)* })
+    };
+
+    let body = if required.is_empty() {
+        constructed
+    } else {
+        let names: Vec<_> = required
+            .iter()
+            .map(|ident| ident.as_ref().map(|ident| ident.to_string()))
+            .collect();
+        // Bound apart from the locals below, which fields may share names with.
+        let unset: Vec<_> = required
+            .iter()
+            .filter_map(|ident| ident.as_ref())
+            .map(|ident| format_ident!("__{}", ident))
+            .collect();
+        let count = required.len();
+        quote! {
+            match (#(self.#required,)*) {
+                (#(::core::option::Option::Some(#required),)*) => #constructed,
+                (#(#unset,)*) => {
+                    let mut missing = [""; #count];
+                    let mut count = 0;
+                    let unset = [#(#unset.is_none().then_some(#names),)*];
+                    for name in unset.into_iter().flatten() {
+                        missing[count] = name;
+                        count += 1;
+                    }
+                    ::core::result::Result::Err(#error::missing_fields(
+                        ::core::stringify!(#name),
+                        &missing[..count],
+                    ))
+                }
+            }
+        }
+    };
+
+    let doc = format!("Builds a [`{}`] field by field.", name);
+    Ok(quote! {
+        #[doc = #doc]
+        #vis struct #builder #impl_generics #where_clause {
+            #(#idents: ::core::option::Option<#types>,)*
+        }
+
+        impl #impl_generics ::core::default::Default for #builder #ty_generics #where_clause {
+            fn default() -> Self {
+                Self { #(#idents: ::core::option::Option::None,)* }
+            }
+        }
+
+        impl #impl_generics #builder #ty_generics #where_clause {
+            pub fn new() -> Self {
+                ::core::default::Default::default()
+            }
+
+            #(#setters)*
+
+            /// Fails with `ErrorCode::Invalid`, naming the fields, if any required field is
+            /// not set.
+            pub fn build(self) -> ::core::result::Result<#name #ty_generics, #error> {
+                #body
+            }
+        }
+    })
+}
+
+/// The path of `utils::errors::Error`, under the crate named by `#[builder(crate = "...")]` if
+/// the `utils` crate is not reachable as `::utils`, e.g. from inside it.
+fn error_path(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let mut krate = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("builder"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                krate = Some(meta.value()?.parse::<LitStr>()?.parse::<Path>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported builder attribute, expected `crate`"))
+            }
+        })?;
+    }
+
+    Ok(match krate {
+        Some(krate) => quote!(#krate::errors::Error),
+        None => quote!(::utils::errors::Error),
+    })
+}
+
+fn parse_field(field: &Field) -> syn::Result<BuilderField<'_>> {
+    let mut default = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("builder"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                default = Some(match meta.value() {
+                    Ok(value) => Some(value.parse::<Expr>()?),
+                    Err(_) => None,
+                });
+                Ok(())
+            } else {
+                Err(meta.error("unsupported builder attribute, expected `default`"))
+            }
+        })?;
+    }
+
+    let (kind, ty) = match (default, option_inner(&field.ty)) {
+        (Some(expr), _) => (Kind::Default(expr), &field.ty),
+        (None, Some(inner)) => (Kind::Optional, inner),
+        (None, None) => (Kind::Required, &field.ty),
+    };
+    Ok(BuilderField { field, kind, ty })
+}
+
+/// The `T` of an `Option<T>` field type.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first() {
+        Some(GenericArgument::Type(inner)) if arguments.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
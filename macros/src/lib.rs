@@ -2,11 +2,15 @@ extern crate proc_macro;
 extern crate quote;
 extern crate syn;
 
+mod async_main;
+mod async_test;
+mod builder;
+mod str_enum;
+
 use proc_macro::TokenStream;
-use quote::__private::Span;
 use quote::quote;
 use syn::parse_macro_input;
-use syn::DeriveInput;
+use syn::{Attribute, Data, DeriveInput, Field, Meta, Variant};
 
 #[proc_macro_attribute]
 pub fn common(_metadata: TokenStream, input: TokenStream) -> TokenStream {
@@ -22,8 +26,7 @@ pub fn common(_metadata: TokenStream, input: TokenStream) -> TokenStream {
 pub fn serializeable(_metadata: TokenStream, input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let output = quote! {
-        use serde::Serialize;
-        #[derive(Serialize)]
+        #[derive(::serde::Serialize)]
         #input
     };
     output.into()
@@ -33,75 +36,179 @@ pub fn serializeable(_metadata: TokenStream, input: TokenStream) -> TokenStream
 pub fn deserializeable(_metadata: TokenStream, input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let output = quote! {
-        use serde::Deserialize;
-        #[derive(Deserialize)]
+        #[derive(::serde::Deserialize)]
         #input
     };
     output.into()
 }
 
+/// Derives `serde::Serialize` and `serde::Deserialize`.
+///
+/// Arguments are passed to serde as container attributes, e.g.
+/// `#[macros::json(rename_all = "camelCase", deny_unknown_fields)]` or
+/// `#[macros::json(tag = "type")]`. Fields and variants take serde's field and variant
+/// attributes as `#[json(...)]`, e.g. `#[json(skip)]` or `#[json(default, rename = "ts")]`.
 #[proc_macro_attribute]
-pub fn json(_metadata: TokenStream, input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
-    let generics = &input.generics;
-
-    let mut lifetime = None;
-    if let Some(lt) = generics.lifetimes().next() {
-        let lt_ident = &lt.lifetime.ident;
-        lifetime = Some(quote!(#lt_ident));
+pub fn json(metadata: TokenStream, input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    let options = proc_macro2::TokenStream::from(metadata);
+
+    match &mut input.data {
+        Data::Struct(data) => data.fields.iter_mut().for_each(json_attrs),
+        Data::Enum(data) => data.variants.iter_mut().for_each(|variant| {
+            json_attrs(variant);
+            variant.fields.iter_mut().for_each(json_attrs);
+        }),
+        Data::Union(_) => {}
     }
 
-    let output = if lifetime.is_some() {
-        quote! {
-            use serde::{Serialize, Deserialize};
+    let options = if options.is_empty() {
+        quote!()
+    } else {
+        quote!(#[serde(#options)])
+    };
+    let output = quote! {
+        #[derive(::serde::Serialize, ::serde::Deserialize)]
+        #options
+        #input
+    };
+    output.into()
+}
 
-            #[derive(Serialize, Deserialize)]
-            #input
+/// Rewrites `#[json(...)]` on a field or variant to the `#[serde(...)]` it stands for.
+fn json_attrs(item: &mut impl HasAttributes) {
+    for attr in item.attrs_mut() {
+        if let Meta::List(list) = &mut attr.meta {
+            if list.path.is_ident("json") {
+                list.path = syn::parse_quote!(serde);
+            }
         }
-    } else {
-        let lifetime_ident = syn::Lifetime::new("'a", Span::call_site());
+    }
+}
 
-        let mut generics = generics.clone();
-        generics.params.insert(
-            0,
-            syn::GenericParam::Lifetime(syn::LifetimeParam::new(lifetime_ident.clone())),
-        );
+trait HasAttributes {
+    fn attrs_mut(&mut self) -> &mut Vec<Attribute>;
+}
 
-        let input_with_lifetime = quote! {
-            use serde::{Serialize, Deserialize};
+impl HasAttributes for Field {
+    fn attrs_mut(&mut self) -> &mut Vec<Attribute> {
+        &mut self.attrs
+    }
+}
 
-            #[derive(Serialize, Deserialize)]
-            #input
-        };
+impl HasAttributes for Variant {
+    fn attrs_mut(&mut self) -> &mut Vec<Attribute> {
+        &mut self.attrs
+    }
+}
 
-        quote! {
-            #input_with_lifetime
-        }
-    };
+/// Derives a `<Name>Builder` with a setter per field taking anything `Into` the field's type,
+/// and a `build()` returning `Result<Name, utils::errors::Error>`.
+///
+/// Fields are required unless they are an `Option<T>`, whose setter takes the `T`, or are marked
+/// `#[builder(default)]` (`Default::default()`) or `#[builder(default = expr)]`. Building without
+/// a required field fails with `ErrorCode::Invalid` naming every missing field.
+///
+/// The builder refers to `::utils::errors::Error`. Where `utils` goes by another path, such as
+/// `crate` inside `utils` itself, name it with `#[builder(crate = "...")]` on the struct.
+///
+/// ```ignore
+/// #[derive(macros::Builder)]
+/// pub struct Quote {
+///     pair: CurrencyPair,
+///     price: Money,
+///     source: Option<Box<str>>,
+///     #[builder(default = 3)]
+///     retries: u32,
+/// }
+///
+/// let quote = QuoteBuilder::new().pair(pair).price(price).build()?;
+/// ```
+#[proc_macro_derive(Builder, attributes(builder))]
+pub fn derive_builder(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    builder::expand(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
 
-    output.into()
+/// Derives the mapping between an enum and the strings its variants are written as: `as_str`,
+/// `FromStr`, `TryFrom<&str>`, `Display`, an `ALL` array of the unit variants and
+/// `from_variant`, which matches the unit variants only.
+///
+/// Unit variants are written as their name unless renamed with `#[str_enum(rename = "...")]`,
+/// and also parse from every `#[str_enum(alias = "...")]`. `#[str_enum(case_insensitive)]` on
+/// the enum matches names and aliases regardless of ASCII case. One single-field variant may
+/// take the strings no other variant matches, given `#[str_enum(parse = ..., as_str = ...)]`
+/// expressions mapping `&str` to `Option` of the field and the field to a `&'static str`. The
+/// `parse` expression does its own matching, so it ignores case itself if the enum does.
+///
+/// Parsing an unknown string fails with `ErrorCode::Invalid`.
+///
+/// ```ignore
+/// #[derive(macros::StrEnum)]
+/// #[str_enum(case_insensitive)]
+/// pub enum Side {
+///     #[str_enum(alias = "bid")]
+///     Buy,
+///     #[str_enum(alias = "ask")]
+///     Sell,
+/// }
+///
+/// assert_eq!("BID".parse::<Side>()?, Side::Buy);
+/// assert_eq!(Side::Sell.as_str(), "Sell");
+/// ```
+#[proc_macro_derive(StrEnum, attributes(str_enum))]
+pub fn derive_str_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    str_enum::expand(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
 
+/// Runs an `async fn main` on `utils::runtime::Runtime`, which logs to stderr, reports panics,
+/// shuts down gracefully on Ctrl-C and exits with the status of a returned error's `ErrorCode`.
+/// Programs that run until interrupted wait on `utils::runtime::shutdown()` to stop.
+///
+/// Options: `flavor = "current_thread"` or `"multi_thread"` (the default), `worker_threads = 4`,
+/// `log = "debug"` for the level used when `RUST_LOG` is not set, and `shutdown_timeout = 10`
+/// for the seconds allowed after Ctrl-C.
+///
+/// ```ignore
+/// #[macros::async_main(worker_threads = 4, log = "warn")]
+/// async fn main() -> Result<(), utils::errors::Error> {
+///     Ok(())
+/// }
+/// ```
 #[proc_macro_attribute]
-pub fn async_main(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn async_main(attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(item as syn::ItemFn);
-
-    let expanded = quote! {
-        #[tokio::main]
-        #input
-    };
-
-    TokenStream::from(expanded)
+    async_main::expand(attr.into(), input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
 
+/// Runs an `async fn` test on Tokio's test runtime.
+///
+/// Options: `timeout = 5` (seconds) or `timeout_ms = 500` fail the test with an
+/// `ErrorCode::Timeout` error if it runs longer, and `start_paused` starts with Tokio's clock
+/// paused, so sleeps and timeouts advance virtual time instantly once the test is idle. Paused
+/// time needs Tokio's `test-util` feature. Waiting on a socket also counts as idle, so
+/// `start_paused` is rejected together with a timeout or fixtures.
+///
+/// Arguments are fixtures: each is created with `utils::testing::Fixture::setup` before the test
+/// runs, e.g. a `MockServer` to point HTTP requests at or a fresh `HttpClientPool`.
+///
+/// ```ignore
+/// #[macros::async_test(timeout = 5)]
+/// async fn test_ticker(server: MockServer, pool: Arc<Mutex<HttpClientPool>>) {
+///     server.mock(HttpMethod::GET, "/ticker", 200, r#"{"price":"30000"}"#);
+/// }
+/// ```
 #[proc_macro_attribute]
-pub fn async_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn async_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(item as syn::ItemFn);
-
-    let expanded = quote! {
-        #[tokio::test]
-        #input
-    };
-
-    TokenStream::from(expanded)
+    async_test::expand(attr.into(), input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
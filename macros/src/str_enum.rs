@@ -0,0 +1,214 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Expr, Fields, LitStr, Variant};
+
+/// A unit variant and the strings it is written as.
+struct Named<'a> {
+    variant: &'a Variant,
+    name: String,
+    aliases: Vec<String>,
+}
+
+/// The single-field variant that takes every string no named variant matches.
+struct Fallback<'a> {
+    variant: &'a Variant,
+    parse: Expr,
+    as_str: Expr,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "StrEnum can only be derived for enums",
+        ));
+    };
+
+    let mut case_insensitive = false;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("str_enum"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("case_insensitive") {
+                case_insensitive = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported str_enum attribute, expected `case_insensitive`"))
+            }
+        })?;
+    }
+
+    let mut named = Vec::new();
+    let mut fallback = None;
+    for variant in &data.variants {
+        match &variant.fields {
+            Fields::Unit => named.push(parse_named(variant)?),
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 && fallback.is_none() => {
+                fallback = Some(parse_fallback(variant)?)
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "StrEnum variants must be unit variants, apart from one single-field \
+                     fallback variant",
+                ))
+            }
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let count = named.len();
+    let variants: Vec<_> = named.iter().map(|n| &n.variant.ident).collect();
+    let names: Vec<_> = named.iter().map(|n| &n.name).collect();
+
+    let matches = named.iter().map(|n| {
+        let ident = &n.variant.ident;
+        let strings = std::iter::once(&n.name).chain(&n.aliases).map(|string| {
+            if case_insensitive {
+                quote!(value.eq_ignore_ascii_case(#string))
+            } else {
+                quote!(value == #string)
+            }
+        });
+        quote! {
+            if #(#strings)||* {
+                return ::core::option::Option::Some(Self::#ident);
+            }
+        }
+    });
+
+    let (fallback_as_str, fallback_parse) = match &fallback {
+        Some(Fallback {
+            variant,
+            parse,
+            as_str,
+        }) => {
+            let ident = &variant.ident;
+            (
+                quote!(Self::#ident(inner) => (#as_str)(inner),),
+                quote!((#parse)(value).map(Self::#ident)),
+            )
+        }
+        None => (quote!(), quote!(::core::option::Option::None)),
+    };
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Every unit variant, in declaration order.
+            pub const ALL: [Self; #count] = [#(Self::#variants,)*];
+
+            /// The string the value is written as.
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    #(Self::#variants => #names,)*
+                    #fallback_as_str
+                }
+            }
+
+            /// The unit variant written as `value`, by its name or one of its aliases.
+            pub fn from_variant(value: &str) -> ::core::option::Option<Self> {
+                #(#matches)*
+                ::core::option::Option::None
+            }
+        }
+
+        impl #impl_generics ::core::str::FromStr for #name #ty_generics #where_clause {
+            type Err = ::utils::errors::Error;
+
+            /// Fails with `ErrorCode::Invalid` if no variant is written as `value`.
+            fn from_str(value: &str) -> ::core::result::Result<Self, Self::Err> {
+                Self::from_variant(value)
+                    .or_else(|| #fallback_parse)
+                    .ok_or_else(|| {
+                        ::utils::errors::Error::new(
+                            "Unknown value",
+                            ::utils::errors::ErrorCode::Invalid,
+                        )
+                        .with_meta(
+                            ::utils::errors::ErrorMeta::new()
+                                .add("type", ::core::stringify!(#name))
+                                .add("value", value)
+                                .build(),
+                        )
+                    })
+            }
+        }
+
+        impl #impl_generics ::core::convert::TryFrom<&str> for #name #ty_generics #where_clause {
+            type Error = ::utils::errors::Error;
+
+            fn try_from(value: &str) -> ::core::result::Result<Self, Self::Error> {
+                ::core::str::FromStr::from_str(value)
+            }
+        }
+
+        impl #impl_generics ::core::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    })
+}
+
+fn parse_named(variant: &Variant) -> syn::Result<Named<'_>> {
+    let mut name = variant.ident.to_string();
+    let mut aliases = Vec::new();
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("str_enum"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("alias") {
+                aliases.push(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported str_enum attribute, expected `rename` or `alias`"))
+            }
+        })?;
+    }
+    Ok(Named {
+        variant,
+        name,
+        aliases,
+    })
+}
+
+fn parse_fallback(variant: &Variant) -> syn::Result<Fallback<'_>> {
+    let mut parse = None;
+    let mut as_str = None;
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("str_enum"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("parse") {
+                parse = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else if meta.path.is_ident("as_str") {
+                as_str = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported str_enum attribute, expected `parse` or `as_str`"))
+            }
+        })?;
+    }
+    match (parse, as_str) {
+        (Some(parse), Some(as_str)) => Ok(Fallback {
+            variant,
+            parse,
+            as_str,
+        }),
+        _ => Err(syn::Error::new_spanned(
+            variant,
+            "a fallback variant needs `#[str_enum(parse = ..., as_str = ...)]`",
+        )),
+    }
+}
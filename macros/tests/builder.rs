@@ -0,0 +1,70 @@
+use utils::errors::ErrorCode;
+
+#[derive(Debug, macros::Builder)]
+struct Options {
+    label: Option<String>,
+    #[builder(default)]
+    count: u32,
+    #[builder(default = 3)]
+    retries: u32,
+}
+
+#[test]
+fn test_defaults() {
+    let options = OptionsBuilder::new().build().unwrap();
+    assert_eq!(options.label, None);
+    assert_eq!((options.count, options.retries), (0, 3));
+
+    let options = OptionsBuilder::new()
+        .label("spot")
+        .retries(1u32)
+        .build()
+        .unwrap();
+    assert_eq!(options.label.as_deref(), Some("spot"));
+    assert_eq!(options.retries, 1);
+}
+
+// Required fields named like the locals of the generated `build`.
+#[derive(Debug, macros::Builder)]
+struct Tally {
+    count: u32,
+    missing: Vec<String>,
+    unset: bool,
+}
+
+#[test]
+fn test_required_fields() {
+    let error = TallyBuilder::new().count(2u32).build().unwrap_err();
+    assert_eq!(error.code(), ErrorCode::Invalid);
+    assert_eq!(error.meta_value("type"), Some("Tally"));
+    assert_eq!(error.meta_value("fields"), Some("missing, unset"));
+
+    let tally = TallyBuilder::new()
+        .count(2u32)
+        .missing(vec!["price".to_string()])
+        .unset(false)
+        .build()
+        .unwrap();
+    assert_eq!(
+        (tally.count, tally.missing.len(), tally.unset),
+        (2, 1, false)
+    );
+}
+
+mod renamed {
+    pub use utils::*;
+}
+
+#[derive(Debug, macros::Builder)]
+#[builder(crate = "renamed")]
+struct Renamed {
+    label: String,
+}
+
+#[test]
+fn test_crate_path() {
+    let error = RenamedBuilder::new().build().unwrap_err();
+    assert_eq!(error.meta_value("fields"), Some("label"));
+    let renamed = RenamedBuilder::new().label("spot").build().unwrap();
+    assert_eq!(renamed.label, "spot");
+}
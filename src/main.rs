@@ -1,59 +1,104 @@
 use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc;
+use std::time::Duration;
 
+use common::arbitrage::{self, ArbitrageMonitor, VenueFees};
+use common::currency::{token, CurrencyPair};
+use common::oracle::{OraclePrice, PriceOracle};
+use common::providers::ProviderRegistry;
+use tokio::sync::{broadcast, mpsc};
 use utils::adapters::http_client::*;
-use utils::errors::{Error, ErrorCode};
+use utils::errors::Error;
 use utils::json::JSON;
-use utils::*;
 
 const NUM_THREADS: usize = 10;
-const BASE_URL: &str = "https://webhook.site";
-const PATH: &str = "27acc74f-7f52-4cfc-abff-9ff4c3af5ca7";
+const PAIR: &str = "BTC/USD";
+const POLL_INTERVAL_SECONDS: u64 = 15;
+const POLL_TIMEOUT_SECONDS: u64 = 10;
+const ARBITRAGE_THRESHOLD: f64 = 0.002;
+const TAKER_FEES: [(&str, f64); 2] = [("coinbase", 0.006), ("kraken", 0.0026)];
+/// Path of an optional JSON file declaring tokens beyond the built-in currencies.
+const TOKENS_CONFIG_VAR: &str = "ADJUSTMENT_TOKENS";
 
 #[macros::async_main]
 pub async fn main() -> Result<(), Error> {
-    let url = url!(BASE_URL, PATH);
-    let headers = http_headers! {
-        "Content-Type" => "application/json",
-    };
-    let request = Arc::new(http_request!(GET, &url, headers));
+    if let Ok(path) = std::env::var(TOKENS_CONFIG_VAR) {
+        token::load_file(path)?;
+    }
+
+    let pair = CurrencyPair::parse(PAIR)?;
+    let registry = ProviderRegistry::with_defaults(&pair);
 
     let client_pool = Arc::new(Mutex::new(HttpClientPool::with_capacity(NUM_THREADS)));
-    let (tx, mut rx) = mpsc::channel::<Result<HttpResponse, Error>>(NUM_THREADS);
-
-    for _ in 0..NUM_THREADS {
-        let pool = client_pool.clone();
-        let request = request.clone();
-        let tx = tx.clone();
-
-        spawn!(async move {
-            let response = send_request!(pool, request).await;
-            if let Err(err) = tx.send(response).await {
-                return Err(Error::new(
-                    format!("Failed to send response: {}", err).as_str(),
-                    ErrorCode::Internal,
-                )
-                .with_cause(err));
-            }
-
-            Ok::<(), Error>(())
-        });
+
+    // Only venues with known fees are traded on; aggregators such as CoinGecko quote prices
+    // nobody can fill, and without fees every spread to them would look like a profit.
+    let mut monitor = ArbitrageMonitor::new(pair.clone()).threshold(ARBITRAGE_THRESHOLD);
+    for (venue, taker) in TAKER_FEES {
+        monitor = monitor.venue(venue, VenueFees::new(taker));
     }
+    let monitor = monitor.build()?;
+    let mut opportunities = monitor.subscribe();
+    let venues = registry
+        .for_pair(&pair)
+        .into_iter()
+        .filter(|provider| {
+            TAKER_FEES
+                .iter()
+                .any(|(venue, _)| *venue == provider.name())
+        })
+        .collect();
+
+    let (quotes, receiver) = mpsc::channel(NUM_THREADS);
+    tokio::spawn(monitor.run(receiver));
+    tokio::spawn(arbitrage::poll(
+        client_pool.clone(),
+        venues,
+        Duration::from_secs(POLL_INTERVAL_SECONDS),
+        Duration::from_secs(POLL_TIMEOUT_SECONDS),
+        quotes,
+    ));
 
-    drop(tx);
-
-    while let Some(result) = rx.recv().await {
-        match result {
-            Ok(response) => {
-                let json = &response.to_json().unwrap();
-                println!("{:?}", json)
-            }
-            Err(err) => {
-                println!("{:?}", err);
-                return Err(err);
-            }
+    let oracle = PriceOracle::new(pair.clone(), client_pool)
+        .providers(registry.for_pair(&pair))
+        .build();
+    match oracle.query().await {
+        Ok(price) => report(&price)?,
+        // The monitor keeps running on its own quotes.
+        Err(error) => eprintln!("price oracle failed: {}", error),
+    }
+
+    loop {
+        tokio::select! {
+            _ = utils::runtime::shutdown() => break,
+            opportunity = opportunities.recv() => match opportunity {
+                Ok(opportunity) => println!("{}", opportunity),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    println!("missed {} arbitrage opportunities", missed)
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
         }
     }
 
     Ok(())
 }
+
+fn report(price: &OraclePrice) -> Result<(), Error> {
+    for quote in price.sources() {
+        println!("{}", quote.to_json()?);
+    }
+    for (source, rejection) in price.rejected() {
+        println!("{}: rejected ({})", source, rejection);
+    }
+    for (source, message) in price.failures() {
+        println!("{}: failed ({})", source, message);
+    }
+
+    println!(
+        "{} {} (confidence {:.2})",
+        price.pair(),
+        price.price(),
+        price.confidence()
+    );
+    Ok(())
+}
@@ -219,7 +219,7 @@ impl HttpClient {
         let status_code = response.status().as_u16();
 
         let headers = response.headers().to_owned();
-        let headers = if headers.len() == 0 {
+        let headers = if headers.is_empty() {
             None
         } else {
             Some(
@@ -258,6 +258,12 @@ pub struct HttpClientBuilder {
     timeout: Option<Duration>,
 }
 
+impl Default for HttpClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HttpClientBuilder {
     pub fn new() -> Self {
         Self { timeout: None }
@@ -322,6 +328,12 @@ pub struct HttpClientPool {
     borrowed: Arc<RwLock<HashSet<usize>>>,
 }
 
+impl Default for HttpClientPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HttpClientPool {
     /// Creates a new `HttpClientPool` with the default pool size.
     pub fn new() -> Self {
@@ -372,7 +384,7 @@ impl HttpClientPool {
     /// ```
     ///
     ///
-    pub fn borrow_client<'a>(&'a mut self) -> Result<Arc<HttpClient>, Error> {
+    pub fn borrow_client(&mut self) -> Result<Arc<HttpClient>, Error> {
         let mut borrowed_set = self.borrowed.try_write().map_err(|err| {
             let poisoned_err = err.to_string();
             Error::new(
@@ -111,14 +111,22 @@
 //! ```
 
 use serde::{ser::SerializeStruct, Deserialize, Serialize};
-use thiserror::Error;
 
-use std::{
-    collections::HashMap,
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+};
+use core::{
     error::Error as StdError,
     fmt::{Display, Formatter},
 };
 
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as HashMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ErrorCode {
     /// The provided input is invalid.
@@ -148,7 +156,7 @@ pub enum ErrorCode {
 }
 
 impl Display for ErrorCode {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
             ErrorCode::Invalid => write!(f, "invalid"),
             ErrorCode::NotFound => write!(f, "not_found"),
@@ -166,6 +174,31 @@ impl Display for ErrorCode {
     }
 }
 
+impl ErrorCode {
+    /// The process exit status for an error with this code, following the BSD `sysexits.h`
+    /// conventions.
+    ///
+    /// # Example
+    /// ```
+    /// use utils::errors::ErrorCode;
+    /// assert_eq!(ErrorCode::NotFound.exit_code(), 66);
+    /// ```
+    ///
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ErrorCode::Invalid | ErrorCode::Unprocessable | ErrorCode::JsonParse => 65,
+            ErrorCode::NotFound => 66,
+            ErrorCode::Unavailable => 69,
+            ErrorCode::Internal | ErrorCode::JsonSerialize => 70,
+            ErrorCode::Conflict => 73,
+            ErrorCode::Timeout => 75,
+            ErrorCode::Unauthorized | ErrorCode::Forbidden => 77,
+            ErrorCode::Unknown => 1,
+        }
+    }
+}
+
+#[derive(Default)]
 pub struct ErrorMeta(HashMap<Box<str>, Box<str>>);
 
 impl ErrorMeta {
@@ -188,21 +221,15 @@ impl ErrorMeta {
     }
 }
 
-impl Default for ErrorMeta {
-    fn default() -> Self {
-        ErrorMeta(HashMap::new())
-    }
-}
-
 impl From<HashMap<Box<str>, Box<str>>> for ErrorMeta {
     fn from(meta: HashMap<Box<str>, Box<str>>) -> Self {
         ErrorMeta(meta)
     }
 }
 
-impl Into<HashMap<Box<str>, Box<str>>> for ErrorMeta {
-    fn into(self) -> HashMap<Box<str>, Box<str>> {
-        self.0
+impl From<ErrorMeta> for HashMap<Box<str>, Box<str>> {
+    fn from(meta: ErrorMeta) -> Self {
+        meta.0
     }
 }
 
@@ -249,7 +276,7 @@ impl ErrorMetaBuilder {
     /// ```
     ///
     pub fn build(&mut self) -> HashMap<Box<str>, Box<str>> {
-        std::mem::take(&mut self.0)
+        core::mem::take(&mut self.0)
     }
 }
 
@@ -266,7 +293,7 @@ impl ErrorMetaBuilder {
 /// assert_eq!(&error.code(), &ErrorCode::Internal);
 /// ```
 ///
-#[derive(Debug, Error)]
+#[derive(Debug)]
 pub struct Error {
     message: Box<str>,
     code: ErrorCode,
@@ -334,6 +361,31 @@ impl Error {
         }
     }
 
+    /// Creates an `ErrorCode::Invalid` error for a builder that was built without some of
+    /// its required fields. This is what `#[derive(macros::Builder)]` builders return.
+    ///
+    /// # Arguments
+    ///
+    /// * `type_name` - The type being built.
+    /// * `fields` - The names of the fields that were not set.
+    ///
+    /// # Example
+    /// ```
+    /// use utils::errors::{Error, ErrorCode};
+    /// let error = Error::missing_fields("Bitcoin", &["name", "price"]);
+    /// assert_eq!(error.code(), ErrorCode::Invalid);
+    /// assert_eq!(error.meta_value("fields"), Some("name, price"));
+    /// ```
+    ///
+    pub fn missing_fields(type_name: &str, fields: &[&str]) -> Error {
+        Error::new("Missing required fields", ErrorCode::Invalid).with_meta(
+            ErrorMeta::new()
+                .add("type", type_name)
+                .add("fields", &fields.join(", "))
+                .build(),
+        )
+    }
+
     /// Attaches a cause to the error.
     ///
     /// # Arguments
@@ -489,7 +541,7 @@ impl Error {
     /// assert_eq!(error.source().unwrap().to_string(), "io error");
     /// ```
     ///
-    pub fn source(&self) -> Option<&(dyn std::error::Error + Send + Sync + 'static)> {
+    pub fn source(&self) -> Option<&(dyn StdError + Send + Sync + 'static)> {
         self.source.as_ref().map(|e| e.as_ref())
     }
 
@@ -538,7 +590,7 @@ impl Error {
 }
 
 impl Display for Error {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "App Error: {}", self.message)
     }
 }
@@ -557,6 +609,14 @@ impl Serialize for Error {
     }
 }
 
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn StdError + 'static))
+    }
+}
+
 impl AsRef<Self> for Error {
     fn as_ref(&self) -> &Self {
         self
@@ -578,13 +638,13 @@ impl<'de> Deserialize<'de> for Error {
         }
 
         struct AppErrorVisitor<'a> {
-            marker: std::marker::PhantomData<&'a ()>,
+            marker: core::marker::PhantomData<&'a ()>,
         }
 
         impl<'de> serde::de::Visitor<'de> for AppErrorVisitor<'_> {
             type Value = Error;
 
-            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+            fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
                 formatter.write_str("struct Error")
             }
 
@@ -673,7 +733,7 @@ impl<'de> Deserialize<'de> for Error {
             "Error",
             FIELDS,
             AppErrorVisitor {
-                marker: std::marker::PhantomData,
+                marker: core::marker::PhantomData,
             },
         )
     }
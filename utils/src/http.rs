@@ -4,6 +4,7 @@ use crate::errors::Error;
 
 pub mod request;
 pub mod response;
+pub mod server;
 pub mod url;
 
 pub use request::{HttpMethod, HttpRequest, HttpRequestBuilder};
@@ -22,7 +23,7 @@ pub type HttpParams = Vec<(Box<str>, Box<str>)>;
 /// A result type for HTTP operations
 pub type HttpResult = Result<HttpResponse, Error>;
 
-//// Creates a hashmap of parameters with the given key-value pairs.
+/// Creates a hashmap of parameters with the given key-value pairs.
 ///
 /// # Arguments
 ///
@@ -129,9 +129,7 @@ impl HttpRequestBuilder {
             let mut headers = self.headers.take().unwrap();
             let default_headers = Self::get_default_headers();
             for (k, v) in default_headers {
-                if !headers.contains_key(&k) {
-                    headers.insert(k, v);
-                }
+                headers.entry(k).or_insert(v);
             }
             self.headers = Some(headers);
         }
@@ -261,7 +259,6 @@ impl HttpRequestBuilder {
     }
 
     fn get_default_cookies() -> HashMap<Box<str>, Box<str>> {
-        let cookies = HashMap::new();
-        cookies
+        HashMap::new()
     }
 }
@@ -29,15 +29,12 @@ impl HttpResponse {
     /// let response = HttpResponse::new(200, Some("{\"name\":\"John\"}"), Some(headers));
     /// ```
     pub fn new(status_code: u16, body: Option<&str>, headers: Option<HashMap<&str, &str>>) -> Self {
-        let headers = match headers {
-            Some(headers) => Some(
-                headers
-                    .into_iter()
-                    .map(|(k, v)| (k.into(), v.into()))
-                    .collect(),
-            ),
-            None => None,
-        };
+        let headers = headers.map(|headers| {
+            headers
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect()
+        });
 
         Self {
             status_code,
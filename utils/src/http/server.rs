@@ -0,0 +1,188 @@
+//! A minimal HTTP/1.1 server side: enough to answer one JSON request per connection, for the
+//! local servers that stand in for real APIs in tests.
+
+use std::future::Future;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::errors::{Error, ErrorCode, ErrorMeta};
+use crate::json::JSON;
+
+/// The largest request body `serve` reads. Larger requests are answered with `413`.
+pub const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// A request read by `serve`.
+pub struct IncomingRequest {
+    method: Box<str>,
+    target: Box<str>,
+    body: String,
+}
+
+impl IncomingRequest {
+    /// The method as sent, e.g. `GET`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// The path of the request target, without the query string.
+    pub fn path(&self) -> &str {
+        self.target
+            .split_once('?')
+            .map_or(&self.target, |(path, _)| path)
+    }
+
+    /// The query string of the request target, empty if there is none.
+    pub fn query(&self) -> &str {
+        self.target.split_once('?').map_or("", |(_, query)| query)
+    }
+
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+}
+
+/// Reads a single request from `stream`, answers it with the JSON status and body `handler`
+/// returns, and closes the connection.
+///
+/// Requests whose `Content-Length` is not a number are answered with `400`, and those with a
+/// body over `MAX_BODY_BYTES` with `413`, both with the serialized `Error` and without calling
+/// `handler`.
+pub async fn serve<F, R>(mut stream: TcpStream, handler: F) -> std::io::Result<()>
+where
+    F: FnOnce(IncomingRequest) -> R,
+    R: Future<Output = (u16, String)>,
+{
+    let (reader, mut writer) = stream.split();
+    let mut reader = BufReader::new(reader);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().into();
+    let target = parts.next().unwrap_or_default().into();
+
+    let mut length = Ok(0);
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| value.trim().to_string());
+            }
+        }
+    }
+
+    let (status, body) = match length {
+        Err(value) => rejection(
+            400,
+            Error::new("Invalid Content-Length", ErrorCode::Invalid)
+                .with_meta(ErrorMeta::new().add("content-length", &value).build()),
+        ),
+        Ok(length) if length > MAX_BODY_BYTES => rejection(
+            413,
+            Error::new("Request body too large", ErrorCode::Invalid).with_meta(
+                ErrorMeta::new()
+                    .add("content-length", &length.to_string())
+                    .add("limit", &MAX_BODY_BYTES.to_string())
+                    .build(),
+            ),
+        ),
+        Ok(length) => {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await?;
+            let request = IncomingRequest {
+                method,
+                target,
+                body: String::from_utf8_lossy(&body).into_owned(),
+            };
+            handler(request).await
+        }
+    };
+
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    );
+    writer.write_all(response.as_bytes()).await?;
+    writer.shutdown().await
+}
+
+fn rejection(status: u16, error: Error) -> (u16, String) {
+    (status, error.to_json().unwrap_or_default())
+}
+
+/// The reason phrase for the status codes the servers answer with.
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Sends a raw request to `serve` with an echoing handler and returns the raw response.
+    async fn exchange(request: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            serve(stream, |request| async move {
+                let echo = format!("{} {} {}", request.method(), request.path(), request.body());
+                (200, echo)
+            })
+            .await
+        });
+
+        let mut client = TcpStream::connect(address).await.unwrap();
+        client.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        server.await.unwrap().unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let response =
+            exchange("POST /echo?depth=1 HTTP/1.1\r\nContent-Length: 4\r\n\r\nping").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nPOST /echo ping"));
+
+        let response = exchange("POST / HTTP/1.1\r\nContent-Length: lots\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(response.contains("Invalid Content-Length"));
+
+        let request = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_BYTES + 1
+        );
+        let response = exchange(&request).await;
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        assert!(response.contains("Request body too large"));
+    }
+}
@@ -130,7 +130,6 @@ use crate::errors::{Error, ErrorCode};
 ///
 /// # Example
 /// ```
-/// fn main() {
 /// use utils::json;
 ///
 /// let data = json!({
@@ -142,7 +141,6 @@ use crate::errors::{Error, ErrorCode};
 /// assert_eq!(data["name"], "John Doe");
 /// assert_eq!(data["age"], 30);
 /// assert_eq!(data["city"], "New York");
-/// }
 /// ```
 ///
 #[macro_export]
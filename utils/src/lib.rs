@@ -1,6 +1,20 @@
+#![allow(clippy::new_ret_no_self)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod adapters;
+#[cfg(feature = "std")]
 pub mod async_tools;
 pub mod errors;
+#[cfg(feature = "std")]
 pub mod http;
+#[cfg(feature = "std")]
 pub mod json;
+#[cfg(feature = "std")]
+pub mod runtime;
+#[cfg(feature = "std")]
+pub mod testing;
+#[cfg(feature = "std")]
 pub mod uuid;
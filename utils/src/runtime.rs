@@ -0,0 +1,405 @@
+//! # Runtime
+//!
+//! Runs a program's async entry point, which is what `#[macros::async_main]` expands to. On top
+//! of starting a Tokio runtime it:
+//!
+//! - logs `log` records to stderr, at the level in `RUST_LOG` or the configured default,
+//! - reports panics on one line per field instead of the default hook's message,
+//! - on Ctrl-C resolves `shutdown()` and gives the program a grace period to finish, exiting
+//!   as it would have if it does, and with status 130 if it is still running at the end of it
+//!   or Ctrl-C is pressed again,
+//! - reports a returned `Error` on stderr and exits with the status of its `ErrorCode`.
+//!
+//! ```no_run
+//! use utils::errors::Error;
+//! use utils::runtime::{self, Flavor, Runtime};
+//!
+//! fn main() -> std::process::ExitCode {
+//!     Runtime::new()
+//!         .flavor(Flavor::CurrentThread)
+//!         .build()
+//!         .run(async {
+//!             runtime::shutdown().await;
+//!             Ok::<(), Error>(())
+//!         })
+//! }
+//! ```
+
+use std::future::Future;
+use std::io::Write;
+use std::process::ExitCode;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use log::{Log, Metadata, Record};
+use tokio::sync::watch;
+
+pub use log::LevelFilter;
+
+use crate::errors::{Error, ErrorCode};
+
+const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+const DEFAULT_SHUTDOWN_TIMEOUT_SECONDS: u64 = 5;
+/// The status shells use for a process stopped by SIGINT.
+const INTERRUPTED_EXIT_CODE: u8 = 130;
+
+/// Whether shutdown has been requested, set once by the first Ctrl-C.
+static SHUTDOWN: LazyLock<watch::Sender<bool>> = LazyLock::new(|| watch::channel(false).0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Flavor {
+    CurrentThread,
+    #[default]
+    MultiThread,
+}
+
+/// What an entry point may return: nothing, or a `Result` whose error converts into `Error`.
+pub trait Outcome {
+    fn into_result(self) -> Result<(), Error>;
+}
+
+impl Outcome for () {
+    fn into_result(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<E: Into<Error>> Outcome for Result<(), E> {
+    fn into_result(self) -> Result<(), Error> {
+        self.map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Runtime {
+    flavor: Flavor,
+    worker_threads: Option<usize>,
+    log_level: LevelFilter,
+    shutdown_timeout: Duration,
+}
+
+impl Runtime {
+    pub fn new() -> RuntimeBuilder {
+        RuntimeBuilder::new()
+    }
+
+    /// Blocks on `main` and returns the status the process should exit with.
+    pub fn run<F>(self, main: F) -> ExitCode
+    where
+        F: Future,
+        F::Output: Outcome,
+    {
+        init_logging(self.log_level);
+        install_panic_hook();
+
+        let mut builder = match self.flavor {
+            Flavor::CurrentThread => tokio::runtime::Builder::new_current_thread(),
+            Flavor::MultiThread => tokio::runtime::Builder::new_multi_thread(),
+        };
+        if let Some(worker_threads) = self.worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        let runtime = match builder.enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(err) => {
+                return report(
+                    &Error::permanent("Failed to start the async runtime", ErrorCode::Internal)
+                        .with_cause(err),
+                )
+            }
+        };
+
+        let outcome = runtime.block_on(supervise(main, self.shutdown_timeout, ctrl_c, &SHUTDOWN));
+        match outcome {
+            Some(Ok(())) => ExitCode::SUCCESS,
+            Some(Err(error)) => report(&error),
+            None => ExitCode::from(INTERRUPTED_EXIT_CODE),
+        }
+    }
+}
+
+/// Resolves once the program has been asked to shut down by Ctrl-C, at once if it already has.
+///
+/// Long-running programs select on it to stop their work and return within the shutdown
+/// timeout.
+pub async fn shutdown() {
+    let mut requested = SHUTDOWN.subscribe();
+    // The sender is static, so the channel never closes.
+    let _ = requested.wait_for(|requested| *requested).await;
+}
+
+/// Runs `main` to completion, or until `interrupt` resolves twice or `shutdown_timeout` after
+/// it first resolved, setting `shutdown` then. Returns `None` if `main` was still running.
+async fn supervise<F, I, S>(
+    main: F,
+    shutdown_timeout: Duration,
+    mut interrupt: I,
+    shutdown: &watch::Sender<bool>,
+) -> Option<Result<(), Error>>
+where
+    F: Future,
+    F::Output: Outcome,
+    I: FnMut() -> S,
+    S: Future<Output = ()>,
+{
+    tokio::pin!(main);
+    tokio::select! {
+        outcome = &mut main => return Some(outcome.into_result()),
+        _ = interrupt() => {}
+    }
+
+    shutdown.send_replace(true);
+    log::warn!(
+        "interrupted, waiting up to {:?} for shutdown, press Ctrl-C again to exit now",
+        shutdown_timeout
+    );
+    tokio::select! {
+        outcome = tokio::time::timeout(shutdown_timeout, main) => {
+            outcome.ok().map(Outcome::into_result)
+        }
+        _ = interrupt() => None,
+    }
+}
+
+/// The next Ctrl-C. Never resolves if the signal handler cannot be installed.
+async fn ctrl_c() {
+    if tokio::signal::ctrl_c().await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Writes the error to stderr, a field per line, and returns the status for its code.
+fn report(error: &Error) -> ExitCode {
+    eprint!("{}", describe(error));
+    ExitCode::from(error.code().exit_code())
+}
+
+/// The report of an error: its message, code and transience, then its metadata sorted by key
+/// and its cause.
+fn describe(error: &Error) -> String {
+    let mut report = format!(
+        "error: {}\n  code: {}\n  transient: {}\n",
+        error.message(),
+        error.code(),
+        error.is_transient()
+    );
+    if let Some(meta) = error.meta() {
+        let mut meta: Vec<_> = meta.iter().collect();
+        meta.sort();
+        for (key, value) in meta {
+            report.push_str(&format!("  {}: {}\n", key, value));
+        }
+    }
+    if let Some(cause) = error.source() {
+        report.push_str(&format!("  cause: {}\n", cause));
+    }
+    report
+}
+
+fn init_logging(default_level: LevelFilter) {
+    let level = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(default_level);
+    // Another logger may already be installed, e.g. by an embedding application.
+    if log::set_logger(&StderrLogger).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+fn install_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+        let thread = std::thread::current();
+
+        let mut report = format!(
+            "panic: {}\n  thread: {}\n",
+            message,
+            thread.name().unwrap_or("<unnamed>")
+        );
+        if let Some(location) = info.location() {
+            report.push_str(&format!("  location: {}\n", location));
+        }
+        let backtrace = std::backtrace::Backtrace::capture();
+        if backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+            report.push_str(&format!("  backtrace:\n{}\n", backtrace));
+        }
+        eprint!("{}", report);
+    }));
+}
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "[{}] {}: {}",
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+pub struct RuntimeBuilder {
+    flavor: Flavor,
+    worker_threads: Option<usize>,
+    log_level: LevelFilter,
+    shutdown_timeout: Duration,
+}
+
+impl Default for RuntimeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RuntimeBuilder {
+    pub fn new() -> Self {
+        Self {
+            flavor: Flavor::default(),
+            worker_threads: None,
+            log_level: DEFAULT_LOG_LEVEL,
+            shutdown_timeout: Duration::from_secs(DEFAULT_SHUTDOWN_TIMEOUT_SECONDS),
+        }
+    }
+
+    pub fn flavor(mut self, flavor: Flavor) -> Self {
+        self.flavor = flavor;
+        self
+    }
+
+    /// The number of worker threads of a multi-threaded runtime. Defaults to one per core.
+    pub fn worker_threads(mut self, worker_threads: usize) -> Self {
+        self.worker_threads = Some(worker_threads);
+        self
+    }
+
+    /// The log level used when `RUST_LOG` is not set.
+    pub fn log_level(mut self, log_level: LevelFilter) -> Self {
+        self.log_level = log_level;
+        self
+    }
+
+    /// How long the program may keep running after Ctrl-C. Defaults to five seconds.
+    pub fn shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    pub fn build(self) -> Runtime {
+        Runtime {
+            flavor: self.flavor,
+            worker_threads: self.worker_threads,
+            log_level: self.log_level,
+            shutdown_timeout: self.shutdown_timeout,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+
+    use super::*;
+    use crate::errors::ErrorMeta;
+
+    fn current_thread() -> Runtime {
+        Runtime::new().flavor(Flavor::CurrentThread).build()
+    }
+
+    /// Ctrl-C pressed `times` times in a row, then never again.
+    fn ctrl_c(times: usize) -> impl FnMut() -> Pin<Box<dyn Future<Output = ()>>> {
+        let mut remaining = times;
+        move || {
+            if remaining == 0 {
+                return Box::pin(std::future::pending());
+            }
+            remaining -= 1;
+            Box::pin(std::future::ready(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_supervise() {
+        let forever = Duration::from_secs(3600);
+        let (shutdown, requested) = watch::channel(false);
+        let outcome = supervise(async {}, forever, ctrl_c(0), &shutdown).await;
+        assert!(matches!(outcome, Some(Ok(()))));
+        assert!(!*requested.borrow());
+
+        // A program that stops when asked exits as it would have.
+        let mut stop = requested.clone();
+        let main = async move {
+            stop.wait_for(|requested| *requested).await.unwrap();
+            Err::<(), _>(Error::new("stopped", ErrorCode::Conflict))
+        };
+        let outcome = supervise(main, forever, ctrl_c(1), &shutdown).await;
+        assert_eq!(outcome.unwrap().unwrap_err().message(), "stopped");
+        assert!(*requested.borrow());
+
+        // One that does not is cut off by the timeout or a second Ctrl-C.
+        let (shutdown, _) = watch::channel(false);
+        let never = std::future::pending::<()>;
+        let timeout = Duration::from_millis(10);
+        assert!(supervise(never(), timeout, ctrl_c(1), &shutdown)
+            .await
+            .is_none());
+        assert!(supervise(never(), forever, ctrl_c(2), &shutdown)
+            .await
+            .is_none());
+    }
+
+    #[test]
+    fn test_report() {
+        let error = Error::permanent("Order not found", ErrorCode::NotFound)
+            .with_meta(
+                ErrorMeta::new()
+                    .add("id", "7")
+                    .add("book", "BTC/USD")
+                    .build(),
+            )
+            .with_cause(std::io::Error::other("disk on fire"));
+        assert_eq!(
+            describe(&error),
+            "error: Order not found\n  code: not_found\n  transient: false\n  book: BTC/USD\n  \
+             id: 7\n  cause: disk on fire\n"
+        );
+        assert_eq!(report(&error), ExitCode::from(66));
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(current_thread().run(async {}), ExitCode::SUCCESS);
+        assert_eq!(
+            current_thread().run(async { Ok::<(), Error>(()) }),
+            ExitCode::SUCCESS
+        );
+        assert_eq!(
+            current_thread().run(async { Err(Error::new("missing", ErrorCode::NotFound)) }),
+            ExitCode::from(66)
+        );
+        assert_eq!(
+            Runtime::new()
+                .worker_threads(2)
+                .build()
+                .run(async { Err(Error::new("late", ErrorCode::Timeout)) }),
+            ExitCode::from(75)
+        );
+    }
+}
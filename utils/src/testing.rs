@@ -0,0 +1,235 @@
+//! # Testing
+//!
+//! Support for `#[macros::async_test]`: the timeout wrapper its tests run in, and the
+//! `Fixture`s it sets up for test arguments.
+//!
+//! ```ignore
+//! use utils::adapters::http_client::HttpClientPool;
+//! use utils::http::HttpMethod;
+//! use utils::testing::MockServer;
+//!
+//! #[macros::async_test(timeout = 5)]
+//! async fn test_ticker(server: MockServer, pool: HttpClientPool) {
+//!     server.mock(HttpMethod::GET, "/ticker", 200, r#"{"price":"30000"}"#);
+//!     // ... requests to `server.url()` answer with the body above.
+//! }
+//! ```
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::adapters::http_client::HttpClientPool;
+use crate::errors::{Error, ErrorCode, ErrorMeta};
+use crate::http::server::{self, IncomingRequest};
+use crate::http::HttpMethod;
+use crate::runtime::Outcome;
+
+/// A value `#[macros::async_test]` creates for each test argument of its type.
+pub trait Fixture: Sized {
+    fn setup() -> impl Future<Output = Result<Self, Error>>;
+}
+
+/// A fresh pool with the default number of clients.
+impl Fixture for HttpClientPool {
+    async fn setup() -> Result<Self, Error> {
+        Ok(HttpClientPool::new())
+    }
+}
+
+/// Shared fixtures, e.g. `Arc<Mutex<HttpClientPool>>` as the price oracle takes it.
+impl<T: Fixture> Fixture for Arc<Mutex<T>> {
+    async fn setup() -> Result<Self, Error> {
+        Ok(Arc::new(Mutex::new(T::setup().await?)))
+    }
+}
+
+impl Fixture for MockServer {
+    async fn setup() -> Result<Self, Error> {
+        MockServer::start().await
+    }
+}
+
+/// Runs a test body, failing with `ErrorCode::Timeout` if it takes longer than `timeout`.
+pub async fn run<F>(test: &str, timeout: Option<Duration>, body: F) -> Result<(), Error>
+where
+    F: Future,
+    F::Output: Outcome,
+{
+    let Some(timeout) = timeout else {
+        return body.await.into_result();
+    };
+    match tokio::time::timeout(timeout, body).await {
+        Ok(outcome) => outcome.into_result(),
+        Err(_) => Err(Error::new("Test timed out", ErrorCode::Timeout).with_meta(
+            ErrorMeta::new()
+                .add("test", test)
+                .add("timeout", &format!("{:?}", timeout))
+                .build(),
+        )),
+    }
+}
+
+struct Route {
+    method: HttpMethod,
+    path: Box<str>,
+    status: u16,
+    body: Box<str>,
+    hits: usize,
+}
+
+/// A local HTTP/1.1 server answering with canned responses, standing in for a provider's API.
+///
+/// Requests are matched on method and path, ignoring the query string; the latest `mock` for a
+/// route wins. Anything else is answered with `404`. The server stops when it is dropped.
+pub struct MockServer {
+    address: SocketAddr,
+    routes: Arc<Mutex<Vec<Route>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Starts serving on a free local port.
+    ///
+    /// Fails with `ErrorCode::Unavailable` if no port can be bound.
+    pub async fn start() -> Result<Self, Error> {
+        let unavailable = |err: std::io::Error| {
+            Error::new("Failed to bind mock server", ErrorCode::Unavailable).with_cause(err)
+        };
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(unavailable)?;
+        let address = listener.local_addr().map_err(unavailable)?;
+        let routes: Arc<Mutex<Vec<Route>>> = Arc::default();
+
+        let task = tokio::spawn({
+            let routes = routes.clone();
+            async move {
+                loop {
+                    let Ok((stream, _)) = listener.accept().await else {
+                        continue;
+                    };
+                    let routes = routes.clone();
+                    tokio::spawn(server::serve(stream, move |request| async move {
+                        respond(&routes, &request)
+                    }));
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            routes,
+            task,
+        })
+    }
+
+    /// The base URL of the server, e.g. `http://127.0.0.1:40123`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// Answers requests for `method` and `path` with `status` and a JSON `body`.
+    pub fn mock(&self, method: HttpMethod, path: &str, status: u16, body: &str) -> &Self {
+        let mut routes = self.routes();
+        routes.retain(|route| route.method != method || *route.path != *path);
+        routes.push(Route {
+            method,
+            path: path.into(),
+            status,
+            body: body.into(),
+            hits: 0,
+        });
+        drop(routes);
+        self
+    }
+
+    /// How many requests the route for `method` and `path` has answered.
+    pub fn hits(&self, method: HttpMethod, path: &str) -> usize {
+        self.routes()
+            .iter()
+            .find(|route| route.method == method && *route.path == *path)
+            .map_or(0, |route| route.hits)
+    }
+
+    fn routes(&self) -> std::sync::MutexGuard<'_, Vec<Route>> {
+        self.routes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The canned response for a request, counting the hit.
+fn respond(routes: &Mutex<Vec<Route>>, request: &IncomingRequest) -> (u16, String) {
+    let mut routes = routes
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    match routes.iter_mut().find(|route| {
+        method_name(&route.method) == request.method() && *route.path == *request.path()
+    }) {
+        Some(route) => {
+            route.hits += 1;
+            (route.status, route.body.to_string())
+        }
+        None => (404, String::new()),
+    }
+}
+
+fn method_name(method: &HttpMethod) -> &'static str {
+    match method {
+        HttpMethod::GET => "GET",
+        HttpMethod::POST => "POST",
+        HttpMethod::PUT => "PUT",
+        HttpMethod::DELETE => "DELETE",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::http_client::HttpClient;
+    use crate::http::HttpRequest;
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let error = run("slow", Some(Duration::from_millis(10)), async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Timeout);
+        assert_eq!(error.meta_value("test"), Some("slow"));
+
+        assert!(run("fast", Some(Duration::from_secs(5)), async {})
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_mock_server() {
+        let server = MockServer::start().await.unwrap();
+        server.mock(HttpMethod::GET, "/ticker", 200, r#"{"price":"1"}"#);
+        let client = HttpClient::new().build(0);
+
+        let url = format!("{}/ticker?pair=BTC-USD", server.url());
+        let request = HttpRequest::new(&url, HttpMethod::GET).build();
+        let response = client.send_request(Arc::new(request)).await.unwrap();
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.body().as_deref(), Some(r#"{"price":"1"}"#));
+        assert_eq!(server.hits(HttpMethod::GET, "/ticker"), 1);
+
+        let request = HttpRequest::new(&server.url(), HttpMethod::POST).build();
+        let response = client.send_request(Arc::new(request)).await.unwrap();
+        assert_eq!(response.status_code(), 404);
+    }
+}